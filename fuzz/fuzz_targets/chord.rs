@@ -0,0 +1,11 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use vscode_cjk_toggle_terminal_fixer::hotkey::Chord;
+
+// note: a parsed chord is printed back, e.g. in the tray menu, so that has to parse again.
+fuzz_target!(|s: &str| {
+    if let Ok(chord) = s.parse::<Chord>() {
+        assert_eq!(chord.to_string().parse::<Chord>().ok(), Some(chord));
+    }
+});
@@ -0,0 +1,9 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use vscode_cjk_toggle_terminal_fixer::config::Config;
+
+// note: a malformed config file must come back as an error, it's read before anything is logged.
+fuzz_target!(|text: &str| {
+    let _ = Config::parse(text);
+});
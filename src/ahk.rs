@@ -0,0 +1,98 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use anyhow::{Context, Result};
+use windows::Win32::UI::{
+    Input::KeyboardAndMouse::{MOD_ALT, MOD_CONTROL, MOD_SHIFT, MOD_WIN},
+    WindowsAndMessaging::{WM_KEYDOWN, WM_KEYUP},
+};
+
+use crate::{
+    config::Config,
+    hotkey::{self, Hotkey},
+    inject, PACKAGE_NAME,
+};
+
+/// the script is written next to the executable, e.g. `vscode-cjk-toggle-terminal-fixer.ahk`.
+pub fn path(app_path: &Path) -> PathBuf {
+    app_path.with_extension("ahk")
+}
+
+/// renders the hotkeys of the config into an AutoHotkey v2 script, which posts the keys to the
+/// focused window like the "post_message" backend.
+///
+/// note: the other backends and the window features have no equivalent in the script.
+pub fn script(config: &Config) -> String {
+    let mut windows = vec![r#"WinActive(" - (Visual Studio Code|VS Code)\s*$")"#.to_owned()];
+    windows.extend(
+        config
+            .target_processes
+            .iter()
+            .map(|name| format!(r#"WinActive("ahk_exe {name}")"#)),
+    );
+
+    let mut script = format!(
+        "; generated by {PACKAGE_NAME}, the hotkeys post their keys so the IME can't swallow them.\n\
+         #Requires AutoHotkey v2.0\n\
+         #SingleInstance Force\n\
+         SetTitleMatchMode \"RegEx\"\n\
+         \n\
+         #HotIf {}\n",
+        windows.join(" || ")
+    );
+    for hotkey in hotkey::table(config) {
+        script.push_str(&render(&hotkey));
+    }
+    script.push_str("#HotIf\n");
+    script
+}
+
+fn render(hotkey: &Hotkey) -> String {
+    let action = toml::Value::try_from(hotkey.action)
+        .map(|action| action.to_string())
+        .unwrap_or_default();
+    if let Some(then) = hotkey.then {
+        return format!(
+            "; skipped {} {then} = {action}, AutoHotkey has no two-step hotkeys.\n\n",
+            hotkey.chord
+        );
+    }
+
+    let modifiers: String = [
+        (MOD_CONTROL, '^'),
+        (MOD_SHIFT, '+'),
+        (MOD_ALT, '!'),
+        (MOD_WIN, '#'),
+    ]
+    .into_iter()
+    .filter(|(modifier, _)| hotkey.chord.modifiers.0 & modifier.0 != 0)
+    .map(|(_, symbol)| symbol)
+    .collect();
+    // note: the virtual key code is independent of the keyboard layout, unlike the key's name.
+    let mut text = format!(
+        "; {} = {action}\n{modifiers}vk{:02X}::\n{{\n",
+        hotkey.chord, hotkey.chord.vk.0
+    );
+    for vk in hotkey.keys() {
+        let lparam = inject::key_lparam(vk).0;
+        for message in [WM_KEYDOWN, WM_KEYUP] {
+            text.push_str(&format!(
+                "    PostMessage {message:#X}, {:#X}, {lparam:#X},, \"A\"\n",
+                vk.0
+            ));
+        }
+    }
+    text.push_str("}\n\n");
+    text
+}
+
+/// writes the script for the current config next to the executable, returns its path.
+pub fn export(app_path: &Path) -> Result<PathBuf> {
+    let config = Config::load(&Config::path(app_path))?;
+    let path = path(app_path);
+    fs::write(&path, script(&config))
+        .with_context(|| format!("failed to write script: {path:?}"))?;
+    Ok(path)
+}
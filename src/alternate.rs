@@ -0,0 +1,67 @@
+use std::path::Path;
+
+use anyhow::{Context, Result};
+use windows::Win32::UI::Input::KeyboardAndMouse::{MOD_CONTROL, VK_J};
+
+use crate::{
+    config::Config,
+    hotkey::{self, Chord, ChordRule},
+    inject::Action,
+};
+
+/// a hotkey commonly bound next to or instead of the terminal toggle, switched on and off from the
+/// tray.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Alternate {
+    TogglePanel,
+    NewTerminal,
+}
+
+pub const ALTERNATES: [Alternate; 2] = [Alternate::TogglePanel, Alternate::NewTerminal];
+
+const CTRL_J: Chord = Chord {
+    modifiers: MOD_CONTROL,
+    vk: VK_J,
+};
+
+impl Alternate {
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::TogglePanel => "Also Fix Ctrl+J (Toggle Panel)",
+            Self::NewTerminal => "Also Fix Ctrl+~ (New Terminal)",
+        }
+    }
+
+    pub fn is_enabled(self, config: &Config) -> bool {
+        match self {
+            Self::TogglePanel => config.chords.contains(&toggle_panel()),
+            Self::NewTerminal => config.new_terminal_hotkey,
+        }
+    }
+}
+
+fn toggle_panel() -> ChordRule {
+    ChordRule {
+        keys: vec![CTRL_J],
+        action: Action::TogglePanel,
+        enabled: true,
+    }
+}
+
+/// adds or removes the hotkey in the config file, keeping the user's other keys and comments.
+pub fn set(path: &Path, alternate: Alternate, enabled: bool) -> Result<()> {
+    let values = match alternate {
+        Alternate::TogglePanel => {
+            let rule = toggle_panel();
+            let mut chords = Config::load(path)?.chords;
+            chords.retain(|chord| !chord.is_same(&rule));
+            if enabled {
+                chords.push(rule);
+            }
+            hotkey::chords_value(&chords)
+        }
+        // note: 「Ctrl+~」 is 「Ctrl+Shift+`」, as 「~」 is 「Shift+`」 on the US layout.
+        Alternate::NewTerminal => format!("new_terminal_hotkey = {enabled}"),
+    };
+    Config::update(path, &values).with_context(|| format!("failed to switch {alternate:?}"))
+}
@@ -0,0 +1,103 @@
+use anyhow::{bail, ensure, Context, Result};
+use windows::Win32::Foundation::SYSTEMTIME;
+
+/// the signature of a local file header.
+const LOCAL_HEADER: u32 = 0x04034b50;
+
+/// an uncompressed zip archive of the entries, plain enough to write by hand.
+pub fn zip(entries: &[(impl AsRef<str>, Vec<u8>)], time: &SYSTEMTIME) -> Vec<u8> {
+    let dos_time = (time.wHour << 11) | (time.wMinute << 5) | (time.wSecond / 2);
+    let dos_date = ((time.wYear.saturating_sub(1980)) << 9) | (time.wMonth << 5) | time.wDay;
+    // note: the names are UTF-8.
+    const FLAGS: u16 = 1 << 11;
+    const VERSION: u16 = 20;
+
+    let mut archive = Vec::new();
+    let mut directory = Vec::new();
+    for (name, data) in entries {
+        let name = name.as_ref();
+        let offset = archive.len() as u32;
+        let crc = crc32(data);
+        // note: the fields the local header and the central directory share.
+        let mut common = Vec::new();
+        common.extend(FLAGS.to_le_bytes());
+        common.extend(0u16.to_le_bytes()); // stored
+        common.extend(dos_time.to_le_bytes());
+        common.extend(dos_date.to_le_bytes());
+        common.extend(crc.to_le_bytes());
+        common.extend((data.len() as u32).to_le_bytes());
+        common.extend((data.len() as u32).to_le_bytes());
+        common.extend((name.len() as u16).to_le_bytes());
+        common.extend(0u16.to_le_bytes()); // extra field
+
+        archive.extend(LOCAL_HEADER.to_le_bytes());
+        archive.extend(VERSION.to_le_bytes());
+        archive.extend(&common);
+        archive.extend(name.as_bytes());
+        archive.extend(data);
+
+        directory.extend(0x02014b50u32.to_le_bytes());
+        directory.extend(VERSION.to_le_bytes()); // made by
+        directory.extend(VERSION.to_le_bytes()); // needed
+        directory.extend(&common);
+        directory.extend([0u8; 10]); // comment, disk, internal and external attributes
+        directory.extend(offset.to_le_bytes());
+        directory.extend(name.as_bytes());
+    }
+
+    let directory_offset = archive.len() as u32;
+    let count = entries.len() as u16;
+    archive.extend(&directory);
+    archive.extend(0x06054b50u32.to_le_bytes());
+    archive.extend([0u8; 4]); // disks
+    archive.extend(count.to_le_bytes());
+    archive.extend(count.to_le_bytes());
+    archive.extend((directory.len() as u32).to_le_bytes());
+    archive.extend(directory_offset.to_le_bytes());
+    archive.extend(0u16.to_le_bytes()); // comment
+    archive
+}
+
+/// the entries of an archive written by `zip`, in order.
+///
+/// note: only stored entries are read, a zip tool compresses them when repacking.
+pub fn unzip(archive: &[u8]) -> Result<Vec<(String, Vec<u8>)>> {
+    let mut entries = Vec::new();
+    let mut rest = archive;
+    while rest.len() >= 4 && u32::from_le_bytes(bytes(rest, 0)) == LOCAL_HEADER {
+        let header = rest.get(..30).context("truncated zip entry")?;
+        let flags = u16::from_le_bytes(bytes(header, 6));
+        let method = u16::from_le_bytes(bytes(header, 8));
+        let crc = u32::from_le_bytes(bytes(header, 14));
+        let size = u32::from_le_bytes(bytes(header, 18)) as usize;
+        let name_len = u16::from_le_bytes(bytes(header, 26)) as usize;
+        let extra_len = u16::from_le_bytes(bytes(header, 28)) as usize;
+        let name = rest.get(30..30 + name_len).context("truncated zip entry")?;
+        let name = String::from_utf8_lossy(name).into_owned();
+        // note: a data descriptor means the sizes follow the data, only known once decompressed.
+        if method != 0 || flags & 0b1000 != 0 {
+            bail!("{name:?} is compressed, only archives written by --backup can be restored");
+        }
+        let start = 30 + name_len + extra_len;
+        let data = rest
+            .get(start..start + size)
+            .with_context(|| format!("{name:?} is truncated"))?;
+        ensure!(crc32(data) == crc, "{name:?} is corrupt");
+        entries.push((name, data.to_vec()));
+        rest = &rest[start + size..];
+    }
+    Ok(entries)
+}
+
+/// the little-endian field at the offset, which the caller checked to be in bounds.
+fn bytes<const N: usize>(data: &[u8], offset: usize) -> [u8; N] {
+    std::array::from_fn(|i| data[offset + i])
+}
+
+fn crc32(data: &[u8]) -> u32 {
+    !data.iter().fold(!0u32, |crc, &byte| {
+        (0..8).fold(crc ^ byte as u32, |crc, _| {
+            (crc >> 1) ^ (0xEDB88320 & (crc & 1).wrapping_neg())
+        })
+    })
+}
@@ -0,0 +1,100 @@
+use anyhow::{ensure, Context, Result};
+#[cfg(feature = "autolaunch")]
+use auto_launch::{AutoLaunch, AutoLaunchBuilder};
+use windows::{
+    core::{HSTRING, PWSTR},
+    ApplicationModel::{StartupTask, StartupTaskState},
+    Win32::{
+        Foundation::APPMODEL_ERROR_NO_PACKAGE, Storage::Packaging::Appx::GetCurrentPackageFullName,
+    },
+};
+
+use crate::PACKAGE_NAME;
+
+/// launches us on logon, via the Run key or, when running from an MSIX package, the package's
+/// startup task as packaged apps can't use the Run key.
+pub enum Autostart {
+    #[cfg(feature = "autolaunch")]
+    RunKey(AutoLaunch),
+    /// note: the manifest must declare a `uap5:StartupTask` whose `TaskId` is the package name.
+    StartupTask(StartupTask),
+}
+
+impl Autostart {
+    /// `None` outside a package in builds without the "autolaunch" feature.
+    pub fn new(app_path: &str) -> Result<Option<Self>> {
+        if is_packaged() {
+            let task = StartupTask::GetAsync(&HSTRING::from(PACKAGE_NAME))?
+                .get()
+                .context("no startup task declared in the package manifest")?;
+            return Ok(Some(Self::StartupTask(task)));
+        }
+
+        #[cfg(feature = "autolaunch")]
+        return Ok(Some(Self::RunKey(
+            AutoLaunchBuilder::new()
+                .set_app_name(PACKAGE_NAME)
+                .set_app_path(app_path)
+                .build()?,
+        )));
+        #[cfg(not(feature = "autolaunch"))]
+        {
+            let _ = app_path;
+            Ok(None)
+        }
+    }
+
+    /// the name of the mechanism, e.g. for `--status`.
+    pub fn backend(&self) -> &'static str {
+        match self {
+            #[cfg(feature = "autolaunch")]
+            Self::RunKey(_) => "run_key",
+            Self::StartupTask(_) => "startup_task",
+        }
+    }
+
+    pub fn is_enabled(&self) -> Result<bool> {
+        match self {
+            #[cfg(feature = "autolaunch")]
+            Self::RunKey(auto_launch) => Ok(auto_launch.is_enabled()?),
+            Self::StartupTask(task) => Ok(matches!(
+                task.State()?,
+                StartupTaskState::Enabled | StartupTaskState::EnabledByPolicy
+            )),
+        }
+    }
+
+    pub fn enable(&self) -> Result<()> {
+        match self {
+            #[cfg(feature = "autolaunch")]
+            Self::RunKey(auto_launch) => Ok(auto_launch.enable()?),
+            Self::StartupTask(task) => {
+                // note: the user or a policy may refuse, which is reported as the resulting state.
+                let state = task.RequestEnableAsync()?.get()?;
+                ensure!(
+                    matches!(
+                        state,
+                        StartupTaskState::Enabled | StartupTaskState::EnabledByPolicy
+                    ),
+                    "the startup task wasn't enabled: {state:?}"
+                );
+                Ok(())
+            }
+        }
+    }
+
+    pub fn disable(&self) -> Result<()> {
+        match self {
+            #[cfg(feature = "autolaunch")]
+            Self::RunKey(auto_launch) => Ok(auto_launch.disable()?),
+            Self::StartupTask(task) => Ok(task.Disable()?),
+        }
+    }
+}
+
+/// whether we run with a package identity, i.e. installed from MSIX.
+pub fn is_packaged() -> bool {
+    let mut length = 0;
+    let result = unsafe { GetCurrentPackageFullName(&mut length, PWSTR::null()) };
+    !matches!(result, Err(err) if err.code() == APPMODEL_ERROR_NO_PACKAGE.to_hresult())
+}
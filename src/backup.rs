@@ -0,0 +1,109 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use anyhow::{bail, ensure, Context, Result};
+#[allow(unused_imports)]
+use tracing::{debug, error, info, trace, warn};
+use windows::Win32::System::SystemInformation::GetLocalTime;
+
+use crate::{archive, config::Config, instance, state::State};
+
+/// the entry holding the config file.
+const CONFIG: &str = "config.toml";
+
+/// the entry holding the state file.
+const STATE: &str = "state.toml";
+
+/// the folder of the entries holding the icon pack.
+const ICONS: &str = "icons/";
+
+/// zips the config, the state and the icon pack, whichever exist, e.g. before reinstalling Windows.
+pub fn create(app_path: &Path, path: &Path) -> Result<()> {
+    let config_path = Config::path(app_path);
+    let mut entries = Vec::new();
+    for (name, file) in [(CONFIG, &config_path), (STATE, &State::path(app_path))] {
+        if file.exists() {
+            let data = fs::read(file).with_context(|| format!("failed to read {file:?}"))?;
+            entries.push((name.to_owned(), data));
+        }
+    }
+    if let Some(dir) = Config::load(&config_path)?.icon_pack {
+        let files = fs::read_dir(&dir).with_context(|| format!("failed to read {dir:?}"))?;
+        for file in files {
+            let file = file?.path();
+            let Some(name) = file.file_name().and_then(|name| name.to_str()) else {
+                continue;
+            };
+            if file.is_file() {
+                let data = fs::read(&file).with_context(|| format!("failed to read {file:?}"))?;
+                entries.push((format!("{ICONS}{name}"), data));
+            }
+        }
+    }
+    ensure!(
+        !entries.is_empty(),
+        "nothing to back up, there's no config file yet"
+    );
+
+    let time = unsafe { GetLocalTime() };
+    fs::write(path, archive::zip(&entries, &time))
+        .with_context(|| format!("failed to write {path:?}"))?;
+    info!("backed up {} files to {path:?}", entries.len());
+    Ok(())
+}
+
+/// writes the files of a backup back, the icon pack to where the restored config expects it.
+///
+/// note: the running instance would overwrite the state on exit, so it has to quit first, and
+/// holding the instance mutex keeps one from starting meanwhile.
+pub fn restore(app_path: &Path, path: &Path) -> Result<()> {
+    let Some(_instance) = instance::acquire()? else {
+        bail!("quit the running instance before restoring a backup");
+    };
+    let data = fs::read(path).with_context(|| format!("failed to read {path:?}"))?;
+    let entries = archive::unzip(&data).with_context(|| format!("invalid backup: {path:?}"))?;
+    ensure!(
+        entries.iter().any(|(name, _)| name == CONFIG),
+        "{path:?} has no config, it isn't a backup"
+    );
+
+    let config_path = Config::path(app_path);
+    let mut icon_pack = None;
+    for (name, data) in &entries {
+        let target = match name.as_str() {
+            CONFIG => config_path.clone(),
+            STATE => State::path(app_path),
+            name => match name.strip_prefix(ICONS) {
+                // note: the icon pack's folder is known once the config was restored, it comes first.
+                Some(icon) => {
+                    let dir = icon_pack.get_or_insert_with(|| icon_pack_dir(&config_path));
+                    let Some(dir) = dir else {
+                        warn!("skipped {name:?}, the restored config has no icon pack");
+                        continue;
+                    };
+                    fs::create_dir_all(&*dir)
+                        .with_context(|| format!("failed to create {dir:?}"))?;
+                    // note: only the file name, an entry mustn't write outside the folder.
+                    let Some(icon) = Path::new(icon).file_name() else {
+                        continue;
+                    };
+                    dir.join(icon)
+                }
+                None => {
+                    warn!("skipped {name:?}, it's unknown");
+                    continue;
+                }
+            },
+        };
+        fs::write(&target, data).with_context(|| format!("failed to write {target:?}"))?;
+        info!("restored {target:?}");
+    }
+    Ok(())
+}
+
+/// the icon pack's folder of the config file just restored.
+fn icon_pack_dir(config_path: &Path) -> Option<PathBuf> {
+    Config::load(config_path).ok()?.icon_pack
+}
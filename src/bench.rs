@@ -0,0 +1,153 @@
+use std::{
+    cell::Cell,
+    io::{self, Write},
+    time::{Duration, Instant},
+};
+
+use anyhow::{bail, ensure, Context, Result};
+#[allow(unused_imports)]
+use tracing::{debug, error, info, trace, warn};
+use windows::{
+    core::{w, PCWSTR},
+    Win32::{
+        Foundation::{HWND, LPARAM, LRESULT, WPARAM},
+        System::{LibraryLoader::GetModuleHandleW, Threading::GetCurrentThreadId},
+        UI::WindowsAndMessaging::{
+            CreateWindowExW, DefWindowProcW, DestroyWindow, DispatchMessageW, GetMessageW,
+            PostThreadMessageW, RegisterClassW, HMENU, HWND_MESSAGE, MSG, WINDOW_EX_STYLE,
+            WINDOW_STYLE, WM_HOTKEY, WM_KEYUP, WNDCLASSW,
+        },
+    },
+};
+
+use crate::{
+    config::Config,
+    hotkey::{self, Hotkey},
+    inject::{Action, Injector, Posted, Target},
+    LogExt, LATENCY_BUDGET,
+};
+
+/// how many hotkeys `--bench` fires.
+const SAMPLES: usize = 1000;
+
+thread_local! {
+    /// the key whose release completes the injection, and when the target window got it.
+    static LAST_KEY: Cell<WPARAM> = const { Cell::new(WPARAM(0)) };
+    static RECEIVED: Cell<Option<Instant>> = const { Cell::new(None) };
+}
+
+/// fires `WM_HOTKEY` at our own message pump many times, injecting the default toggle hotkey into
+/// a window of ours each time, and fails if the 95th percentile of the time from firing to the
+/// window receiving the keys is over the latency budget.
+///
+/// note: the default config rather than the user's, so the numbers of two builds compare.
+pub fn run() -> Result<()> {
+    let config = Config::default();
+    let hotkey = hotkey::table(&config)
+        .into_iter()
+        .find(|hotkey| hotkey.action == Action::ToggleTerminal && hotkey.then.is_none())
+        .context("the default config has no toggle hotkey")?;
+    let hwnd = create_target()?;
+    let latencies = measure(&config, hwnd, &hotkey);
+    unsafe { DestroyWindow(hwnd) }.warn();
+    let mut latencies = latencies?;
+    latencies.sort();
+
+    let percentile = |p: usize| latencies[(latencies.len() * p / 100).min(latencies.len() - 1)];
+    let p95 = percentile(95);
+    let text = format!(
+        "{SAMPLES} hotkeys injected via PostMessage\np50: {:?}\np95: {p95:?}\nmax: {:?}\n",
+        percentile(50),
+        latencies[latencies.len() - 1],
+    );
+    io::stdout()
+        .write_all(text.as_bytes())
+        .context("failed to write the standard output")?;
+    ensure!(
+        p95 <= LATENCY_BUDGET,
+        "the 95th percentile of {p95:?} is over the budget of {LATENCY_BUDGET:?}"
+    );
+    Ok(())
+}
+
+fn measure(config: &Config, hwnd: HWND, hotkey: &Hotkey) -> Result<Vec<Duration>> {
+    let keys: Vec<_> = hotkey.keys().collect();
+    let last = keys.last().context("the hotkey has no key")?;
+    LAST_KEY.with(|key| key.set(WPARAM(last.0 as usize)));
+    let target = Target {
+        config,
+        hwnd,
+        hotkey,
+        keys: &keys,
+    };
+    let thread = unsafe { GetCurrentThreadId() };
+    let mut latencies = Vec::with_capacity(SAMPLES);
+    let mut msg = MSG::default();
+    for _ in 0..SAMPLES {
+        RECEIVED.with(|received| received.set(None));
+        let fired = Instant::now();
+        unsafe { PostThreadMessageW(thread, WM_HOTKEY, WPARAM(hotkey.id), LPARAM(0))? };
+        let received = loop {
+            if let Some(received) = RECEIVED.with(Cell::get) {
+                break received;
+            }
+            if !unsafe { GetMessageW(&mut msg, HWND(0), 0, 0) }.as_bool() {
+                bail!("the message pump quit");
+            }
+            match msg.message {
+                WM_HOTKEY if msg.hwnd == HWND(0) => Posted.inject(&target)?,
+                _ => unsafe {
+                    DispatchMessageW(&msg);
+                },
+            }
+        };
+        latencies.push(received - fired);
+    }
+    Ok(latencies)
+}
+
+/// a message-only window standing in for VSCode, it notes when it got the last key.
+fn create_target() -> Result<HWND> {
+    unsafe {
+        let instance = GetModuleHandleW(PCWSTR::null())?;
+        let class = WNDCLASSW {
+            lpfnWndProc: Some(on_message),
+            hInstance: instance.into(),
+            lpszClassName: w!("vscode-cjk-toggle-terminal-fixer-bench"),
+            ..Default::default()
+        };
+        ensure!(
+            RegisterClassW(&class) != 0,
+            "failed to register the window class: {}",
+            windows::core::Error::from_win32()
+        );
+        let hwnd = CreateWindowExW(
+            WINDOW_EX_STYLE(0),
+            class.lpszClassName,
+            PCWSTR::null(),
+            WINDOW_STYLE(0),
+            0,
+            0,
+            0,
+            0,
+            HWND_MESSAGE,
+            HMENU(0),
+            instance,
+            None,
+        );
+        ensure!(
+            hwnd != HWND(0),
+            "failed to create the message window: {}",
+            windows::core::Error::from_win32()
+        );
+        Ok(hwnd)
+    }
+}
+
+extern "system" fn on_message(hwnd: HWND, msg: u32, wparam: WPARAM, lparam: LPARAM) -> LRESULT {
+    if msg == WM_KEYUP && wparam == LAST_KEY.with(Cell::get) {
+        RECEIVED.with(|received| received.set(Some(Instant::now())));
+        return LRESULT(0);
+    }
+    unsafe { DefWindowProcW(hwnd, msg, wparam, lparam) }
+}
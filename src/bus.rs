@@ -0,0 +1,330 @@
+use std::{
+    collections::BTreeMap,
+    sync::mpsc::{Receiver, RecvTimeoutError},
+    time::{Duration, Instant},
+};
+
+#[allow(unused_imports)]
+use tracing::{debug, error, info, trace, warn};
+
+use crate::{alternate::Alternate, preset::Preset, rules::Rule, status};
+
+/// an event of the tray thread, by where it comes from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Event {
+    /// a hotkey, chord, gesture or trigger was performed, by the id of its `Hotkey`.
+    Hotkey { id: usize },
+    /// a menu item was chosen, or the tray icon or its balloon clicked.
+    Tray(MenuAction),
+    /// something outside the tray changed.
+    System(Change),
+    /// a command of another process, see `ipc::respond`.
+    #[cfg(feature = "ipc")]
+    Ipc(Command),
+    /// a timer started with `Timers::start` elapsed.
+    Timer(Timer),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum MenuAction {
+    Exit,
+    Restart,
+    /// starts again as administrator, e.g. after the balloon explaining `Change::Blocked` was
+    /// clicked.
+    RelaunchElevated,
+    AutoLaunch,
+    Pause,
+    Telemetry,
+    ReportProblem,
+    Troubleshoot,
+    #[cfg(feature = "settings-ui")]
+    RecentEvents,
+    #[cfg(feature = "settings-ui")]
+    TriggerHistory,
+    ImportPowerToys,
+    ExportAhk,
+    DisableWorkspace,
+    AddTarget,
+    Learning,
+    Alternate(Alternate),
+    Preset(Preset),
+    /// switches a rule of the config on or off.
+    Rule(Rule),
+    /// a read-only line of the "Status" submenu.
+    Status(status::Line),
+    /// the tray icon was right-clicked, its menu is shown on the monitor of the click.
+    ShowMenu,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Change {
+    /// the status changed, e.g. the hotkeys were registered again.
+    Status,
+    /// whether a window receiving the hotkeys is focused, the tray icon is dimmed otherwise.
+    Focused(bool),
+    /// the tray icon sometimes doesn't survive fast user switching, it's set again.
+    Revalidate,
+    /// the scaling or the theme of the taskbar changed, a burst of changes is coalesced into one
+    /// `Timer::Display`.
+    Display,
+    /// a key was blocked by UIPI as the window runs as administrator.
+    Blocked,
+    /// a hotkey was skipped as its window is minimized or cloaked.
+    Hidden,
+}
+
+#[cfg(feature = "ipc")]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Command {
+    /// pauses or resumes unless it's as requested already.
+    SetPaused(bool),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Timer {
+    /// refreshes the "Status" submenu for the uptime, the tray has no notification of the menu
+    /// opening.
+    Status,
+    /// the display settled after `Change::Display`, the icons are loaded again if needed.
+    Display,
+}
+
+/// what a handler wants once it handled an event.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Flow {
+    Continue,
+    /// ends `Bus::run`, e.g. on exit.
+    Stop,
+}
+
+/// handles the events it's interested in, ignoring the others.
+pub trait Handler {
+    fn handle(&mut self, event: Event, timers: &mut Timers) -> Flow;
+}
+
+impl<F: FnMut(Event, &mut Timers) -> Flow> Handler for F {
+    fn handle(&mut self, event: Event, timers: &mut Timers) -> Flow {
+        self(event, timers)
+    }
+}
+
+/// the pending timers of the bus, each at most once.
+#[derive(Debug, Default)]
+pub struct Timers(BTreeMap<Timer, Instant>);
+
+impl Timers {
+    /// sends `Event::Timer(timer)` after `delay`, replacing the time it was started for before.
+    pub fn start(&mut self, timer: Timer, delay: Duration) {
+        self.0.insert(timer, Instant::now() + delay);
+    }
+
+    pub fn cancel(&mut self, timer: Timer) {
+        self.0.remove(&timer);
+    }
+
+    /// the timer elapsing first and when.
+    fn next(&self) -> Option<(Timer, Instant)> {
+        self.0
+            .iter()
+            .min_by_key(|(_, &due)| due)
+            .map(|(&timer, &due)| (timer, due))
+    }
+}
+
+/// offers the events sent by any thread to the handlers, one after another in the order they were
+/// added.
+pub struct Bus<'a> {
+    rx: Receiver<Event>,
+    handlers: Vec<Box<dyn Handler + 'a>>,
+    timers: Timers,
+}
+
+impl<'a> Bus<'a> {
+    pub fn new(rx: Receiver<Event>) -> Self {
+        Self {
+            rx,
+            handlers: Vec::new(),
+            timers: Timers::default(),
+        }
+    }
+
+    pub fn with(mut self, handler: impl Handler + 'a) -> Self {
+        self.handlers.push(Box::new(handler));
+        self
+    }
+
+    pub fn timers(&mut self) -> &mut Timers {
+        &mut self.timers
+    }
+
+    /// handles the events until a handler stops or every sender is gone.
+    pub fn run(mut self) {
+        loop {
+            let event = match self.timers.next() {
+                Some((timer, due)) => {
+                    match self
+                        .rx
+                        .recv_timeout(due.saturating_duration_since(Instant::now()))
+                    {
+                        Ok(event) => event,
+                        Err(RecvTimeoutError::Timeout) => {
+                            self.timers.cancel(timer);
+                            Event::Timer(timer)
+                        }
+                        Err(RecvTimeoutError::Disconnected) => break,
+                    }
+                }
+                None => match self.rx.recv() {
+                    Ok(event) => event,
+                    Err(_) => break,
+                },
+            };
+            trace!("{event:?}");
+            for handler in &mut self.handlers {
+                if handler.handle(event, &mut self.timers) == Flow::Stop {
+                    return;
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{cell::RefCell, sync::mpsc};
+
+    use super::*;
+
+    /// short enough to keep the tests fast, long enough for a scripted burst to arrive within it.
+    const SETTLE_DELAY: Duration = Duration::from_millis(50);
+
+    /// feeds the scripted events to a bus with the handler, returns every event it got until the
+    /// handler stopped.
+    ///
+    /// note: the sender is kept until then, the bus would stop early without it.
+    fn simulate(script: &[Event], handler: impl Handler) -> Vec<Event> {
+        let (tx, rx) = mpsc::channel();
+        for &event in script {
+            tx.send(event).unwrap();
+        }
+        let seen = RefCell::new(Vec::new());
+        Bus::new(rx)
+            .with(|event, _: &mut Timers| {
+                seen.borrow_mut().push(event);
+                Flow::Continue
+            })
+            .with(handler)
+            .run();
+        drop(tx);
+        seen.into_inner()
+    }
+
+    /// coalesces display changes like the tray does, and stops once they settled.
+    fn settle(event: Event, timers: &mut Timers) -> Flow {
+        match event {
+            Event::System(Change::Display) => timers.start(Timer::Display, SETTLE_DELAY),
+            Event::Timer(Timer::Display) | Event::Tray(MenuAction::Exit) => return Flow::Stop,
+            _ => {}
+        }
+        Flow::Continue
+    }
+
+    #[test]
+    fn display_changes_settle_once() {
+        let script = [Event::System(Change::Display); 50];
+        let seen = simulate(&script, settle);
+        assert_eq!(seen[..50], script);
+        assert_eq!(seen[50..], [Event::Timer(Timer::Display)]);
+    }
+
+    #[test]
+    fn events_arrive_while_a_timer_is_pending() {
+        let script = [
+            Event::System(Change::Display),
+            Event::Hotkey { id: 1 },
+            Event::System(Change::Revalidate),
+            Event::Tray(MenuAction::Exit),
+        ];
+        assert_eq!(simulate(&script, settle), script);
+    }
+
+    #[test]
+    fn exit_stops_the_later_handlers_and_events() {
+        let script = [
+            Event::Hotkey { id: 1 },
+            Event::Tray(MenuAction::Exit),
+            Event::Hotkey { id: 2 },
+        ];
+        let later = RefCell::new(Vec::new());
+        let seen = RefCell::new(Vec::new());
+        let (tx, rx) = mpsc::channel();
+        for event in script {
+            tx.send(event).unwrap();
+        }
+        Bus::new(rx)
+            .with(|event, timers: &mut Timers| {
+                seen.borrow_mut().push(event);
+                settle(event, timers)
+            })
+            .with(|event, _: &mut Timers| {
+                later.borrow_mut().push(event);
+                Flow::Continue
+            })
+            .run();
+        assert_eq!(seen.into_inner(), script[..2]);
+        assert_eq!(later.into_inner(), script[..1]);
+    }
+
+    #[test]
+    fn timers_elapse_by_due_time() {
+        let script = [Event::System(Change::Status)];
+        let seen = simulate(&script, |event, timers: &mut Timers| match event {
+            Event::System(Change::Status) => {
+                timers.start(Timer::Status, SETTLE_DELAY * 2);
+                timers.start(Timer::Display, SETTLE_DELAY);
+                Flow::Continue
+            }
+            Event::Timer(Timer::Status) => Flow::Stop,
+            _ => Flow::Continue,
+        });
+        assert_eq!(
+            seen[1..],
+            [Event::Timer(Timer::Display), Event::Timer(Timer::Status)]
+        );
+    }
+
+    #[test]
+    fn run_ends_once_every_sender_is_gone() {
+        let (tx, rx) = mpsc::channel();
+        tx.send(Event::Hotkey { id: 1 }).unwrap();
+        drop(tx);
+        let seen = RefCell::new(Vec::new());
+        Bus::new(rx)
+            .with(|event, timers: &mut Timers| {
+                // note: a pending timer doesn't keep the bus running either.
+                timers.start(Timer::Status, Duration::from_secs(3600));
+                seen.borrow_mut().push(event);
+                Flow::Continue
+            })
+            .run();
+        assert_eq!(seen.into_inner(), [Event::Hotkey { id: 1 }]);
+    }
+
+    #[test]
+    fn next_is_the_earliest_timer() {
+        let mut timers = Timers::default();
+        assert_eq!(timers.next(), None);
+        timers.start(Timer::Status, SETTLE_DELAY * 2);
+        timers.start(Timer::Display, SETTLE_DELAY);
+        assert_eq!(timers.next().map(|(timer, _)| timer), Some(Timer::Display));
+        // note: starting a timer again replaces its due time.
+        timers.start(Timer::Display, SETTLE_DELAY * 3);
+        let (timer, due) = timers.next().unwrap();
+        assert_eq!(timer, Timer::Status);
+        assert!(due <= Instant::now() + SETTLE_DELAY * 2);
+        timers.cancel(Timer::Status);
+        assert_eq!(timers.next().map(|(timer, _)| timer), Some(Timer::Display));
+        timers.cancel(Timer::Display);
+        assert_eq!(timers.next(), None);
+    }
+}
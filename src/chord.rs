@@ -0,0 +1,98 @@
+use std::cell::RefCell;
+
+use anyhow::Result;
+#[allow(unused_imports)]
+use tracing::{debug, error, info, trace, warn};
+use windows::Win32::{
+    Foundation::{LPARAM, WPARAM},
+    System::Threading::GetCurrentThreadId,
+    UI::{
+        Input::KeyboardAndMouse::{
+            GetAsyncKeyState, HOT_KEY_MODIFIERS, MOD_ALT, MOD_CONTROL, MOD_SHIFT, MOD_WIN,
+            VIRTUAL_KEY, VK_CONTROL, VK_LCONTROL, VK_LMENU, VK_LSHIFT, VK_LWIN, VK_MENU,
+            VK_RCONTROL, VK_RMENU, VK_RSHIFT, VK_RWIN, VK_SHIFT,
+        },
+        WindowsAndMessaging::{GetForegroundWindow, PostThreadMessageW, WM_APP},
+    },
+};
+
+use crate::{
+    hotkey::Hotkey,
+    inject,
+    keyhook::{self, Key, Listener},
+    LogExt,
+};
+
+/// posted to the message pump with the id of the hotkey in `wParam` once its second chord is pressed.
+pub const WM_CHORD: u32 = WM_APP + 3;
+
+thread_local! {
+    static PENDING: RefCell<Option<Hotkey>> = const { RefCell::new(None) };
+}
+
+/// starts waiting for the second chord of a two-step hotkey whose first chord was just pressed.
+///
+/// note: it waits until the next key press, like VSCode, we don't time out a started chord.
+pub fn begin(hotkey: Hotkey) -> Result<()> {
+    PENDING.with(|pending| *pending.borrow_mut() = Some(hotkey));
+    keyhook::listen(Listener::Chord, true)
+}
+
+/// completes or abandons the pending chord with the key, returns whether the key is swallowed.
+pub fn on_key(key: &Key) -> bool {
+    if !key.down || is_modifier(key.vk) {
+        return false;
+    }
+    let Some(hotkey) = PENDING.with(|pending| pending.borrow_mut().take()) else {
+        return false;
+    };
+    keyhook::listen(Listener::Chord, false).warn();
+    if let Some(then) = hotkey.then {
+        if then.vk == key.vk && then.modifiers == held_modifiers() {
+            unsafe {
+                PostThreadMessageW(GetCurrentThreadId(), WM_CHORD, WPARAM(hotkey.id), LPARAM(0))
+            }
+            .warn();
+            return true;
+        }
+    }
+
+    // note: not our chord, so the swallowed first chord is handed to the window before this key.
+    debug!("{:?} doesn't complete {hotkey:?}", key.vk);
+    inject::post_keys(unsafe { GetForegroundWindow() }, [hotkey.chord.vk]).warn();
+    false
+}
+
+pub fn is_modifier(vk: VIRTUAL_KEY) -> bool {
+    matches!(
+        vk,
+        VK_SHIFT
+            | VK_LSHIFT
+            | VK_RSHIFT
+            | VK_CONTROL
+            | VK_LCONTROL
+            | VK_RCONTROL
+            | VK_MENU
+            | VK_LMENU
+            | VK_RMENU
+            | VK_LWIN
+            | VK_RWIN
+    )
+}
+
+/// the modifiers currently held down.
+pub fn held_modifiers() -> HOT_KEY_MODIFIERS {
+    let held = |vk: VIRTUAL_KEY| unsafe { GetAsyncKeyState(vk.0 as i32) } < 0;
+    [
+        (VK_CONTROL, MOD_CONTROL),
+        (VK_SHIFT, MOD_SHIFT),
+        (VK_MENU, MOD_ALT),
+        (VK_LWIN, MOD_WIN),
+        (VK_RWIN, MOD_WIN),
+    ]
+    .into_iter()
+    .filter(|&(vk, _)| held(vk))
+    .fold(HOT_KEY_MODIFIERS(0), |modifiers, (_, modifier)| {
+        modifiers | modifier
+    })
+}
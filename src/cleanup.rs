@@ -0,0 +1,154 @@
+use std::{fs, path::Path};
+
+use anyhow::{Context, Result};
+#[allow(unused_imports)]
+use tracing::{debug, error, info, trace, warn};
+use windows::{
+    core::{HSTRING, PWSTR},
+    Win32::{
+        Foundation::ERROR_NO_MORE_ITEMS,
+        System::Registry::{
+            RegCloseKey, RegDeleteValueW, RegEnumValueW, RegOpenKeyExW, HKEY, HKEY_CURRENT_USER,
+            KEY_QUERY_VALUE, KEY_SET_VALUE,
+        },
+    },
+};
+
+use crate::{config::Config, crashloop, errors, state::State, LogExt, PACKAGE_NAME};
+
+const RUN_KEY: &str = r"Software\Microsoft\Windows\CurrentVersion\Run";
+
+/// where Explorer keeps the enabled state of the Run key entries, e.g. once toggled in Task Manager.
+const STARTUP_APPROVED_KEY: &str =
+    r"Software\Microsoft\Windows\CurrentVersion\Explorer\StartupApproved\Run";
+
+/// removes what we leave behind outside the install directory, run by uninstallers via `--cleanup`.
+pub fn run(app_path: &Path, remove_config: bool) -> Result<()> {
+    remove_autostart().warn();
+    if let Some(dir) = app_path.parent() {
+        remove_logs(dir).warn();
+    }
+    crashloop::leave(app_path).warn();
+    let state_path = State::path(app_path);
+    if state_path.exists() {
+        fs::remove_file(&state_path)
+            .with_context(|| format!("failed to remove {state_path:?}"))
+            .warn();
+    }
+    if remove_config {
+        let path = Config::path(app_path);
+        if path.exists() {
+            fs::remove_file(&path).with_context(|| format!("failed to remove {path:?}"))?;
+            info!("removed {path:?}");
+        }
+    }
+    Ok(())
+}
+
+/// removes the Run key entries of every version, including ones installed to another path.
+///
+/// note: an entry is ours if it's named after us or launches an executable named after us.
+fn remove_autostart() -> Result<()> {
+    let run = open(RUN_KEY)?;
+    let names: Vec<_> = values(run)
+        .into_iter()
+        .filter(|(name, command)| {
+            name.eq_ignore_ascii_case(PACKAGE_NAME)
+                || command.to_ascii_lowercase().contains(PACKAGE_NAME)
+        })
+        .map(|(name, _)| name)
+        .collect();
+    let startup_approved = open(STARTUP_APPROVED_KEY).warn();
+    for name in &names {
+        let name = HSTRING::from(name);
+        unsafe { RegDeleteValueW(run, &name) }
+            .with_context(|| {
+                errors::Registry(format!("failed to remove the autostart entry {name}"))
+            })
+            .warn();
+        if let Some(startup_approved) = startup_approved {
+            // note: not every entry has an approval state, so failures are expected.
+            let _ = unsafe { RegDeleteValueW(startup_approved, &name) };
+        }
+        info!("removed the autostart entry {name}");
+    }
+
+    unsafe { RegCloseKey(run) }.warn();
+    if let Some(startup_approved) = startup_approved {
+        unsafe { RegCloseKey(startup_approved) }.warn();
+    }
+    Ok(())
+}
+
+fn open(subkey: &str) -> Result<HKEY> {
+    let mut key = HKEY::default();
+    unsafe {
+        RegOpenKeyExW(
+            HKEY_CURRENT_USER,
+            &HSTRING::from(subkey),
+            0,
+            KEY_QUERY_VALUE | KEY_SET_VALUE,
+            &mut key,
+        )
+    }
+    .with_context(|| errors::Registry(format!("failed to open HKCU\\{subkey}")))?;
+    Ok(key)
+}
+
+/// the names and string data of the values in the key.
+fn values(key: HKEY) -> Vec<(String, String)> {
+    let mut values = Vec::new();
+    let mut name = vec![0u16; 16384];
+    let mut data = vec![0u8; 65536];
+    for index in 0.. {
+        let mut name_len = name.len() as u32;
+        let mut data_len = data.len() as u32;
+        let result = unsafe {
+            RegEnumValueW(
+                key,
+                index,
+                PWSTR(name.as_mut_ptr()),
+                &mut name_len,
+                None,
+                None,
+                Some(data.as_mut_ptr()),
+                Some(&mut data_len),
+            )
+        };
+        match result {
+            Ok(()) => {
+                let data: Vec<_> = data[..data_len as usize]
+                    .chunks_exact(2)
+                    .map(|unit| u16::from_le_bytes([unit[0], unit[1]]))
+                    .collect();
+                values.push((
+                    String::from_utf16_lossy(&name[..name_len as usize]),
+                    String::from_utf16_lossy(&data),
+                ));
+            }
+            Err(err) if err.code() == ERROR_NO_MORE_ITEMS.to_hresult() => break,
+            Err(err) => warn!("failed to read value #{index}: {err:?}"),
+        }
+    }
+    values
+}
+
+/// removes the log files of every version, e.g. `vscode-cjk-toggle-terminal-fixer-0.1.5.log`.
+///
+/// note: the log file of this run is removed as well once we exit.
+fn remove_logs(dir: &Path) -> Result<()> {
+    let prefix = format!("{PACKAGE_NAME}-");
+    for entry in fs::read_dir(dir).with_context(|| format!("failed to list {dir:?}"))? {
+        let path = entry?.path();
+        let is_log = path
+            .file_name()
+            .and_then(|name| name.to_str())
+            .is_some_and(|name| name.starts_with(&prefix) && name.ends_with(".log"));
+        if is_log {
+            fs::remove_file(&path)
+                .with_context(|| format!("failed to remove {path:?}"))
+                .warn();
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,95 @@
+use std::path::PathBuf;
+
+use anyhow::{bail, Context, Result};
+
+use crate::inject::Action;
+
+/// the command line switches, e.g. `--silent-setup --autostart` as run by a package manager.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct Args {
+    /// create the config file if missing and skip anything interactive before starting as usual.
+    pub silent_setup: bool,
+    /// enable autostart during `--silent-setup`, it's left alone otherwise.
+    pub autostart: bool,
+    /// remove the autostart entries and log files of every version and exit, run by uninstallers.
+    pub cleanup: bool,
+    /// remove the config file during `--cleanup` as well.
+    pub remove_config: bool,
+    /// start with the hotkeys paused, e.g. when relaunched by Windows.
+    pub paused: bool,
+    /// write the default config with every key and its description and exit.
+    pub print_default_config: bool,
+    /// where `--print-default-config` writes to, the standard output if not given.
+    pub default_config_path: Option<PathBuf>,
+    /// print the status of the running instance and exit, failing if none runs.
+    pub status: bool,
+    /// print the status as JSON during `--status`.
+    pub json: bool,
+    /// print the counters of the running instance in the Prometheus text format and exit.
+    pub metrics: bool,
+    /// make the running instance perform the action as if its hotkey was pressed and exit, e.g.
+    /// `--trigger` bound to a macro key toggles the terminal, `--trigger focus_terminal` focuses it.
+    pub trigger: Option<Action>,
+    /// zip the config, the state and the icon pack to the file and exit.
+    pub backup: Option<PathBuf>,
+    /// write the files of a `--backup` back and exit, the running instance has to quit first.
+    pub restore: Option<PathBuf>,
+    /// inject many hotkeys into a window of ours and exit, failing if they're slower than the
+    /// latency budget, e.g. in CI.
+    pub bench: bool,
+}
+
+impl Args {
+    pub fn parse(args: impl IntoIterator<Item = String>) -> Result<Self> {
+        let mut parsed = Self::default();
+        let mut args = args.into_iter().peekable();
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--silent-setup" => parsed.silent_setup = true,
+                "--autostart" => parsed.autostart = true,
+                "--cleanup" => parsed.cleanup = true,
+                "--remove-config" => parsed.remove_config = true,
+                "--paused" => parsed.paused = true,
+                "--status" => parsed.status = true,
+                "--json" => parsed.json = true,
+                "--metrics" => parsed.metrics = true,
+                "--bench" => parsed.bench = true,
+                "--trigger" => {
+                    parsed.trigger = Some(match args.next_if(|next| !next.starts_with("--")) {
+                        Some(action) => action.parse()?,
+                        None => Action::ToggleTerminal,
+                    });
+                }
+                "--backup" => parsed.backup = Some(path(&mut args, &arg)?),
+                "--restore" => parsed.restore = Some(path(&mut args, &arg)?),
+                "--print-default-config" => {
+                    parsed.print_default_config = true;
+                    parsed.default_config_path = args
+                        .next_if(|next| !next.starts_with("--"))
+                        .map(PathBuf::from);
+                }
+                _ => bail!("unknown argument: {arg:?}"),
+            }
+        }
+        if parsed.autostart && !parsed.silent_setup {
+            bail!("--autostart requires --silent-setup");
+        }
+        if parsed.remove_config && !parsed.cleanup {
+            bail!("--remove-config requires --cleanup");
+        }
+        if parsed.json && !parsed.status {
+            bail!("--json requires --status");
+        }
+        Ok(parsed)
+    }
+}
+
+/// the path following the switch.
+fn path(
+    args: &mut std::iter::Peekable<impl Iterator<Item = String>>,
+    switch: &str,
+) -> Result<PathBuf> {
+    args.next_if(|next| !next.starts_with("--"))
+        .map(PathBuf::from)
+        .with_context(|| format!("{switch} requires a path"))
+}
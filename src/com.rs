@@ -0,0 +1,41 @@
+use std::cell::RefCell;
+
+use anyhow::Result;
+#[allow(unused_imports)]
+use tracing::{debug, error, info, trace, warn};
+use windows::{
+    core::{ComInterface, IUnknown, GUID},
+    Win32::System::Com::{
+        CoCreateInstance, CoInitializeEx, CLSCTX_INPROC_SERVER, COINIT_APARTMENTTHREADED,
+    },
+};
+
+use crate::LogExt;
+
+thread_local! {
+    /// the objects created on the calling thread by their class.
+    static OBJECTS: RefCell<Vec<(GUID, IUnknown)>> = const { RefCell::new(Vec::new()) };
+}
+
+/// runs `f` with the object of the class, created on the calling thread on first use and kept for
+/// its lifetime.
+pub fn with<I: ComInterface, T>(clsid: &GUID, f: impl FnOnce(&I) -> Result<T>) -> Result<T> {
+    let known = OBJECTS.with(|objects| {
+        objects
+            .borrow()
+            .iter()
+            .find(|(class, _)| class == clsid)
+            .map(|(_, object)| object.clone())
+    });
+    let object = match known {
+        Some(object) => object,
+        None => {
+            // note: S_FALSE or a mismatched apartment mean COM is usable on this thread already.
+            unsafe { CoInitializeEx(None, COINIT_APARTMENTTHREADED) }.warn();
+            let object: IUnknown = unsafe { CoCreateInstance(clsid, None, CLSCTX_INPROC_SERVER)? };
+            OBJECTS.with(|objects| objects.borrow_mut().push((*clsid, object.clone())));
+            object
+        }
+    };
+    f(&object.cast()?)
+}
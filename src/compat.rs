@@ -0,0 +1,35 @@
+use std::sync::OnceLock;
+
+use crate::{diagnostics, LogExt};
+
+/// a feature of Windows newer than the oldest version we support, Windows 10 1809, or one that
+/// behaves differently there.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Feature {
+    /// per-monitor DPI awareness v2, the system DPI is used without.
+    PerMonitorDpiV2,
+    /// `SetPreferredAppMode` of uxtheme, the menus stay light without.
+    PreferredAppMode,
+    /// the light theme of the taskbar, it's always dark without.
+    LightTaskbar,
+}
+
+impl Feature {
+    /// the first build with the feature.
+    fn build(self) -> u32 {
+        match self {
+            // note: Windows 10 1703.
+            Self::PerMonitorDpiV2 => 15063,
+            // note: Windows 10 1903.
+            Self::PreferredAppMode | Self::LightTaskbar => 18362,
+        }
+    }
+}
+
+/// whether the running Windows has the feature, assumed if the build is unknown.
+pub fn has(feature: Feature) -> bool {
+    static BUILD: OnceLock<Option<u32>> = OnceLock::new();
+    BUILD
+        .get_or_init(|| diagnostics::os_build().warn())
+        .is_none_or(|build| build >= feature.build())
+}
@@ -0,0 +1,413 @@
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fs,
+    io::{self, Write},
+    path::{Path, PathBuf},
+};
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use toml_edit::{DocumentMut, Item, Value};
+use windows::Win32::UI::WindowsAndMessaging::WM_APP;
+
+use crate::{
+    desktop, gesture,
+    hotkey::{self, ChordRule},
+    inject, logfile, migration, overrides,
+    quirk::Quirk,
+    remote, schedule, timestamp, window,
+};
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(default)]
+pub struct Config {
+    /// the version of the config format, older files are upgraded on start.
+    pub config_version: u32,
+    /// activate the most recently active VSCode window before toggling when VSCode isn't focused.
+    pub bring_to_front: bool,
+    /// restore a minimized or cloaked target window before toggling, it's skipped otherwise, as
+    /// nothing would visibly happen.
+    pub restore_hidden_windows: bool,
+    /// what to do when the target window is on another virtual desktop: "skip" and tell why in
+    /// the tray, or "switch" to its desktop and toggle.
+    pub other_desktop: desktop::Policy,
+    /// which VSCode windows receive the toggle: "foreground", only the focused one, "last_active",
+    /// the most recently active one when VSCode isn't focused, or "all_visible", every visible one.
+    pub target_windows: window::Policy,
+    /// launch VSCode when no VSCode window exists and toggle the terminal once its window appears.
+    pub launch_if_missing: bool,
+    /// path to `Code.exe`, auto-detected from the default install locations if not set.
+    pub code_path: Option<PathBuf>,
+    /// dock the summoned VSCode window to the top of the current monitor and restore its placement
+    /// when dismissed by the hotkey again, requires `bring_to_front`.
+    pub quake_mode: bool,
+    /// height of the docked window in percent of the monitor's work area.
+    pub quake_height_percent: u32,
+    /// the injection backends tried in order until one succeeds: "post_message", "send_input",
+    /// "command_palette" and "uia".
+    pub backends: Vec<inject::Backend>,
+    /// the menu items invoked by the "uia" backend per action, e.g. `toggle_terminal = ["查看", "终端"]`
+    /// for a localized VSCode, English menus are assumed for missing actions.
+    pub uia_menu_paths: BTreeMap<inject::Action, Vec<String>>,
+    /// extra IME quirks, checked before the built-in ones.
+    pub ime_quirks: Vec<Quirk>,
+    /// how long to wait for a busy target window to respond again before the hotkey is dropped, 0
+    /// drops it right away.
+    pub busy_retry_ms: u64,
+    /// how the toggle is injected in a remote desktop session, e.g.
+    /// `{ enabled = true, backend = "send_input", key_delay_ms = 15 }`.
+    pub remote_session: remote::Profile,
+    /// hand the first toggle per keyboard layout to VSCode as-is and pause the hotkeys if VSCode
+    /// toggles the terminal on its own, checked via UI Automation.
+    pub probe_native_hotkey: bool,
+    /// skip the toggle while a text input other than the editor's or terminal's is focused, e.g. the
+    /// command palette or a find widget, or a dialog in the window is open, checked via UI
+    /// Automation, the injected key would be typed into it.
+    ///
+    /// note: off by default, asking VSCode for its focus adds a cross-process call to every press.
+    pub skip_in_text_inputs: bool,
+    /// check via UI Automation whether the focus moved after injecting and retry once with the
+    /// next backend if it didn't.
+    pub verify_injection: bool,
+    /// how the hotkeys on the key left of 「1」 are recognized: "virtual_key", registered again when
+    /// the foreground window has another keyboard layout, or "scan_code" via a keyboard hook.
+    pub trigger: hotkey::Trigger,
+    /// also fix 「Ctrl+Shift+`」, which creates a new terminal.
+    pub new_terminal_hotkey: bool,
+    /// a hotkey returning the focus to the editor, e.g. "Ctrl+Shift+`", which makes the hotkey
+    /// toggling the terminal move the focus to it from the editor rather than hide it, checked via
+    /// UI Automation.
+    ///
+    /// note: both run VSCode's commands rather than pressing keys, the chord needn't be bound in
+    /// VSCode, but it mustn't be another hotkey's, e.g. "Ctrl+Shift+`" while `new_terminal_hotkey`
+    /// is on.
+    pub return_to_editor_hotkey: Option<hotkey::Chord>,
+    /// the actions performed instead while the VSCode window is full screen, e.g. in zen mode,
+    /// `{ toggle_terminal = "toggle_panel" }`, run as VSCode's commands like
+    /// `return_to_editor_hotkey`.
+    pub full_screen_actions: BTreeMap<inject::Action, inject::Action>,
+    /// holding the hotkey toggling the terminal shows it only until the hotkey is released, a short
+    /// press toggles it as usual.
+    pub hold_to_peek: bool,
+    /// more hotkeys swallowed by the IME, a single chord or two-step, e.g.
+    /// `{ keys = ["Ctrl+K", "Ctrl+`"], action = "focus_terminal" }`, the modifiers are `Ctrl`, `Shift`,
+    /// `Alt`, `Win` and `AltGr`.
+    ///
+    /// note: the first chord of a two-step hotkey is taken from every application, it's replayed to
+    /// the foreground window if the next key doesn't complete the chord. a rule with
+    /// `enabled = false` is kept but ignored, see the tray's "Rules" menu.
+    pub chords: Vec<ChordRule>,
+    /// a modifier tapped twice in a row performing an action, e.g.
+    /// `{ double_tap = "Ctrl", action = "toggle_terminal" }`, the modifiers are `Ctrl`, `Shift` and
+    /// `Alt`.
+    ///
+    /// note: the action is run via the command palette or UI Automation, the modifier is released by
+    /// then. a rule with `enabled = false` is kept but ignored.
+    pub gestures: Vec<gesture::GestureRule>,
+    /// the longest a tap of a gesture and the pause between its taps may take.
+    pub double_tap_ms: u32,
+    /// parts of the device names of the keyboards whose hotkeys are fixed, e.g. `VID_04FE&PID_0021`
+    /// for a JIS keyboard, the others' are passed to the focused window as-is, all by default.
+    ///
+    /// note: the device name of the keyboard is logged on each hotkey.
+    pub keyboards: Vec<String>,
+    /// the VSCode workspaces the hotkeys are left alone in, by the folder name in the window title,
+    /// e.g. a project binding 「Ctrl+`」 to something else.
+    pub disabled_workspaces: Vec<String>,
+    /// executables of other applications whose windows receive the hotkeys like VSCode's when
+    /// focused, e.g. `idea64.exe`.
+    pub target_processes: Vec<String>,
+    /// a directory of tray icons replacing the built-in ones, `normal.ico`, `dimmed.ico`,
+    /// `paused.ico`, `scheduled.ico` and `error.ico`, each optionally suffixed with `-light` or `-dark` for the taskbar
+    /// theme, read at startup and when the scaling of the taskbar changes.
+    pub icon_pack: Option<PathBuf>,
+    /// dim the tray icon while no window receiving the hotkeys is focused.
+    pub dim_when_unfocused: bool,
+    /// leave out the notifications nobody asked for, e.g. the weekly summary, the sounds of the
+    /// others and the menu animations, which are also left out while Windows' animation effects
+    /// are off.
+    pub quiet: bool,
+    /// when the hotkeys are active, e.g. `{ from = "09:00", to = "19:00", days = ["mon", "tue", "wed",
+    /// "thu", "fri"] }`, they're dormant otherwise, always active if not set.
+    pub active_hours: Option<schedule::ActiveHours>,
+    /// ignore the hotkeys while presenting, e.g. in presentation mode or a full-screen slide show, so
+    /// no terminal pops up on a shared screen.
+    pub pause_while_presenting: bool,
+    /// where the log goes: "file", next to the executable, or "off" to write nothing to disk, the
+    /// recent events are kept in memory either way, applied on restart.
+    pub logging: logfile::Logging,
+    /// the time zone of the log timestamps, "utc" or "local", both in ISO 8601.
+    pub log_timezone: timestamp::Timezone,
+    /// show how many hotkeys were handled and why some failed once a week, e.g. to notice a VSCode
+    /// update breaking something.
+    pub weekly_summary: bool,
+    /// zip the diagnostics next to the log once this many hotkeys failed within a minute, e.g. 5, 0
+    /// never does.
+    pub snapshot_failures: usize,
+    /// write the time and the status as JSON to this file every minute, e.g. for a monitoring script
+    /// to check we're running without the pipe, removed on exit.
+    pub heartbeat_path: Option<PathBuf>,
+    /// send anonymous aggregate usage statistics once a day, see "Usage Statistics…" in the tray
+    /// for the exact payload.
+    pub telemetry: bool,
+    /// where the usage statistics are posted to.
+    pub telemetry_url: Option<String>,
+    /// serve the status, pause, resume and triggering actions over HTTP on this loopback port, 0
+    /// doesn't, applied on restart.
+    ///
+    /// note: requires a build with the "http-api" feature.
+    pub http_api_port: u16,
+    /// the token the HTTP API requires as `Authorization: Bearer <token>`.
+    pub http_api_token: Option<String>,
+}
+
+/// the commented config file written on first run.
+pub const TEMPLATE: &str = include_str!("../assets/config.toml");
+
+/// posted to the message pump once the config file was changed from the tray.
+pub const WM_RELOAD: u32 = WM_APP + 4;
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            config_version: migration::CONFIG_VERSION,
+            bring_to_front: false,
+            restore_hidden_windows: false,
+            other_desktop: desktop::Policy::Skip,
+            target_windows: window::Policy::Foreground,
+            launch_if_missing: false,
+            code_path: None,
+            quake_mode: false,
+            quake_height_percent: 50,
+            backends: inject::BACKENDS.to_vec(),
+            uia_menu_paths: BTreeMap::new(),
+            ime_quirks: Vec::new(),
+            busy_retry_ms: 2000,
+            remote_session: remote::Profile::default(),
+            probe_native_hotkey: false,
+            skip_in_text_inputs: false,
+            verify_injection: false,
+            trigger: hotkey::Trigger::VirtualKey,
+            new_terminal_hotkey: false,
+            return_to_editor_hotkey: None,
+            full_screen_actions: BTreeMap::new(),
+            hold_to_peek: false,
+            chords: Vec::new(),
+            gestures: Vec::new(),
+            double_tap_ms: 300,
+            keyboards: Vec::new(),
+            disabled_workspaces: Vec::new(),
+            target_processes: Vec::new(),
+            icon_pack: None,
+            dim_when_unfocused: false,
+            quiet: false,
+            active_hours: None,
+            pause_while_presenting: false,
+            logging: logfile::Logging::File,
+            log_timezone: timestamp::Timezone::Utc,
+            weekly_summary: false,
+            snapshot_failures: 0,
+            heartbeat_path: None,
+            telemetry: false,
+            telemetry_url: None,
+            http_api_port: 0,
+            http_api_token: None,
+        }
+    }
+}
+
+impl Config {
+    /// the config file lives next to the executable, e.g. `vscode-cjk-toggle-terminal-fixer.toml`,
+    /// unless `CJK_FIXER_CONFIG` names another one.
+    pub fn path(app_path: &Path) -> PathBuf {
+        overrides::config_path().unwrap_or_else(|| app_path.with_extension("toml"))
+    }
+
+    /// whether the foreground window has to be tracked for the configured features.
+    pub fn tracks_foreground(&self) -> bool {
+        self.target_windows == window::Policy::LastActive
+            || self.bring_to_front
+            || self.launch_if_missing
+            || self.dim_when_unfocused
+            || self.trigger == hotkey::Trigger::VirtualKey
+    }
+
+    /// writes the template unless the config file already exists, returns whether it was created.
+    pub fn create(path: &Path) -> Result<bool> {
+        match fs::OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .open(path)
+        {
+            Ok(mut file) => {
+                file.write_all(TEMPLATE.as_bytes())
+                    .with_context(|| format!("failed to write config: {path:?}"))?;
+                Ok(true)
+            }
+            Err(err) if err.kind() == io::ErrorKind::AlreadyExists => Ok(false),
+            Err(err) => Err(err).with_context(|| format!("failed to create config: {path:?}")),
+        }
+    }
+
+    /// writes the default config with every key set, its type and description, to the file or the
+    /// standard output.
+    ///
+    /// note: a release build has no console, so its standard output has to be redirected.
+    pub fn print_default(path: Option<&Path>) -> Result<()> {
+        let text = Self::documented_default()?;
+        match path {
+            Some(path) => {
+                fs::write(path, text).with_context(|| format!("failed to write config: {path:?}"))
+            }
+            None => io::stdout()
+                .write_all(text.as_bytes())
+                .context("failed to write the standard output"),
+        }
+    }
+
+    /// the defaults serialized from `Config::default()`, so they can't drift from the code, described
+    /// by the comments of the template.
+    ///
+    /// note: the keys unset by default stay commented out with the template's example, a key the
+    /// template doesn't describe is appended after the others.
+    fn documented_default() -> Result<String> {
+        let defaults: DocumentMut = toml::to_string(&Self::default())?.parse()?;
+        let mut keys = String::from(
+            "# vscode-cjk-toggle-terminal-fixer config, every key is set to its default.\n",
+        );
+        let mut tables = String::new();
+        let mut described = BTreeSet::new();
+        // note: the first block is the template's header.
+        for block in TEMPLATE.trim().split("\n\n").skip(1) {
+            let mut text = String::new();
+            // note: the keys following a table header belong to that table, `None` if it's empty by
+            // default.
+            let mut table = None;
+            for line in block.lines() {
+                let line = line.strip_prefix("# ").unwrap_or(line);
+                if line.starts_with('[') {
+                    let name = line.trim_matches(['[', ']']);
+                    described.insert(name.to_owned());
+                    let values = defaults
+                        .get(name)
+                        .and_then(Item::as_table)
+                        .filter(|values| !values.is_empty());
+                    match values {
+                        Some(_) => text.push_str(&format!("{line}\n")),
+                        None => text.push_str(&format!("# {line}\n")),
+                    }
+                    table = Some(values);
+                    continue;
+                }
+                let Some((key, example)) = example(line) else {
+                    text.push_str(&format!("# {line}\n"));
+                    continue;
+                };
+                // note: the example of a table unset by default is kept as a whole.
+                if matches!(table, Some(None)) {
+                    text.push_str(&format!("# {line}\n"));
+                    continue;
+                }
+                let default = match table {
+                    Some(values) => values.and_then(|values| values.get(&key)),
+                    None => {
+                        described.insert(key.clone());
+                        defaults.get(&key)
+                    }
+                };
+                match default.and_then(Item::as_value) {
+                    Some(default) => {
+                        let value = default.to_string();
+                        let value = value.trim();
+                        text.push_str(&format!("# {}\n{key} = {value}\n", default.type_name()));
+                        if value != example.to_string().trim() {
+                            text.push_str(&format!("# e.g. {line}\n"));
+                        }
+                    }
+                    None => text.push_str(&format!(
+                        "# {}, not set by default\n# {line}\n",
+                        example.type_name()
+                    )),
+                }
+            }
+            let out = if table.is_some() {
+                &mut tables
+            } else {
+                &mut keys
+            };
+            out.push_str(&format!("\n{text}"));
+        }
+
+        for (key, item) in defaults.iter() {
+            if described.contains(key) {
+                continue;
+            }
+            let mut document = DocumentMut::new();
+            document.insert(key, item.clone());
+            let out = if item.is_table_like() && !item.is_inline_table() {
+                &mut tables
+            } else {
+                &mut keys
+            };
+            out.push_str(&format!("\n# undocumented\n{document}"));
+        }
+        Ok(keys + &tables)
+    }
+
+    /// sets the keys of `values`, a TOML snippet, in the config file while keeping the user's other
+    /// keys and comments.
+    pub fn update(path: &Path, values: &str) -> Result<()> {
+        migration::upgrade(path)?;
+        let text = match fs::read_to_string(path) {
+            Ok(text) => text,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => String::new(),
+            Err(err) => {
+                return Err(err).with_context(|| format!("failed to read config: {path:?}"))
+            }
+        };
+        let mut document: DocumentMut = text
+            .parse()
+            .with_context(|| format!("invalid config: {path:?}"))?;
+        let values: DocumentMut = values.parse()?;
+        for (key, item) in values.iter() {
+            document.insert(key, item.clone());
+        }
+
+        let text = document.to_string();
+        // note: the values might clash with a hand-written key, e.g. one of the wrong type or a rule
+        // switched on again with a chord another hotkey took meanwhile.
+        Self::parse(&text).with_context(|| format!("invalid config: {path:?}"))?;
+        fs::write(path, text).with_context(|| format!("failed to write config: {path:?}"))
+    }
+
+    /// a missing config file is not an error, defaults are used instead.
+    ///
+    /// note: a file of an older version is read as if upgraded, e.g. when it couldn't be written.
+    pub fn load(path: &Path) -> Result<Self> {
+        match fs::read_to_string(path) {
+            Ok(text) => Self::parse(&text).with_context(|| format!("invalid config: {path:?}")),
+            Err(err) if err.kind() == io::ErrorKind::NotFound => Ok(Self::default()),
+            Err(err) => Err(err).with_context(|| format!("failed to read config: {path:?}")),
+        }
+    }
+
+    /// reads the text of a config file, upgrading an older version first.
+    pub fn parse(text: &str) -> Result<Self> {
+        let mut document: DocumentMut = text.parse()?;
+        migration::apply(&mut document);
+        let config: Self = toml::from_str(&document.to_string())?;
+        hotkey::check_duplicates(&config)?;
+        Ok(config)
+    }
+}
+
+/// the key and value of a commented example line of the template, e.g. `busy_retry_ms = 2000`.
+fn example(line: &str) -> Option<(String, Value)> {
+    let document: DocumentMut = line.parse().ok()?;
+    let mut entries = document.iter();
+    let (key, item) = entries.next()?;
+    if entries.next().is_some() {
+        return None;
+    }
+    Some((key.to_owned(), item.as_value()?.clone()))
+}
@@ -0,0 +1,158 @@
+use std::{fmt, thread};
+
+#[allow(unused_imports)]
+use tracing::{debug, error, info, trace, warn};
+use windows::{
+    core::HSTRING,
+    Win32::{
+        Foundation::HWND,
+        UI::{
+            Input::KeyboardAndMouse::{VK_CONTROL, VK_LCONTROL, VK_OEM_3, VK_RCONTROL},
+            WindowsAndMessaging::{MessageBoxW, MB_ICONWARNING, MB_OK},
+        },
+    },
+};
+
+use crate::{powertoys, procs, quiet, LogExt, PACKAGE_NAME};
+
+/// a keyboard remapping tool known to swallow or rewrite 「Ctrl+`」 before it reaches us.
+struct Tool {
+    name: &'static str,
+    executables: &'static [&'static str],
+    advice: &'static str,
+}
+
+const KEYBOARD_MANAGER: &str = "PowerToys Keyboard Manager";
+
+const KNOWN_TOOLS: [Tool; 4] = [
+    Tool {
+        name: KEYBOARD_MANAGER,
+        executables: &["PowerToys.KeyboardManagerEngine.exe"],
+        advice: "remove its remappings of Ctrl+` or limit them to other applications",
+    },
+    Tool {
+        name: "AutoHotkey",
+        executables: &[
+            "AutoHotkey.exe",
+            "AutoHotkey64.exe",
+            "AutoHotkey32.exe",
+            "AutoHotkeyU64.exe",
+            "AutoHotkeyU32.exe",
+        ],
+        advice: "make sure no running script binds ^` or remaps `",
+    },
+    Tool {
+        name: "kanata",
+        executables: &["kanata.exe"],
+        advice: "make sure your layout passes Ctrl+` through unchanged",
+    },
+    Tool {
+        name: "KMonad",
+        executables: &["kmonad.exe"],
+        advice: "make sure your layout passes Ctrl+` through unchanged",
+    },
+];
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Conflict {
+    pub tool: &'static str,
+    pub detail: String,
+    pub advice: &'static str,
+}
+
+impl fmt::Display for Conflict {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}, {}.", self.tool, self.detail, self.advice)
+    }
+}
+
+/// looks for running remapping tools and mappings of 「Ctrl+`」 in PowerToys Keyboard Manager.
+pub fn detect() -> Vec<Conflict> {
+    let running = procs::running();
+    let mut conflicts: Vec<_> = KNOWN_TOOLS
+        .iter()
+        .filter_map(|tool| {
+            let exe = tool
+                .executables
+                .iter()
+                .find(|exe| running.iter().any(|name| name.eq_ignore_ascii_case(exe)))?;
+            Some(Conflict {
+                tool: tool.name,
+                detail: format!("{exe} is running"),
+                advice: tool.advice,
+            })
+        })
+        .collect();
+
+    // note: the mappings only matter while the engine runs, Keyboard Manager may be disabled.
+    if conflicts
+        .iter()
+        .any(|conflict| conflict.tool == KEYBOARD_MANAGER)
+    {
+        conflicts.extend(keyboard_manager_conflicts());
+    }
+    conflicts
+}
+
+fn keyboard_manager_conflicts() -> Vec<Conflict> {
+    let Some(Some(mappings)) = powertoys::keyboard_manager().warn() else {
+        return Vec::new();
+    };
+    let is_ctrl = |vk| matches!(vk, VK_CONTROL | VK_LCONTROL | VK_RCONTROL);
+    let key_remaps = mappings.remap_keys.in_process.iter().filter(|remap| {
+        remap
+            .original()
+            .iter()
+            .any(|&vk| vk == VK_OEM_3 || is_ctrl(vk))
+    });
+    let shortcut_remaps = mappings
+        .remap_shortcuts
+        .global
+        .iter()
+        .chain(
+            mappings
+                .remap_shortcuts
+                .app_specific
+                .iter()
+                .filter(|remap| remap.target_app.eq_ignore_ascii_case("code")),
+        )
+        .filter(|remap| {
+            let original = remap.original();
+            original.contains(&VK_OEM_3) && original.iter().any(|&vk| is_ctrl(vk))
+        });
+    key_remaps
+        .chain(shortcut_remaps)
+        .map(|remap| Conflict {
+            tool: KEYBOARD_MANAGER,
+            detail: format!(
+                "{} is remapped to {}",
+                remap.original_keys, remap.new_remap_keys
+            ),
+            advice: "remove the mapping or limit it to other applications",
+        })
+        .collect()
+}
+
+/// tells the user about the conflicts without blocking the caller.
+///
+/// note: left out in quiet mode, the conflicts are logged anyway.
+pub fn show(conflicts: Vec<Conflict>) {
+    if conflicts.is_empty() || quiet::is_on() {
+        return;
+    }
+    let text = conflicts
+        .iter()
+        .map(ToString::to_string)
+        .collect::<Vec<_>>()
+        .join("\n\n");
+    thread::spawn(move || unsafe {
+        MessageBoxW(
+            HWND(0),
+            &HSTRING::from(format!(
+                "These tools may keep Ctrl+` from reaching VSCode:\n\n{text}"
+            )),
+            &HSTRING::from(PACKAGE_NAME),
+            MB_OK | MB_ICONWARNING,
+        );
+    });
+}
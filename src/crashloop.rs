@@ -0,0 +1,102 @@
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+    thread,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use anyhow::{Context, Result};
+#[allow(unused_imports)]
+use tracing::{debug, error, info, trace, warn};
+use windows::{
+    core::HSTRING,
+    Win32::{
+        Foundation::HWND,
+        UI::WindowsAndMessaging::{MessageBoxW, MB_ICONWARNING, MB_OK},
+    },
+};
+
+use crate::{LogExt, PACKAGE_NAME};
+
+/// how many runs may end without exiting cleanly within `WINDOW` before safe mode.
+const THRESHOLD: usize = 3;
+
+/// how recent the runs that didn't exit cleanly have to be to count.
+const WINDOW: Duration = Duration::from_secs(5 * 60);
+
+/// the starts of the runs that haven't exited cleanly yet, one Unix time per line, e.g.
+/// `vscode-cjk-toggle-terminal-fixer.starts`, removed on a clean exit.
+fn path(app_path: &Path) -> PathBuf {
+    app_path.with_extension("starts")
+}
+
+/// records the start and returns whether to start in safe mode, with the defaults rather than the
+/// config file, because the last runs kept crashing, e.g. on a bad config or a broken icon pack.
+///
+/// note: the user is told once per crash loop, a safe mode crashing as well isn't repeated.
+pub fn enter(app_path: &Path) -> bool {
+    let path = path(app_path);
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default();
+    let mut starts = match fs::read_to_string(&path) {
+        Ok(text) => text
+            .lines()
+            .filter_map(|line| line.trim().parse().ok())
+            .map(Duration::from_secs)
+            .filter(|&start| now.saturating_sub(start) < WINDOW)
+            .collect(),
+        Err(err) if err.kind() == io::ErrorKind::NotFound => Vec::new(),
+        Err(err) => {
+            warn!("failed to read {path:?}: {err}");
+            Vec::new()
+        }
+    };
+    let crashes = starts.len();
+    starts.push(now);
+    let text: String = starts
+        .iter()
+        .map(|start| format!("{}\n", start.as_secs()))
+        .collect();
+    fs::write(&path, text)
+        .with_context(|| format!("failed to write {path:?}"))
+        .warn();
+
+    if crashes < THRESHOLD {
+        return false;
+    }
+    warn!("{crashes} runs within {WINDOW:?} didn't exit cleanly, starting in safe mode");
+    if crashes == THRESHOLD {
+        notify(crashes);
+    }
+    true
+}
+
+/// records the clean exit.
+pub fn leave(app_path: &Path) -> Result<()> {
+    let path = path(app_path);
+    match fs::remove_file(&path) {
+        Err(err) if err.kind() != io::ErrorKind::NotFound => {
+            Err(err).with_context(|| format!("failed to remove {path:?}"))
+        }
+        _ => Ok(()),
+    }
+}
+
+fn notify(crashes: usize) {
+    thread::spawn(move || {
+        let text = format!(
+            "{PACKAGE_NAME} stopped unexpectedly {crashes} times within a few minutes, so it \
+             started in safe mode, with the default settings and icons.\n\nCheck the config file \
+             and the icon pack, then choose \"Restart\" in the tray."
+        );
+        unsafe {
+            MessageBoxW(
+                HWND(0),
+                &HSTRING::from(text),
+                &HSTRING::from(PACKAGE_NAME),
+                MB_OK | MB_ICONWARNING,
+            )
+        };
+    });
+}
@@ -0,0 +1,35 @@
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+#[allow(unused_imports)]
+use tracing::{debug, error, info, trace, warn};
+use windows::Win32::{
+    Foundation::HWND,
+    UI::Shell::{IVirtualDesktopManager, VirtualDesktopManager},
+};
+
+use crate::{com, LogExt};
+
+/// what becomes of a hotkey whose window is on another virtual desktop.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Policy {
+    /// skipped, the tray tells why nothing happened.
+    #[default]
+    Skip,
+    /// switches to the window's desktop by activating the window, then toggles.
+    Switch,
+}
+
+/// whether the window is on a virtual desktop other than the current one.
+///
+/// note: `false` if it can't be told, e.g. for a window that closed meanwhile.
+pub fn is_elsewhere(hwnd: HWND) -> bool {
+    with(|manager| Ok(unsafe { manager.IsWindowOnCurrentVirtualDesktop(hwnd)? }))
+        .warn()
+        .is_some_and(|current| !current.as_bool())
+}
+
+/// runs `f` with the virtual desktop manager of the calling thread, created on first use.
+fn with<T>(f: impl FnOnce(&IVirtualDesktopManager) -> Result<T>) -> Result<T> {
+    com::with(&VirtualDesktopManager, f)
+}
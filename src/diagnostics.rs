@@ -0,0 +1,128 @@
+use std::{env, fs, path::Path};
+
+use anyhow::{ensure, Context, Result};
+use windows::{
+    core::{w, HSTRING, PCWSTR},
+    Win32::{
+        Foundation::HWND,
+        System::Registry::{RegGetValueW, HKEY_LOCAL_MACHINE, RRF_RT_REG_SZ},
+        UI::{Shell::ShellExecuteW, WindowsAndMessaging::SW_SHOWNORMAL},
+    },
+};
+
+use crate::{config::Config, PACKAGE_VERSION};
+
+/// the build number of Windows, e.g. `22631`.
+///
+/// note: `GetVersionEx` reports Windows 8 to executables without a compatibility manifest, the
+/// registry doesn't.
+pub fn os_build() -> Result<u32> {
+    let mut buffer = [0u16; 32];
+    let mut buffer_size = std::mem::size_of_val(&buffer) as u32;
+    unsafe {
+        RegGetValueW(
+            HKEY_LOCAL_MACHINE,
+            w!(r"SOFTWARE\Microsoft\Windows NT\CurrentVersion"),
+            w!("CurrentBuildNumber"),
+            RRF_RT_REG_SZ,
+            None,
+            Some(buffer.as_mut_ptr().cast()),
+            Some(&mut buffer_size),
+        )?
+    };
+    // note: the size includes the terminating null.
+    let units = (buffer_size as usize / 2).saturating_sub(1);
+    let build = String::from_utf16_lossy(&buffer[..units]);
+    build
+        .parse()
+        .with_context(|| format!("invalid build number: {build:?}"))
+}
+
+/// a coarse bucket of the Windows version, too coarse to tell machines apart.
+pub fn os_bucket() -> &'static str {
+    match os_build() {
+        Ok(22000..) => "Windows 11",
+        Ok(10240..) => "Windows 10",
+        Ok(_) => "Windows 8.1 or older",
+        Err(_) => "unknown",
+    }
+}
+
+/// how much of the end of the log goes into a report, GitHub rejects overly long URLs.
+const LOG_EXCERPT_LEN: usize = 4000;
+
+/// opens GitHub's new-issue page with the versions, the config and the end of the log filled in.
+pub fn report_problem(config_path: &Path, log_path: &Path) -> Result<()> {
+    let url = format!(
+        "{}/issues/new?body={}",
+        env!("CARGO_PKG_REPOSITORY"),
+        percent_encode(&report(config_path, log_path))
+    );
+    let result = unsafe {
+        ShellExecuteW(
+            HWND(0),
+            w!("open"),
+            &HSTRING::from(url),
+            PCWSTR::null(),
+            PCWSTR::null(),
+            SW_SHOWNORMAL,
+        )
+    };
+    // note: values above 32 indicate success.
+    ensure!(
+        result.0 > 32,
+        "failed to open the browser: {}",
+        windows::core::Error::from_win32()
+    );
+    Ok(())
+}
+
+/// the body of a bug report, with the user's name and profile path redacted.
+pub fn report(config_path: &Path, log_path: &Path) -> String {
+    let config = match Config::load(config_path) {
+        Ok(config) => format!("{config:#?}"),
+        Err(err) => format!("{err:?}"),
+    };
+    let log = fs::read_to_string(log_path).unwrap_or_default();
+    let mut excerpt_start = log.len().saturating_sub(LOG_EXCERPT_LEN);
+    while !log.is_char_boundary(excerpt_start) {
+        excerpt_start += 1;
+    }
+    // note: start at a line boundary so the excerpt doesn't begin mid-line.
+    let excerpt = match log[excerpt_start..].split_once('\n') {
+        Some((_, rest)) if excerpt_start > 0 => rest,
+        _ => &log[excerpt_start..],
+    };
+
+    redact(&format!(
+        "**Describe the problem**\n\n\n\n\
+         **Environment**\n\n\
+         - version: {PACKAGE_VERSION}\n\
+         - os: {} (build {})\n\n\
+         **Config**\n\n```\n{config}\n```\n\n\
+         **Log**\n\n```\n{excerpt}\n```\n",
+        os_bucket(),
+        os_build().map_or_else(|_| "unknown".into(), |build| build.to_string()),
+    ))
+}
+
+fn redact(text: &str) -> String {
+    let mut text = text.to_owned();
+    for (var, placeholder) in [("USERPROFILE", "%USERPROFILE%"), ("USERNAME", "<user>")] {
+        if let Some(value) = env::var(var).ok().filter(|value| !value.is_empty()) {
+            text = text.replace(&value, placeholder);
+        }
+    }
+    text
+}
+
+fn percent_encode(text: &str) -> String {
+    text.bytes()
+        .map(|byte| match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' => {
+                (byte as char).to_string()
+            }
+            _ => format!("%{byte:02X}"),
+        })
+        .collect()
+}
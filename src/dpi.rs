@@ -0,0 +1,239 @@
+use std::{
+    mem,
+    sync::atomic::{AtomicIsize, Ordering},
+};
+
+use anyhow::{ensure, Result};
+#[allow(unused_imports)]
+use tracing::{debug, error, info, trace, warn};
+use windows::{
+    core::{w, PCWSTR},
+    Win32::{
+        Foundation::{HWND, LPARAM, LRESULT, POINT, WPARAM},
+        Graphics::Gdi::{
+            GetMonitorInfoW, MonitorFromPoint, MonitorFromWindow, HMONITOR, MONITORINFO,
+            MONITOR_DEFAULTTONULL, MONITOR_DEFAULTTOPRIMARY,
+        },
+        System::{LibraryLoader::GetModuleHandleW, Threading::GetCurrentThreadId},
+        UI::{
+            HiDpi::{
+                GetDpiForMonitor, GetDpiForSystem, GetSystemMetricsForDpi,
+                SetProcessDpiAwarenessContext, DPI_AWARENESS_CONTEXT_PER_MONITOR_AWARE_V2,
+                MDT_EFFECTIVE_DPI,
+            },
+            WindowsAndMessaging::{
+                CreateWindowExW, DefWindowProcW, DestroyWindow, FindWindowW, GetCursorPos,
+                GetSystemMetrics, PostMessageW, PostThreadMessageW, RegisterClassW,
+                SetProcessDPIAware, SetWindowPos, HMENU, HWND_TOP, SM_CXSMICON, SPI_SETWORKAREA,
+                SWP_NOACTIVATE, SWP_NOSIZE, SWP_NOZORDER, WM_APP, WM_DISPLAYCHANGE, WM_DPICHANGED,
+                WM_SETTINGCHANGE, WM_THEMECHANGED, WNDCLASSW, WS_EX_TOOLWINDOW, WS_POPUP,
+            },
+        },
+    },
+};
+
+use crate::{
+    compat::{self, Feature},
+    theme, LogExt,
+};
+
+/// posted to the message pump when the scaling or the layout of the monitors changed.
+pub const WM_DPI: u32 = WM_APP + 9;
+
+/// posted to the window following the taskbar once the tray icon was clicked on another monitor.
+const WM_TRAY_MOVED: u32 = WM_APP + 18;
+
+/// the class of the window following the taskbar.
+const CLASS: PCWSTR = w!("vscode-cjk-toggle-terminal-fixer-dpi");
+
+/// the monitor the tray icon was last clicked on, 0 until it was.
+static TRAY_MONITOR: AtomicIsize = AtomicIsize::new(0);
+
+/// opts into per-monitor DPI awareness, so the metrics aren't scaled to 96 DPI and the tray icon is
+/// rendered at its real size instead of being stretched by Windows.
+///
+/// note: the docked VSCode window is placed in physical pixels then, as are the monitor bounds.
+pub fn set_aware() -> Result<()> {
+    if !compat::has(Feature::PerMonitorDpiV2) {
+        ensure!(
+            unsafe { SetProcessDPIAware() }.as_bool(),
+            "failed to opt into system DPI awareness"
+        );
+        return Ok(());
+    }
+    unsafe { SetProcessDpiAwarenessContext(DPI_AWARENESS_CONTEXT_PER_MONITOR_AWARE_V2)? };
+    Ok(())
+}
+
+/// the size of the tray icon in pixels, e.g. 20 at 125% scaling of the taskbar's monitor.
+pub fn small_icon_size() -> u32 {
+    // note: an older Windows scales everything with the primary monitor.
+    if !compat::has(Feature::PerMonitorDpiV2) {
+        return unsafe { GetSystemMetrics(SM_CXSMICON) as u32 };
+    }
+    unsafe { GetSystemMetricsForDpi(SM_CXSMICON, taskbar()) as u32 }
+}
+
+/// the DPI of the monitor hosting the taskbar and the notification area, which isn't necessarily
+/// the primary one.
+pub fn taskbar() -> u32 {
+    let (mut dpi_x, mut dpi_y) = (0, 0);
+    match unsafe { GetDpiForMonitor(taskbar_monitor(), MDT_EFFECTIVE_DPI, &mut dpi_x, &mut dpi_y) }
+        .warn()
+    {
+        Some(()) => dpi_x,
+        None => unsafe { GetDpiForSystem() },
+    }
+}
+
+/// the monitor the tray icon was last clicked on, with "show taskbar on all displays" maybe a
+/// secondary taskbar's, or else the main taskbar's.
+///
+/// note: without a taskbar, e.g. while Explorer restarts, the primary monitor is assumed.
+fn taskbar_monitor() -> HMONITOR {
+    let clicked = HMONITOR(TRAY_MONITOR.load(Ordering::Relaxed));
+    let mut info = MONITORINFO {
+        cbSize: mem::size_of::<MONITORINFO>() as u32,
+        ..Default::default()
+    };
+    // note: the monitor may have been disconnected since.
+    if clicked.0 != 0 && unsafe { GetMonitorInfoW(clicked, &mut info) }.as_bool() {
+        return clicked;
+    }
+    unsafe {
+        let taskbar = FindWindowW(w!("Shell_TrayWnd"), PCWSTR::null());
+        MonitorFromWindow(taskbar, MONITOR_DEFAULTTOPRIMARY)
+    }
+}
+
+/// remembers the monitor under the cursor as the one showing the tray icon, called once it was
+/// clicked, the icons are loaded again for that monitor's scaling if it's another one.
+///
+/// note: the theme of the taskbars is the same on every monitor, only the scaling differs.
+pub fn track_tray_click() {
+    let mut point = POINT::default();
+    if unsafe { GetCursorPos(&mut point) }.warn().is_none() {
+        return;
+    }
+    let monitor = unsafe { MonitorFromPoint(point, MONITOR_DEFAULTTONULL) };
+    if monitor.0 == 0 || TRAY_MONITOR.swap(monitor.0, Ordering::Relaxed) == monitor.0 {
+        return;
+    }
+    debug!("the tray icon was clicked on monitor {:#x}", monitor.0);
+    unsafe {
+        let hwnd = FindWindowW(CLASS, PCWSTR::null());
+        if hwnd != HWND(0) {
+            PostMessageW(hwnd, WM_TRAY_MOVED, WPARAM(0), LPARAM(0)).warn();
+        }
+    }
+}
+
+/// receives the DPI changes of the taskbar's monitor and the changes of the monitor layout, e.g.
+/// the taskbar moving to another monitor, as well as the theme changes.
+///
+/// note: only top-level windows get `WM_DPICHANGED`, so a hidden one is kept on the taskbar's
+/// monitor instead of a message-only one.
+pub fn watch() -> Result<HWND> {
+    unsafe {
+        let instance = GetModuleHandleW(PCWSTR::null())?;
+        let class = WNDCLASSW {
+            lpfnWndProc: Some(on_message),
+            hInstance: instance.into(),
+            lpszClassName: CLASS,
+            ..Default::default()
+        };
+        ensure!(
+            RegisterClassW(&class) != 0,
+            "failed to register the window class: {}",
+            windows::core::Error::from_win32()
+        );
+        let hwnd = CreateWindowExW(
+            WS_EX_TOOLWINDOW,
+            class.lpszClassName,
+            PCWSTR::null(),
+            WS_POPUP,
+            0,
+            0,
+            0,
+            0,
+            HWND(0),
+            HMENU(0),
+            instance,
+            None,
+        );
+        ensure!(
+            hwnd != HWND(0),
+            "failed to create the window: {}",
+            windows::core::Error::from_win32()
+        );
+        follow_taskbar(hwnd);
+        Ok(hwnd)
+    }
+}
+
+pub fn unwatch(hwnd: HWND) {
+    unsafe { DestroyWindow(hwnd).warn() };
+}
+
+/// moves the window onto the taskbar's monitor, so it gets that monitor's DPI changes.
+fn follow_taskbar(hwnd: HWND) {
+    let mut info = MONITORINFO {
+        cbSize: mem::size_of::<MONITORINFO>() as u32,
+        ..Default::default()
+    };
+    unsafe {
+        if !GetMonitorInfoW(taskbar_monitor(), &mut info).as_bool() {
+            return;
+        }
+        SetWindowPos(
+            hwnd,
+            HWND_TOP,
+            info.rcMonitor.left,
+            info.rcMonitor.top,
+            0,
+            0,
+            SWP_NOSIZE | SWP_NOZORDER | SWP_NOACTIVATE,
+        )
+        .warn();
+    }
+}
+
+unsafe extern "system" fn on_message(
+    hwnd: HWND,
+    msg: u32,
+    wparam: WPARAM,
+    lparam: LPARAM,
+) -> LRESULT {
+    // note: the broadcast carries the changed setting, a theme change is "ImmersiveColorSet".
+    // note: Windows 11 sometimes changes the mode with `WM_THEMECHANGED` only.
+    let theme_changed = msg == WM_THEMECHANGED
+        || (msg == WM_SETTINGCHANGE
+            && lparam.0 != 0
+            && PCWSTR(lparam.0 as *const u16).as_wide() == w!("ImmersiveColorSet").as_wide());
+    if theme_changed {
+        PostThreadMessageW(GetCurrentThreadId(), theme::WM_THEME, WPARAM(0), LPARAM(0)).warn();
+    }
+    let changed = match msg {
+        WM_DPICHANGED => true,
+        WM_DISPLAYCHANGE | WM_TRAY_MOVED => {
+            follow_taskbar(hwnd);
+            true
+        }
+        // note: the work area changes when the taskbar is moved to another monitor.
+        WM_SETTINGCHANGE if wparam.0 as u32 == SPI_SETWORKAREA.0 => {
+            follow_taskbar(hwnd);
+            true
+        }
+        _ => false,
+    };
+    if changed {
+        debug!("display change: {msg:#X}");
+        // note: the tray thread owns the icons, the message pump forwards the change to it.
+        PostThreadMessageW(GetCurrentThreadId(), WM_DPI, WPARAM(0), LPARAM(0)).warn();
+        // note: the suggested rectangle of `WM_DPICHANGED` is ignored, the window has no size.
+        if msg == WM_DPICHANGED {
+            return LRESULT(0);
+        }
+    }
+    DefWindowProcW(hwnd, msg, wparam, lparam)
+}
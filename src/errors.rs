@@ -0,0 +1,97 @@
+use std::{any::Any, fmt};
+
+use windows::{
+    core::HRESULT,
+    Win32::Foundation::{
+        ERROR_ACCESS_DENIED, ERROR_HOTKEY_ALREADY_REGISTERED, ERROR_INVALID_WINDOW_HANDLE,
+        E_ACCESSDENIED,
+    },
+};
+
+/// the context of a registry operation, its access denied is told apart from a window's.
+#[derive(Debug)]
+pub struct Registry(pub String);
+
+impl fmt::Display for Registry {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+/// the errors users run into, worded for them, the first matching entry wins.
+///
+/// note: `true` restricts an entry to registry operations.
+const MESSAGES: [(HRESULT, bool, &str); 5] = [
+    (
+        ERROR_HOTKEY_ALREADY_REGISTERED.to_hresult(),
+        false,
+        "the shortcut is taken by Windows or another application, pick another chord",
+    ),
+    (
+        ERROR_ACCESS_DENIED.to_hresult(),
+        true,
+        "Windows denied changing the registry, e.g. a policy of your organization locks it",
+    ),
+    (
+        ERROR_ACCESS_DENIED.to_hresult(),
+        false,
+        "Windows denied access, the other program probably runs as administrator",
+    ),
+    (
+        E_ACCESSDENIED,
+        false,
+        "Windows denied access, the other program probably runs as administrator",
+    ),
+    (
+        ERROR_INVALID_WINDOW_HANDLE.to_hresult(),
+        false,
+        "the window was closed in the meantime",
+    ),
+];
+
+fn message(code: HRESULT, registry: bool) -> Option<&'static str> {
+    MESSAGES
+        .iter()
+        .find(|&&(c, r, _)| c == code && (registry || !r))
+        .map(|&(_, _, message)| message)
+}
+
+/// the error for a notification, the Windows errors in words for the user or in the system's
+/// language rather than as an HRESULT.
+pub fn describe(err: &anyhow::Error) -> String {
+    let registry = err.downcast_ref::<Registry>().is_some();
+    let causes: Vec<_> = err
+        .chain()
+        .map(|cause| match cause.downcast_ref::<windows::core::Error>() {
+            Some(err) => describe_windows(err, registry),
+            None => cause.to_string(),
+        })
+        .collect();
+    causes.join(": ")
+}
+
+fn describe_windows(err: &windows::core::Error, registry: bool) -> String {
+    if let Some(message) = message(err.code(), registry) {
+        return message.to_owned();
+    }
+    // note: formatted by Windows in the user's language, without the trailing period and newline.
+    let text = err.message().to_string_lossy();
+    match text.trim_end().trim_end_matches('.') {
+        "" => err.to_string(),
+        text => text.to_owned(),
+    }
+}
+
+/// what the error means for the user, added to the log line, if it's one of `MESSAGES`.
+pub fn hint(err: &dyn Any) -> Option<&'static str> {
+    if let Some(err) = err.downcast_ref::<anyhow::Error>() {
+        let registry = err.downcast_ref::<Registry>().is_some();
+        err.chain()
+            .filter_map(|cause| cause.downcast_ref::<windows::core::Error>())
+            .find_map(|err| message(err.code(), registry))
+    } else if let Some(err) = err.downcast_ref::<windows::core::Error>() {
+        message(err.code(), false)
+    } else {
+        None
+    }
+}
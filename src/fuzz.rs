@@ -0,0 +1,30 @@
+/// how many inputs each parser is tried with.
+///
+/// note: a smoke test for `cargo test`, the targets in `fuzz/` run far longer with coverage
+/// feedback.
+pub const RUNS: usize = 10_000;
+
+/// arbitrary strings of up to 16 pieces, mostly from `pieces` so they get past the first check of
+/// the grammar, the same ones every run so a failure can be reproduced.
+pub fn strings(pieces: &[&str]) -> impl Iterator<Item = String> {
+    let pieces: Vec<String> = pieces
+        .iter()
+        .copied()
+        .chain(["\0", "\n", "é", "日", "\u{1F600}"])
+        .map(str::to_owned)
+        .collect();
+    let mut state = 0x2545_f491_4f6c_dd1d_u64;
+    let mut next = move || {
+        // note: xorshift, good enough to wander the grammar.
+        state ^= state << 13;
+        state ^= state >> 7;
+        state ^= state << 17;
+        state as usize
+    };
+    (0..RUNS).map(move |_| {
+        let len = next() % 17;
+        (0..len)
+            .map(|_| pieces[next() % pieces.len()].as_str())
+            .collect()
+    })
+}
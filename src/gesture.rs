@@ -0,0 +1,329 @@
+use std::{
+    cell::{Cell, RefCell},
+    fmt,
+};
+
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+#[allow(unused_imports)]
+use tracing::{debug, error, info, trace, warn};
+use windows::Win32::{
+    Foundation::{LPARAM, WPARAM},
+    System::Threading::GetCurrentThreadId,
+    UI::{
+        Input::KeyboardAndMouse::{
+            HOT_KEY_MODIFIERS, MOD_ALT, MOD_CONTROL, MOD_SHIFT, VIRTUAL_KEY, VK_CONTROL,
+            VK_LCONTROL, VK_LMENU, VK_LSHIFT, VK_MENU, VK_RCONTROL, VK_RMENU, VK_RSHIFT, VK_SHIFT,
+        },
+        WindowsAndMessaging::{PostThreadMessageW, WM_APP},
+    },
+};
+
+use crate::{
+    config::Config,
+    hotkey::{Chord, Hotkey},
+    inject::Action,
+    keyhook::{self, Key, Listener},
+    rules, LogExt,
+};
+
+/// posted to the message pump with the id of the gesture's hotkey in `wParam` once it's performed.
+pub const WM_GESTURE: u32 = WM_APP + 12;
+
+/// the ids of the gestures' hotkeys start here.
+const ID_BASE: usize = 2600;
+
+/// a modifier tapped twice, e.g. `{ double_tap = "Ctrl", action = "toggle_terminal" }`.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+pub struct GestureRule {
+    pub double_tap: Modifier,
+    pub action: Action,
+    /// switched off from the tray's "Rules" menu, kept to be switched on again.
+    #[serde(default = "rules::enabled", skip_serializing_if = "rules::is_enabled")]
+    pub enabled: bool,
+}
+
+impl fmt::Display for GestureRule {
+    /// the TOML inline table the rule is read from.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let double_tap = toml::Value::try_from(self.double_tap).map_err(|_| fmt::Error)?;
+        let action = toml::Value::try_from(self.action).map_err(|_| fmt::Error)?;
+        write!(f, "{{ double_tap = {double_tap}, action = {action}")?;
+        if !self.enabled {
+            write!(f, ", enabled = false")?;
+        }
+        write!(f, " }}")
+    }
+}
+
+/// the `gestures` key of the config file holding the rules.
+pub fn gestures_value(rules: &[GestureRule]) -> String {
+    let rules: Vec<_> = rules.iter().map(ToString::to_string).collect();
+    format!("gestures = [{}]", rules.join(", "))
+}
+
+/// the modifiers a gesture can be made with, either the left or the right one.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+pub enum Modifier {
+    Ctrl,
+    Shift,
+    Alt,
+}
+
+impl Modifier {
+    fn of(vk: VIRTUAL_KEY) -> Option<Self> {
+        match vk {
+            VK_CONTROL | VK_LCONTROL | VK_RCONTROL => Some(Self::Ctrl),
+            VK_SHIFT | VK_LSHIFT | VK_RSHIFT => Some(Self::Shift),
+            VK_MENU | VK_LMENU | VK_RMENU => Some(Self::Alt),
+            _ => None,
+        }
+    }
+
+    /// the chord of the modifier alone, which is how the gesture shows up in the logs.
+    fn chord(self) -> Chord {
+        let (modifiers, vk): (HOT_KEY_MODIFIERS, _) = match self {
+            Self::Ctrl => (MOD_CONTROL, VK_CONTROL),
+            Self::Shift => (MOD_SHIFT, VK_SHIFT),
+            Self::Alt => (MOD_ALT, VK_MENU),
+        };
+        Chord { modifiers, vk }
+    }
+}
+
+/// how far a double tap has come, with the time of its latest key event.
+#[derive(Debug, Clone, Copy)]
+enum Tap {
+    Idle,
+    Down(Modifier, u32),
+    Tapped(Modifier, u32),
+    Again(Modifier, u32),
+}
+
+struct Gestures {
+    hotkeys: Vec<Hotkey>,
+    /// the longest a tap and the pause between the taps may take, in milliseconds.
+    interval: u32,
+}
+
+thread_local! {
+    static GESTURES: RefCell<Option<Gestures>> = const { RefCell::new(None) };
+    static TAP: Cell<Tap> = const { Cell::new(Tap::Idle) };
+}
+
+/// recognizes the gestures in config with the keyboard hook and posts `WM_GESTURE` for them.
+///
+/// note: the modifier isn't swallowed, the focused window sees the taps as usual.
+pub fn hook(config: &Config) -> Result<()> {
+    unhook();
+    if !config.gestures.iter().any(|rule| rule.enabled) {
+        return Ok(());
+    }
+    let hotkeys = config
+        .gestures
+        .iter()
+        .enumerate()
+        .filter(|(_, rule)| rule.enabled)
+        .map(|(i, rule)| Hotkey {
+            id: ID_BASE + i,
+            chord: rule.double_tap.chord(),
+            then: None,
+            action: rule.action,
+            trigger: 0,
+        })
+        .collect();
+    GESTURES.with(|cell| {
+        *cell.borrow_mut() = Some(Gestures {
+            hotkeys,
+            interval: config.double_tap_ms,
+        })
+    });
+    keyhook::listen(Listener::Gesture, true)
+}
+
+pub fn unhook() {
+    TAP.with(|tap| tap.set(Tap::Idle));
+    if GESTURES.with(|cell| cell.borrow_mut().take()).is_some() {
+        keyhook::listen(Listener::Gesture, false).warn();
+    }
+}
+
+/// a gesture's hotkey, by the id `WM_GESTURE` carries in `wParam`.
+pub fn find(id: usize) -> Option<Hotkey> {
+    GESTURES.with(|cell| {
+        cell.borrow()
+            .as_ref()?
+            .hotkeys
+            .iter()
+            .find(|hotkey| hotkey.id == id)
+            .copied()
+    })
+}
+
+pub fn count() -> usize {
+    GESTURES.with(|cell| cell.borrow().as_ref().map_or(0, |hook| hook.hotkeys.len()))
+}
+
+/// the next state after the key event, and whether it completes a double tap.
+///
+/// note: a tap is only a press released in time, holding the modifier for a chord doesn't count.
+fn advance(
+    tap: Tap,
+    modifier: Option<Modifier>,
+    down: bool,
+    time: u32,
+    interval: u32,
+) -> (Tap, bool) {
+    let within = |since: u32| time.wrapping_sub(since) <= interval;
+    match (tap, modifier, down) {
+        // note: a held key repeats its press.
+        (Tap::Down(held, _) | Tap::Again(held, _), Some(modifier), true) if held == modifier => {
+            (tap, false)
+        }
+        (Tap::Tapped(tapped, since), Some(modifier), true)
+            if tapped == modifier && within(since) =>
+        {
+            (Tap::Again(modifier, time), false)
+        }
+        (_, Some(modifier), true) => (Tap::Down(modifier, time), false),
+        (Tap::Down(held, since), Some(modifier), false) if held == modifier && within(since) => {
+            (Tap::Tapped(modifier, time), false)
+        }
+        (Tap::Again(held, since), Some(modifier), false) if held == modifier && within(since) => {
+            (Tap::Idle, true)
+        }
+        (_, None, false) => (tap, false),
+        _ => (Tap::Idle, false),
+    }
+}
+
+/// advances the double tap with the key, posts `WM_GESTURE` once one is performed.
+pub fn on_key(key: &Key) {
+    let modifier = Modifier::of(key.vk);
+    let id = GESTURES.with(|cell| {
+        let cell = cell.borrow();
+        let hook = cell.as_ref()?;
+        let (tap, completed) = advance(
+            TAP.with(Cell::get),
+            modifier,
+            key.down,
+            key.time,
+            hook.interval,
+        );
+        TAP.with(|cell| cell.set(tap));
+        let modifier = modifier.filter(|_| completed)?;
+        hook.hotkeys
+            .iter()
+            .find(|hotkey| hotkey.chord == modifier.chord())
+            .map(|hotkey| hotkey.id)
+    });
+    if let Some(id) = id {
+        unsafe { PostThreadMessageW(GetCurrentThreadId(), WM_GESTURE, WPARAM(id), LPARAM(0)) }
+            .warn();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const INTERVAL: u32 = 300;
+
+    /// whether each key event `(modifier, down, time)` completes a double tap, starting idle.
+    fn completed(events: &[(Option<Modifier>, bool, u32)]) -> Vec<bool> {
+        let mut tap = Tap::Idle;
+        events
+            .iter()
+            .map(|&(modifier, down, time)| {
+                let completed;
+                (tap, completed) = advance(tap, modifier, down, time, INTERVAL);
+                completed
+            })
+            .collect()
+    }
+
+    const CTRL: Option<Modifier> = Some(Modifier::Ctrl);
+
+    #[test]
+    fn double_tap_completes_on_the_second_release() {
+        let events = [
+            (CTRL, true, 0),
+            (CTRL, false, 80),
+            (CTRL, true, 200),
+            (CTRL, true, 230),
+            (CTRL, false, 300),
+        ];
+        assert_eq!(completed(&events), [false, false, false, false, true]);
+    }
+
+    #[test]
+    fn double_tap_survives_the_tick_count_wrapping() {
+        let events = [
+            (CTRL, true, u32::MAX - 100),
+            (CTRL, false, u32::MAX - 20),
+            (CTRL, true, 50),
+            (CTRL, false, 120),
+        ];
+        assert_eq!(completed(&events), [false, false, false, true]);
+    }
+
+    #[test]
+    fn slow_taps_dont_complete() {
+        // note: held too long, paused too long and released too late.
+        for events in [
+            [
+                (CTRL, true, 0),
+                (CTRL, false, 400),
+                (CTRL, true, 500),
+                (CTRL, false, 550),
+            ],
+            [
+                (CTRL, true, 0),
+                (CTRL, false, 50),
+                (CTRL, true, 400),
+                (CTRL, false, 450),
+            ],
+            [
+                (CTRL, true, 0),
+                (CTRL, false, 50),
+                (CTRL, true, 100),
+                (CTRL, false, 500),
+            ],
+        ] {
+            assert_eq!(completed(&events), [false; 4], "{events:?}");
+        }
+    }
+
+    #[test]
+    fn other_keys_interrupt() {
+        let shift = Some(Modifier::Shift);
+        for events in [
+            // note: a chord like Ctrl+C, then Ctrl tapped once.
+            [
+                (CTRL, true, 0),
+                (None, true, 20),
+                (None, false, 40),
+                (CTRL, false, 60),
+            ],
+            [
+                (CTRL, true, 0),
+                (CTRL, false, 20),
+                (shift, true, 40),
+                (shift, false, 60),
+            ],
+        ] {
+            let mut events = events.to_vec();
+            events.extend([(CTRL, true, 80), (CTRL, false, 100)]);
+            assert!(!completed(&events).contains(&true), "{events:?}");
+        }
+        // note: the release of another key doesn't interrupt a tap.
+        let events = [
+            (CTRL, true, 0),
+            (None, false, 20),
+            (CTRL, false, 40),
+            (CTRL, true, 60),
+            (CTRL, false, 80),
+        ];
+        assert_eq!(completed(&events), [false, false, false, false, true]);
+    }
+}
@@ -0,0 +1,85 @@
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+    sync::Mutex,
+    thread,
+    time::Duration,
+};
+
+use anyhow::{Context, Result};
+use serde::Serialize;
+#[allow(unused_imports)]
+use tracing::{debug, error, info, trace, warn};
+
+use crate::{status, timestamp, LockExt, LogExt};
+
+/// how often the heartbeat is written, a file older than twice this means we're gone.
+const INTERVAL: Duration = Duration::from_secs(60);
+
+/// what the heartbeat file holds, the status of `--status --json` with the time it was written.
+#[derive(Serialize)]
+struct Heartbeat {
+    /// UTC in ISO 8601, e.g. `2024-05-01T17:30:00.000Z`.
+    at: String,
+    #[serde(flatten)]
+    status: status::Report,
+}
+
+/// the configured heartbeat file, written by the timer.
+static PATH: Mutex<Option<PathBuf>> = Mutex::new(None);
+
+/// writes the heartbeat to the file from now on, or stops if `None`, e.g. after the config was
+/// reloaded, removing the file written before.
+pub fn configure(path: Option<PathBuf>) {
+    let mut configured = PATH.locked();
+    if let Some(previous) = configured
+        .as_deref()
+        .filter(|&previous| Some(previous) != path.as_deref())
+    {
+        remove(previous).warn();
+    }
+    if let Some(path) = &path {
+        write(path).warn();
+    }
+    *configured = path;
+}
+
+/// starts the timer writing the heartbeat while it's configured.
+pub fn watch() -> Result<()> {
+    thread::Builder::new()
+        .name("heartbeat".to_owned())
+        .spawn(|| loop {
+            thread::sleep(INTERVAL);
+            let path = PATH.locked().clone();
+            if let Some(path) = path {
+                write(&path).warn();
+            }
+        })?;
+    Ok(())
+}
+
+/// removes the heartbeat on exit, so monitoring scripts needn't wait for it to go stale.
+pub fn stop() {
+    configure(None);
+}
+
+/// note: written to a temporary file first, a script never reads half a heartbeat.
+fn write(path: &Path) -> Result<()> {
+    let heartbeat = Heartbeat {
+        at: timestamp::now(timestamp::Timezone::Utc),
+        status: status::report(),
+    };
+    let text = serde_json::to_string_pretty(&heartbeat)? + "\n";
+    let temporary = path.with_extension("tmp");
+    fs::write(&temporary, text).with_context(|| format!("failed to write {temporary:?}"))?;
+    fs::rename(&temporary, path).with_context(|| format!("failed to write {path:?}"))
+}
+
+fn remove(path: &Path) -> Result<()> {
+    match fs::remove_file(path) {
+        Err(err) if err.kind() != io::ErrorKind::NotFound => {
+            Err(err).with_context(|| format!("failed to remove {path:?}"))
+        }
+        _ => Ok(()),
+    }
+}
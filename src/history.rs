@@ -0,0 +1,123 @@
+use std::{cell::Cell, collections::VecDeque, fmt, sync::Mutex, time::Duration};
+
+#[allow(unused_imports)]
+use tracing::{debug, error, info, trace, warn};
+
+#[cfg(feature = "settings-ui")]
+use crate::viewer;
+use crate::{
+    inject::{Action, Backend},
+    timestamp, LockExt,
+};
+
+/// how many triggers are kept in memory.
+const CAPACITY: usize = 200;
+
+/// what became of a trigger, as far as the hotkey's handler got.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Outcome {
+    /// there was no window to perform the action on.
+    #[default]
+    NoTarget,
+    /// injected via the backend, not yet verified.
+    Injected(Backend),
+    /// every backend failed.
+    Failed,
+    /// left alone on purpose, e.g. while presenting.
+    Skipped(&'static str),
+    /// the window isn't responding, the action is retried once it does.
+    Deferred,
+    /// the raw key was handed to VSCode to see whether it handles it on its own.
+    Probing,
+    /// VSCode wasn't running and was launched.
+    Launched,
+}
+
+impl fmt::Display for Outcome {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::NoTarget => f.write_str("no target window"),
+            Self::Injected(backend) => write!(f, "injected via {backend:?}"),
+            Self::Failed => f.write_str("failed, no backend succeeded"),
+            Self::Skipped(reason) => write!(f, "skipped, {reason}"),
+            Self::Deferred => f.write_str("deferred, the window isn't responding"),
+            Self::Probing => f.write_str("probing whether VSCode handles the key"),
+            Self::Launched => f.write_str("launched VSCode"),
+        }
+    }
+}
+
+/// a hotkey pressed and what became of it.
+#[derive(Debug, Clone)]
+pub struct Record {
+    /// in local time, which is what the user remembers pressing it at.
+    pub at: String,
+    pub action: Action,
+    /// the executable of the target window, e.g. `Code.exe`.
+    pub target: Option<String>,
+    pub outcome: Outcome,
+    pub latency: Duration,
+}
+
+impl fmt::Display for Record {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}  {}  {}  {}  {} ms",
+            self.at,
+            self.action,
+            self.target.as_deref().unwrap_or("-"),
+            self.outcome,
+            self.latency.as_millis()
+        )
+    }
+}
+
+/// the latest triggers, oldest first.
+static RECORDS: Mutex<VecDeque<Record>> = Mutex::new(VecDeque::new());
+
+#[cfg(feature = "settings-ui")]
+static WINDOW: viewer::Window = viewer::Window::new();
+
+thread_local! {
+    /// the outcome of the trigger being handled, noted along the way.
+    static OUTCOME: Cell<Outcome> = const { Cell::new(Outcome::NoTarget) };
+}
+
+/// starts handling a trigger, forgetting what was noted since the last one, e.g. by a retry.
+pub fn begin() {
+    OUTCOME.with(|cell| cell.set(Outcome::NoTarget));
+}
+
+/// notes what became of the trigger being handled, the last note wins, e.g. of several windows.
+pub fn note(outcome: Outcome) {
+    OUTCOME.with(|cell| cell.set(outcome));
+}
+
+/// keeps the trigger with the outcome noted while handling it.
+pub fn record(action: Action, target: Option<String>, latency: Duration) {
+    let record = Record {
+        at: timestamp::now(timestamp::Timezone::Local),
+        action,
+        target,
+        outcome: OUTCOME.with(|cell| cell.replace(Outcome::NoTarget)),
+        latency,
+    };
+    let mut records = RECORDS.locked();
+    if records.len() == CAPACITY {
+        records.pop_front();
+    }
+    records.push_back(record);
+}
+
+/// the latest triggers, oldest first.
+#[cfg(feature = "settings-ui")]
+pub fn lines() -> Vec<String> {
+    RECORDS.locked().iter().map(ToString::to_string).collect()
+}
+
+/// shows the latest triggers in a window of their own, without blocking the caller.
+#[cfg(feature = "settings-ui")]
+pub fn show() {
+    viewer::show(&WINDOW, "Trigger History", lines);
+}
@@ -0,0 +1,784 @@
+use std::{
+    cell::{Cell, RefCell},
+    fmt,
+    str::FromStr,
+};
+
+use anyhow::{bail, ensure, Context, Result};
+use serde::{Deserialize, Serialize};
+#[allow(unused_imports)]
+use tracing::{debug, error, info, trace, warn};
+use windows::Win32::{
+    Foundation::{ERROR_HOTKEY_ALREADY_REGISTERED, HWND},
+    UI::{
+        Input::KeyboardAndMouse::{
+            GetKeyboardLayout, MapVirtualKeyExW, RegisterHotKey, ToUnicodeEx, UnregisterHotKey,
+            HOT_KEY_MODIFIERS, MAPVK_VSC_TO_VK, MOD_ALT, MOD_CONTROL, MOD_SHIFT, MOD_WIN,
+            VIRTUAL_KEY, VK_CONTROL, VK_D, VK_DELETE, VK_E, VK_ESCAPE, VK_F1, VK_F12, VK_L,
+            VK_LCONTROL, VK_LMENU, VK_LSHIFT, VK_LWIN, VK_MENU, VK_OEM_3, VK_R, VK_RCONTROL,
+            VK_RMENU, VK_RSHIFT, VK_RWIN, VK_SHIFT, VK_SPACE, VK_TAB,
+        },
+        TextServices::HKL,
+        WindowsAndMessaging::{GetForegroundWindow, GetWindowThreadProcessId, WM_APP},
+    },
+};
+
+use crate::{
+    chord,
+    config::Config,
+    gesture,
+    inject::{Action, SCANCODE_OEM_3},
+    metrics, overrides, rules, scancode, LogExt,
+};
+
+/// the keys named by a word rather than the character they type.
+const NAMED_KEYS: [(VIRTUAL_KEY, &str); 4] = [
+    (VK_TAB, "Tab"),
+    (VK_SPACE, "Space"),
+    (VK_ESCAPE, "Escape"),
+    (VK_DELETE, "Delete"),
+];
+
+/// a key pressed together with modifiers, e.g. 「Ctrl+`」.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct Chord {
+    pub modifiers: HOT_KEY_MODIFIERS,
+    pub vk: VIRTUAL_KEY,
+}
+
+impl FromStr for Chord {
+    type Err = anyhow::Error;
+
+    /// parses the notation of VSCode's keybindings, e.g. `Ctrl+Shift+K`.
+    ///
+    /// note: Windows reports AltGr as 「Ctrl+Alt」, so that's what `AltGr` stands for.
+    fn from_str(s: &str) -> Result<Self> {
+        let mut modifiers = HOT_KEY_MODIFIERS(0);
+        let mut vk = None;
+        for part in s.split('+').map(str::trim) {
+            match part.to_ascii_lowercase().as_str() {
+                "ctrl" | "control" => modifiers |= MOD_CONTROL,
+                "shift" => modifiers |= MOD_SHIFT,
+                "alt" => modifiers |= MOD_ALT,
+                "altgr" => modifiers |= MOD_CONTROL | MOD_ALT,
+                "win" | "meta" | "super" => modifiers |= MOD_WIN,
+                key => {
+                    ensure!(vk.is_none(), "more than one key in {s:?}");
+                    vk = Some(parse_key(key).with_context(|| format!("unknown key {part:?}"))?);
+                }
+            }
+        }
+
+        let vk = vk.with_context(|| format!("no key in {s:?}"))?;
+        ensure!(modifiers.0 != 0, "{s:?} has no modifier");
+        let chord = Self { modifiers, vk };
+        if let Some(reason) = chord.reserved() {
+            bail!("{s:?} is reserved by Windows, {reason}");
+        }
+        Ok(chord)
+    }
+}
+
+impl fmt::Display for Chord {
+    /// the notation `FromStr` parses, or the modifier alone for a gesture's chord.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let names: Vec<_> = [
+            (MOD_CONTROL, "Ctrl"),
+            (MOD_SHIFT, "Shift"),
+            (MOD_ALT, "Alt"),
+            (MOD_WIN, "Win"),
+        ]
+        .into_iter()
+        .filter(|(modifier, _)| self.modifiers.0 & modifier.0 != 0)
+        .map(|(_, name)| name)
+        .collect();
+        write!(f, "{}", names.join("+"))?;
+        if self.vk == VIRTUAL_KEY(0) && names.is_empty() {
+            return write!(f, "none");
+        }
+        if chord::is_modifier(self.vk) {
+            return Ok(());
+        }
+        if !names.is_empty() {
+            write!(f, "+")?;
+        }
+        if let Some((_, name)) = NAMED_KEYS.iter().find(|(vk, _)| *vk == self.vk) {
+            return write!(f, "{name}");
+        }
+        match self.vk {
+            VK_OEM_3 => write!(f, "`"),
+            vk if (VK_F1.0..VK_F1.0 + 24).contains(&vk.0) => write!(f, "F{}", vk.0 - VK_F1.0 + 1),
+            vk => write!(f, "{}", char::from(vk.0 as u8)),
+        }
+    }
+}
+
+impl Chord {
+    /// the chord of pressing the keys together, `None` unless it's modifiers and one key we can name.
+    pub fn from_keys(keys: &[VIRTUAL_KEY]) -> Option<Self> {
+        let mut modifiers = HOT_KEY_MODIFIERS(0);
+        let mut key = None;
+        for &vk in keys {
+            match vk {
+                VK_CONTROL | VK_LCONTROL | VK_RCONTROL => modifiers |= MOD_CONTROL,
+                VK_SHIFT | VK_LSHIFT | VK_RSHIFT => modifiers |= MOD_SHIFT,
+                VK_MENU | VK_LMENU | VK_RMENU => modifiers |= MOD_ALT,
+                VK_LWIN | VK_RWIN => modifiers |= MOD_WIN,
+                vk if key.is_none() && is_nameable(vk) => key = Some(vk),
+                _ => return None,
+            }
+        }
+        Some(Self {
+            modifiers,
+            vk: key.filter(|_| modifiers.0 != 0)?,
+        })
+    }
+
+    /// why no application can register the chord, if it's one of those Windows keeps to itself.
+    fn reserved(self) -> Option<&'static str> {
+        const CTRL_ALT: HOT_KEY_MODIFIERS = HOT_KEY_MODIFIERS(MOD_CONTROL.0 | MOD_ALT.0);
+        const CTRL_SHIFT: HOT_KEY_MODIFIERS = HOT_KEY_MODIFIERS(MOD_CONTROL.0 | MOD_SHIFT.0);
+        const ALT_SHIFT: HOT_KEY_MODIFIERS = HOT_KEY_MODIFIERS(MOD_ALT.0 | MOD_SHIFT.0);
+        const WIN_SHIFT: HOT_KEY_MODIFIERS = HOT_KEY_MODIFIERS(MOD_WIN.0 | MOD_SHIFT.0);
+
+        match (self.modifiers, self.vk) {
+            (_, VK_F12) => Some("F12 breaks into the debugger"),
+            (MOD_WIN, VK_L) => Some("Win+L locks the computer"),
+            (MOD_WIN, VK_D) => Some("Win+D shows the desktop"),
+            (MOD_WIN, VK_E) => Some("Win+E opens File Explorer"),
+            (MOD_WIN, VK_R) => Some("Win+R opens the Run dialog"),
+            (MOD_WIN, VK_TAB) => Some("Win+Tab opens Task View"),
+            (MOD_WIN | WIN_SHIFT, VK_SPACE) => Some("Win+Space switches the input method"),
+            (CTRL_ALT, VK_DELETE) => Some("Ctrl+Alt+Delete opens the security screen"),
+            (MOD_ALT | ALT_SHIFT, VK_TAB) => Some("Alt+Tab switches windows"),
+            (MOD_ALT | ALT_SHIFT, VK_ESCAPE) => Some("Alt+Escape cycles through windows"),
+            (MOD_CONTROL, VK_ESCAPE) => Some("Ctrl+Escape opens the Start menu"),
+            (CTRL_SHIFT, VK_ESCAPE) => Some("Ctrl+Shift+Escape opens Task Manager"),
+            _ => None,
+        }
+    }
+
+    /// the character the chord types with the keyboard layout of the foreground window, e.g. 「@」
+    /// for 「Ctrl+Alt+Q」, which is AltGr+Q on a German keyboard.
+    fn typed_character(self) -> Option<String> {
+        const PRESSED: u8 = 0x80;
+        // note: keeps the dead key state of the foreground window untouched.
+        const KEEP_KEYBOARD_STATE: u32 = 0b100;
+
+        let mut state = [0u8; 256];
+        for (modifier, vk) in [
+            (MOD_CONTROL, VK_CONTROL),
+            (MOD_SHIFT, VK_SHIFT),
+            (MOD_ALT, VK_MENU),
+        ] {
+            if self.modifiers.0 & modifier.0 != 0 {
+                state[vk.0 as usize] = PRESSED;
+            }
+        }
+        let mut buffer = [0u16; 8];
+        let len = unsafe {
+            ToUnicodeEx(
+                self.vk.0 as u32,
+                0,
+                &state,
+                &mut buffer,
+                KEEP_KEYBOARD_STATE,
+                foreground_layout(),
+            )
+        };
+        let typed = String::from_utf16_lossy(&buffer[..usize::try_from(len).ok()?]);
+        (!typed.chars().all(char::is_control)).then_some(typed)
+    }
+}
+
+/// whether `parse_key` accepts a name for the key.
+fn is_nameable(vk: VIRTUAL_KEY) -> bool {
+    let ascii = u8::try_from(vk.0).unwrap_or(0);
+    vk == VK_OEM_3
+        || NAMED_KEYS.iter().any(|(named, _)| *named == vk)
+        || ascii.is_ascii_uppercase()
+        || ascii.is_ascii_digit()
+        || (VK_F1.0..VK_F1.0 + 24).contains(&vk.0)
+}
+
+impl TryFrom<String> for Chord {
+    type Error = anyhow::Error;
+
+    fn try_from(s: String) -> Result<Self> {
+        s.parse()
+    }
+}
+
+impl From<Chord> for String {
+    fn from(chord: Chord) -> Self {
+        chord.to_string()
+    }
+}
+
+fn parse_key(key: &str) -> Option<VIRTUAL_KEY> {
+    match key {
+        "esc" => return Some(VK_ESCAPE),
+        "del" => return Some(VK_DELETE),
+        _ => {}
+    }
+    if let Some((vk, _)) = NAMED_KEYS
+        .iter()
+        .find(|(_, name)| name.eq_ignore_ascii_case(key))
+    {
+        return Some(*vk);
+    }
+    match key.as_bytes() {
+        [b'`'] => Some(VK_OEM_3),
+        // note: the virtual key codes of letters and digits are their uppercase ASCII codes.
+        &[c] if c.is_ascii_alphanumeric() => Some(VIRTUAL_KEY(c.to_ascii_uppercase() as u16)),
+        [b'f', n @ ..] => match std::str::from_utf8(n).ok()?.parse::<u16>().ok()? {
+            n @ 1..=24 => Some(VIRTUAL_KEY(VK_F1.0 + n - 1)),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+/// a hotkey from config, a single chord or two-step, e.g.
+/// `{ keys = ["Ctrl+K", "Ctrl+`"], action = "focus_terminal" }`.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+pub struct ChordRule {
+    pub keys: Vec<Chord>,
+    pub action: Action,
+    /// switched off from the tray's "Rules" menu, kept to be switched on again.
+    #[serde(default = "rules::enabled", skip_serializing_if = "rules::is_enabled")]
+    pub enabled: bool,
+}
+
+impl ChordRule {
+    /// the same keys and action, whether enabled or not.
+    pub fn is_same(&self, other: &Self) -> bool {
+        self.keys == other.keys && self.action == other.action
+    }
+}
+
+impl fmt::Display for ChordRule {
+    /// the TOML inline table the rule is read from.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let keys: Vec<_> = self
+            .keys
+            .iter()
+            .map(|chord| format!("{:?}", chord.to_string()))
+            .collect();
+        let action = toml::Value::try_from(self.action).map_err(|_| fmt::Error)?;
+        write!(f, "{{ keys = [{}], action = {action}", keys.join(", "))?;
+        if !self.enabled {
+            write!(f, ", enabled = false")?;
+        }
+        write!(f, " }}")
+    }
+}
+
+/// the `chords` key of the config file holding the rules.
+pub fn chords_value(rules: &[ChordRule]) -> String {
+    let rules: Vec<_> = rules.iter().map(ToString::to_string).collect();
+    format!("chords = [{}]", rules.join(", "))
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Hotkey {
+    /// note: any value is acceptable as long as it's unique among our hotkeys.
+    pub id: usize,
+    /// the registered chord.
+    pub chord: Chord,
+    /// the second chord of a two-step hotkey, captured by a keyboard hook after the first one.
+    pub then: Option<Chord>,
+    pub action: Action,
+    /// counts the presses, logged with everything done for one so it can be followed across the
+    /// message pump, 0 until pressed.
+    pub trigger: u64,
+}
+
+impl Hotkey {
+    /// the keys VSCode binds the action to, the modifiers are still held by the user.
+    pub fn keys(&self) -> impl Iterator<Item = VIRTUAL_KEY> {
+        [Some(self.chord), self.then]
+            .into_iter()
+            .flatten()
+            .map(|chord| chord.vk)
+            .filter(|&vk| vk != VIRTUAL_KEY(0))
+    }
+
+    /// the hotkey as pressed just now, with the next trigger id.
+    pub fn pressed(self) -> Self {
+        let trigger = TRIGGERS.with(|triggers| {
+            triggers.set(triggers.get() + 1);
+            triggers.get()
+        });
+        Self { trigger, ..self }
+    }
+
+    /// whether no modifiers are held by the time the action is injected, e.g. for a gesture of a
+    /// modifier alone or an action triggered from the pipe.
+    pub fn without_modifiers(&self) -> bool {
+        self.chord.modifiers.0 == 0 || chord::is_modifier(self.chord.vk)
+    }
+
+    /// the hotkey as performed later than pressed, e.g. once VSCode launched, keeping its id and
+    /// trigger: as if unpressed unless the user still holds the modifiers, without them a bare key
+    /// would be typed.
+    pub fn deferred(&self) -> Self {
+        let held = self.then.unwrap_or(self.chord).modifiers;
+        if self.without_modifiers() || chord::held_modifiers() == held {
+            return *self;
+        }
+        debug!("the modifiers of {} were released meanwhile", self.chord);
+        Self {
+            id: self.id,
+            trigger: self.trigger,
+            ..unpressed(self.action)
+        }
+    }
+}
+
+/// an action triggered without a key press, e.g. via the pipe, its chord has no keys.
+pub fn unpressed(action: Action) -> Hotkey {
+    Hotkey {
+        id: UNPRESSED_ID,
+        chord: Chord {
+            modifiers: HOT_KEY_MODIFIERS(0),
+            vk: VIRTUAL_KEY(0),
+        },
+        then: None,
+        action,
+        trigger: 0,
+    }
+}
+
+const CTRL_OEM_3: Hotkey = Hotkey {
+    id: 2333,
+    chord: Chord {
+        modifiers: MOD_CONTROL,
+        vk: VK_OEM_3,
+    },
+    then: None,
+    action: Action::ToggleTerminal,
+    trigger: 0,
+};
+
+const CTRL_SHIFT_OEM_3: Hotkey = Hotkey {
+    id: 2334,
+    chord: Chord {
+        modifiers: HOT_KEY_MODIFIERS(MOD_CONTROL.0 | MOD_SHIFT.0),
+        vk: VK_OEM_3,
+    },
+    then: None,
+    action: Action::NewTerminal,
+    trigger: 0,
+};
+
+/// posted to the message pump with whether the hotkeys are paused from the tray in `wParam`.
+pub const WM_PAUSE: u32 = WM_APP + 6;
+
+/// posted to the message pump with the keyboard layout of the new foreground window in `lParam`.
+pub const WM_LAYOUT: u32 = WM_APP + 7;
+
+/// the action VSCode binds to the chord by default, if it's one of the chords we fix.
+pub fn builtin_action(chord: Chord) -> Option<Action> {
+    [CTRL_OEM_3, CTRL_SHIFT_OEM_3]
+        .into_iter()
+        .find(|hotkey| hotkey.chord == chord)
+        .map(|hotkey| hotkey.action)
+}
+
+/// recognizes hotkeys, each arriving at the message pump of the registering thread as `WM_HOTKEY`
+/// with its id.
+///
+/// note: the hotkeys are the registering thread's, as are the messages.
+pub trait HotkeyBackend {
+    /// adds the hotkeys, skipping the ones that can't be, e.g. taken by another application.
+    fn register(&self, hotkeys: Vec<Hotkey>) -> Result<()>;
+    /// removes all hotkeys.
+    fn unregister(&self);
+    /// lets the chords through to the focused window as if they weren't hotkeys while `true`, e.g.
+    /// while they're injected.
+    fn set_passthrough(&self, passthrough: bool);
+    /// the hotkey it recognizes by the id, without copying the others as it's looked up on every
+    /// press.
+    fn find(&self, id: usize) -> Option<Hotkey>;
+    /// how many hotkeys it recognizes.
+    fn count(&self) -> usize;
+}
+
+/// `RegisterHotKey`, by the virtual key the keyboard layout maps the chord to.
+pub struct Registered;
+
+/// every backend, in the order the id of a `WM_HOTKEY` is looked up.
+const BACKENDS: [&dyn HotkeyBackend; 2] = [&Registered, &scancode::Hook];
+
+/// whether `trigger` picks `scancode::Hook` for the hotkey rather than `Registered`.
+fn is_hooked(hotkey: &Hotkey, trigger: Trigger) -> bool {
+    trigger == Trigger::ScanCode && hotkey.chord.vk == VK_OEM_3
+}
+
+/// how the hotkeys on the key left of 「1」 are recognized.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Trigger {
+    /// by the virtual key the keyboard layout of the foreground window maps the key to, registered
+    /// again whenever the foreground window has another layout.
+    ///
+    /// note: switching the layout within a window is only noticed once the foreground changes.
+    #[default]
+    VirtualKey,
+    /// by the scan code of the physical key, whatever the layout, via a keyboard hook.
+    ScanCode,
+}
+
+/// the ids of hotkeys from config start here.
+const CONFIG_ID_BASE: usize = 2400;
+
+/// the id of an action triggered without a key press.
+const UNPRESSED_ID: usize = 2335;
+
+/// the id of the hotkey returning the focus to the editor.
+pub const RETURN_TO_EDITOR_ID: usize = 2336;
+
+thread_local! {
+    static REGISTERED: RefCell<Vec<Hotkey>> = const { RefCell::new(Vec::new()) };
+    /// the keyboard layout the key left of 「1」 is resolved with.
+    static LAYOUT: Cell<HKL> = const { Cell::new(HKL(0)) };
+    static TRIGGERS: Cell<u64> = const { Cell::new(0) };
+}
+
+pub fn foreground_layout() -> HKL {
+    unsafe { GetKeyboardLayout(GetWindowThreadProcessId(GetForegroundWindow(), None)) }
+}
+
+/// the virtual key of the physical key left of 「1」 with the layout, `VK_OEM_3` on the US one.
+fn backquote_key(hkl: HKL) -> VIRTUAL_KEY {
+    if hkl == HKL(0) {
+        return VK_OEM_3;
+    }
+    match unsafe { MapVirtualKeyExW(SCANCODE_OEM_3 as u32, MAPVK_VSC_TO_VK, hkl) } {
+        0 => VK_OEM_3,
+        vk => VIRTUAL_KEY(vk as u16),
+    }
+}
+
+/// resolves 「`」 in the chords with the keyboard layout from now on, returns whether that maps it to
+/// another key, so the hotkeys have to be registered again.
+pub fn switch_layout(hkl: HKL) -> bool {
+    let before = LAYOUT.with(|layout| backquote_key(layout.replace(hkl)));
+    let after = backquote_key(hkl);
+    if before != after {
+        info!("the key left of 「1」 is {after:?} with the keyboard layout {hkl:?}");
+    }
+    before != after
+}
+
+/// the hotkeys enabled in config.
+pub fn table(config: &Config) -> Vec<Hotkey> {
+    let mut hotkeys = vec![toggle()];
+    if config.new_terminal_hotkey {
+        hotkeys.push(CTRL_SHIFT_OEM_3);
+    }
+    if let Some(chord) = config.return_to_editor_hotkey {
+        hotkeys.push(Hotkey {
+            id: RETURN_TO_EDITOR_ID,
+            chord,
+            then: None,
+            action: Action::FocusEditor,
+            trigger: 0,
+        });
+    }
+    for (i, rule) in config.chords.iter().enumerate() {
+        // note: the ids of the others stay the same, the rule keeps its place.
+        if !rule.enabled {
+            continue;
+        }
+        match rule.keys[..] {
+            [chord] | [chord, _] => hotkeys.push(Hotkey {
+                id: CONFIG_ID_BASE + i,
+                chord,
+                then: rule.keys.get(1).copied(),
+                action: rule.action,
+                trigger: 0,
+            }),
+            _ => warn!("{rule} must have one or two keys"),
+        }
+    }
+    hotkeys
+}
+
+/// the hotkey toggling the terminal, 「Ctrl+`」 unless the environment overrides it.
+fn toggle() -> Hotkey {
+    match overrides::hotkey() {
+        Some(chord) => Hotkey {
+            chord,
+            ..CTRL_OEM_3
+        },
+        None => CTRL_OEM_3,
+    }
+}
+
+/// fails if two hotkeys in config start with the same chord, only the first of them could be
+/// registered.
+pub fn check_duplicates(config: &Config) -> Result<()> {
+    let mut named = vec![("the toggle hotkey".to_owned(), toggle().chord)];
+    if config.new_terminal_hotkey {
+        named.push(("new_terminal_hotkey".to_owned(), CTRL_SHIFT_OEM_3.chord));
+    }
+    if let Some(chord) = config.return_to_editor_hotkey {
+        named.push(("return_to_editor_hotkey".to_owned(), chord));
+    }
+    for (i, rule) in config.chords.iter().enumerate() {
+        if let (true, Some(&chord)) = (rule.enabled, rule.keys.first()) {
+            named.push((format!("chords[{i}]"), chord));
+        }
+    }
+    for (i, (name, chord)) in named.iter().enumerate() {
+        if let Some((other, _)) = named[..i].iter().find(|(_, other)| other == chord) {
+            bail!("{other} and {name} are both {chord}, pick another chord for one of them");
+        }
+    }
+    Ok(())
+}
+
+/// registers the hotkeys for the calling thread with the backend `trigger` picks for each, so
+/// `WM_HOTKEY` arrives at its message pump.
+///
+/// note: a hotkey taken by another application is skipped, unless none can be registered at all.
+pub fn register(hotkeys: &[Hotkey], trigger: Trigger) -> Result<()> {
+    metrics::record_registration();
+    let altgr = MOD_CONTROL.0 | MOD_ALT.0;
+    for hotkey in hotkeys {
+        if hotkey.chord.modifiers.0 & altgr == altgr {
+            if let Some(typed) = hotkey.chord.typed_character() {
+                warn!(
+                    "{} types 「{typed}」 with AltGr on the current layout, the hotkey takes it",
+                    hotkey.chord
+                );
+            }
+        }
+    }
+    let (hooked, registered): (Vec<_>, Vec<_>) = hotkeys
+        .iter()
+        .partition(|hotkey| is_hooked(hotkey, trigger));
+    scancode::Hook.register(hooked).warn();
+    Registered.register(registered).warn();
+    ensure!(
+        BACKENDS.iter().any(|backend| backend.count() > 0),
+        "no hotkey could be registered"
+    );
+    Ok(())
+}
+
+impl HotkeyBackend for Registered {
+    fn register(&self, hotkeys: Vec<Hotkey>) -> Result<()> {
+        for hotkey in hotkeys {
+            if register_one(&hotkey)
+                .with_context(|| format!("failed to register {hotkey:?}"))
+                .warn()
+                .is_some()
+            {
+                REGISTERED.with(|registered| registered.borrow_mut().push(hotkey));
+            }
+        }
+        Ok(())
+    }
+
+    fn unregister(&self) {
+        REGISTERED.with(|registered| {
+            for hotkey in registered.borrow_mut().drain(..) {
+                unsafe { UnregisterHotKey(HWND(0), hotkey.id as i32) }.warn();
+            }
+        });
+    }
+
+    /// note: the hotkeys are unregistered meanwhile, but kept to register them again.
+    fn set_passthrough(&self, passthrough: bool) {
+        REGISTERED.with(|registered| {
+            for hotkey in registered.borrow().iter() {
+                if passthrough {
+                    unsafe { UnregisterHotKey(HWND(0), hotkey.id as i32) }.warn();
+                } else {
+                    register_one(hotkey).warn();
+                }
+            }
+        });
+    }
+
+    fn find(&self, id: usize) -> Option<Hotkey> {
+        REGISTERED.with(|registered| {
+            registered
+                .borrow()
+                .iter()
+                .find(|hotkey| hotkey.id == id)
+                .copied()
+        })
+    }
+
+    fn count(&self) -> usize {
+        REGISTERED.with(|registered| registered.borrow().len())
+    }
+}
+
+/// note: 「`」 is registered as the key at its position on the US layout, the hotkey keeps `VK_OEM_3`
+/// as VSCode binds the key by position as well.
+fn register_one(hotkey: &Hotkey) -> Result<()> {
+    let vk = match hotkey.chord.vk {
+        VK_OEM_3 => LAYOUT.with(|layout| backquote_key(layout.get())),
+        vk => vk,
+    };
+    let result =
+        unsafe { RegisterHotKey(HWND(0), hotkey.id as i32, hotkey.chord.modifiers, vk.0 as _) };
+    match result {
+        Err(err) if err.code() == ERROR_HOTKEY_ALREADY_REGISTERED.to_hresult() => bail!(
+            "{} is taken by Windows or another application, pick another chord",
+            hotkey.chord
+        ),
+        result => Ok(result?),
+    }
+}
+
+/// unregisters all hotkeys of the calling thread, e.g. before registering a reloaded table.
+pub fn unregister() {
+    for backend in BACKENDS {
+        backend.unregister();
+    }
+    gesture::unhook();
+}
+
+/// how many hotkeys are registered or hooked.
+pub fn count() -> usize {
+    BACKENDS
+        .iter()
+        .map(|backend| backend.count())
+        .sum::<usize>()
+        + gesture::count()
+}
+
+/// a registered hotkey, by the id `WM_HOTKEY` carries in `wParam`.
+pub fn find(id: usize) -> Option<Hotkey> {
+    BACKENDS.iter().find_map(|backend| backend.find(id))
+}
+
+/// lets the chords of all hotkeys of the calling thread through until the guard is dropped.
+pub fn suspend() -> Suspended {
+    for backend in BACKENDS {
+        backend.set_passthrough(true);
+    }
+    Suspended
+}
+
+pub struct Suspended;
+
+impl Drop for Suspended {
+    fn drop(&mut self) {
+        for backend in BACKENDS {
+            backend.set_passthrough(false);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fuzz;
+
+    /// every chord of modifiers and a key we can name, other than the reserved ones.
+    fn chords() -> impl Iterator<Item = Chord> {
+        (1..16).flat_map(|modifiers| {
+            (0..=u8::MAX)
+                .map(|vk| VIRTUAL_KEY(vk as u16))
+                .filter(|&vk| is_nameable(vk))
+                .map(move |vk| Chord {
+                    modifiers: HOT_KEY_MODIFIERS(modifiers),
+                    vk,
+                })
+                .filter(|chord| chord.reserved().is_none())
+        })
+    }
+
+    #[test]
+    fn chord_round_trips() {
+        for chord in chords() {
+            assert_eq!(chord.to_string().parse::<Chord>().unwrap(), chord);
+        }
+    }
+
+    #[test]
+    fn chord_parses_vscode_notation() {
+        let chord: Chord = "ctrl+shift+`".parse().unwrap();
+        assert_eq!(chord.modifiers, MOD_CONTROL | MOD_SHIFT);
+        assert_eq!(chord.vk, VK_OEM_3);
+        let chord: Chord = " AltGr + q ".parse().unwrap();
+        assert_eq!(chord.modifiers, MOD_CONTROL | MOD_ALT);
+        assert_eq!(chord.vk, VIRTUAL_KEY(b'Q' as u16));
+        for invalid in [
+            "", "Ctrl", "`", "Ctrl+A+B", "Ctrl+F25", "Ctrl+F0", "Hyper+A", "F12+Ctrl",
+        ] {
+            assert!(invalid.parse::<Chord>().is_err(), "{invalid:?}");
+        }
+    }
+
+    #[test]
+    fn reserved_chords_are_rejected() {
+        for reserved in [
+            "Ctrl+F12",
+            "Win+L",
+            "win+d",
+            "Win+E",
+            "Win+R",
+            "Win+Tab",
+            "Win+Space",
+            "Win+Shift+Space",
+            "Ctrl+Alt+Del",
+            "AltGr+Delete",
+            "Alt+Tab",
+            "Alt+Shift+Tab",
+            "Alt+Esc",
+            "Ctrl+Escape",
+            "Ctrl+Shift+Esc",
+        ] {
+            let error = reserved.parse::<Chord>().unwrap_err().to_string();
+            assert!(
+                error.contains("reserved by Windows"),
+                "{reserved:?}: {error}"
+            );
+        }
+        for free in [
+            "Win+Shift+D",
+            "Ctrl+Tab",
+            "Ctrl+Space",
+            "Shift+Escape",
+            "Ctrl+Delete",
+        ] {
+            assert!(free.parse::<Chord>().is_ok(), "{free:?}");
+        }
+    }
+
+    #[test]
+    fn duplicates_include_the_overridden_toggle() {
+        let config: Config = toml::from_str("new_terminal_hotkey = true").unwrap();
+        assert!(check_duplicates(&config).is_ok());
+        std::env::set_var("CJK_FIXER_HOTKEY", "Ctrl+Shift+`");
+        let result = check_duplicates(&config);
+        std::env::remove_var("CJK_FIXER_HOTKEY");
+        assert_eq!(
+            result.unwrap_err().to_string(),
+            "the toggle hotkey and new_terminal_hotkey are both Ctrl+Shift+`, pick another chord for \
+             one of them"
+        );
+    }
+
+    #[test]
+    fn chord_parses_arbitrary_input() {
+        let pieces = [
+            "+", " ", "ctrl", "Control", "Shift", "alt", "AltGr", "win", "meta", "Super", "`", "a",
+            "Z", "1", "f", "F", "12", "24", "0", "l", "tab", "Space", "esc", "Delete",
+        ];
+        for s in fuzz::strings(&pieces) {
+            if let Ok(chord) = s.parse::<Chord>() {
+                assert_eq!(chord.to_string().parse::<Chord>().unwrap(), chord, "{s:?}");
+            }
+        }
+    }
+}
@@ -0,0 +1,103 @@
+use std::ptr;
+
+use anyhow::{ensure, Context, Result};
+use windows::{
+    core::{w, HSTRING, PCWSTR, PWSTR},
+    Win32::Networking::WinHttp::{
+        WinHttpCloseHandle, WinHttpConnect, WinHttpCrackUrl, WinHttpOpen, WinHttpOpenRequest,
+        WinHttpQueryHeaders, WinHttpReceiveResponse, WinHttpSendRequest, URL_COMPONENTS,
+        WINHTTP_ACCESS_TYPE_AUTOMATIC_PROXY, WINHTTP_FLAG_SECURE, WINHTTP_INTERNET_SCHEME_HTTPS,
+        WINHTTP_OPEN_REQUEST_FLAGS, WINHTTP_QUERY_FLAG_NUMBER, WINHTTP_QUERY_STATUS_CODE,
+    },
+};
+
+use crate::{LogExt, PACKAGE_NAME};
+
+/// closes the WinHTTP handle on drop.
+struct Handle(*mut std::ffi::c_void);
+
+impl Handle {
+    fn new(handle: *mut std::ffi::c_void) -> Result<Self> {
+        ensure!(!handle.is_null(), windows::core::Error::from_win32());
+        Ok(Self(handle))
+    }
+}
+
+impl Drop for Handle {
+    fn drop(&mut self) {
+        unsafe { WinHttpCloseHandle(self.0) }.warn();
+    }
+}
+
+/// posts the body to the URL and returns the status code, blocking until the response arrives.
+///
+/// note: WinHTTP picks up the system proxy and certificate store, so no extra dependency is needed.
+pub fn post(url: &str, content_type: &str, body: &[u8]) -> Result<u16> {
+    let wide_url: Vec<u16> = url.encode_utf16().collect();
+    let mut host = [0u16; 256];
+    let mut path = [0u16; 2048];
+    let mut components = URL_COMPONENTS {
+        dwStructSize: std::mem::size_of::<URL_COMPONENTS>() as u32,
+        lpszHostName: PWSTR(host.as_mut_ptr()),
+        dwHostNameLength: host.len() as u32,
+        lpszUrlPath: PWSTR(path.as_mut_ptr()),
+        dwUrlPathLength: path.len() as u32,
+        ..Default::default()
+    };
+    unsafe { WinHttpCrackUrl(&wide_url, 0, &mut components) }
+        .with_context(|| format!("invalid URL: {url:?}"))?;
+    let flags = if components.nScheme == WINHTTP_INTERNET_SCHEME_HTTPS {
+        WINHTTP_FLAG_SECURE
+    } else {
+        WINHTTP_OPEN_REQUEST_FLAGS(0)
+    };
+
+    unsafe {
+        let session = Handle::new(WinHttpOpen(
+            &HSTRING::from(PACKAGE_NAME),
+            WINHTTP_ACCESS_TYPE_AUTOMATIC_PROXY,
+            PCWSTR::null(),
+            PCWSTR::null(),
+            0,
+        ))?;
+        let connection = Handle::new(WinHttpConnect(
+            session.0,
+            PCWSTR(host.as_ptr()),
+            components.nPort,
+            0,
+        ))?;
+        let request = Handle::new(WinHttpOpenRequest(
+            connection.0,
+            w!("POST"),
+            PCWSTR(path.as_ptr()),
+            PCWSTR::null(),
+            PCWSTR::null(),
+            ptr::null(),
+            flags,
+        ))?;
+        let headers: Vec<u16> = format!("Content-Type: {content_type}\r\n")
+            .encode_utf16()
+            .collect();
+        WinHttpSendRequest(
+            request.0,
+            Some(&headers),
+            Some(body.as_ptr().cast()),
+            body.len() as u32,
+            body.len() as u32,
+            0,
+        )?;
+        WinHttpReceiveResponse(request.0, ptr::null_mut())?;
+
+        let mut status = 0u32;
+        let mut status_size = std::mem::size_of::<u32>() as u32;
+        WinHttpQueryHeaders(
+            request.0,
+            WINHTTP_QUERY_STATUS_CODE | WINHTTP_QUERY_FLAG_NUMBER,
+            PCWSTR::null(),
+            Some((&mut status as *mut u32).cast()),
+            &mut status_size,
+            ptr::null_mut(),
+        )?;
+        Ok(status as u16)
+    }
+}
@@ -0,0 +1,141 @@
+use std::{
+    io::{BufRead, BufReader, Read, Write},
+    net::{Ipv4Addr, TcpListener, TcpStream},
+    thread,
+    time::Duration,
+};
+
+use anyhow::{bail, Context, Result};
+#[allow(unused_imports)]
+use tracing::{debug, error, info, trace, warn};
+
+use crate::{config::Config, ipc, LogExt};
+
+/// the longest request line and headers accepted, the API takes no bodies.
+const MAX_HEAD_LEN: u64 = 8192;
+
+/// how long a client may take to send its request or receive the response.
+const TIMEOUT: Duration = Duration::from_secs(5);
+
+/// serves the commands of the pipe over HTTP on the loopback interface, e.g. for Stream Deck
+/// buttons: `GET /status`, `GET /metrics`, `POST /pause`, `POST /resume` and
+/// `POST /trigger/<action>`, each with an `Authorization: Bearer <token>` header.
+///
+/// note: the token keeps out other local users as well as web pages, which can reach the loopback
+/// interface but can't send the header without a CORS preflight we never allow.
+pub fn serve(config: &Config, control: ipc::Control) -> Result<()> {
+    if config.http_api_port == 0 {
+        return Ok(());
+    }
+    let token = config
+        .http_api_token
+        .clone()
+        .filter(|token| !token.is_empty())
+        .context("the HTTP API requires http_api_token")?;
+    let listener = TcpListener::bind((Ipv4Addr::LOCALHOST, config.http_api_port))
+        .with_context(|| format!("failed to listen on port {}", config.http_api_port))?;
+    info!("HTTP API listening on {}", listener.local_addr()?);
+    thread::Builder::new()
+        .name("http-api".to_owned())
+        .spawn(move || {
+            for stream in listener.incoming() {
+                match stream {
+                    Ok(stream) => handle(stream, &token, &control).warn(),
+                    Err(err) => Err::<(), _>(err).context("failed to accept").warn(),
+                };
+            }
+        })?;
+    Ok(())
+}
+
+/// answers one request and closes the connection.
+fn handle(stream: TcpStream, token: &str, control: &ipc::Control) -> Result<()> {
+    stream.set_read_timeout(Some(TIMEOUT))?;
+    stream.set_write_timeout(Some(TIMEOUT))?;
+    let mut reader = BufReader::new((&stream).take(MAX_HEAD_LEN));
+    let mut request_line = String::new();
+    reader.read_line(&mut request_line)?;
+    let mut authorization = None;
+    loop {
+        let mut line = String::new();
+        if reader.read_line(&mut line)? == 0 {
+            bail!("incomplete request: {request_line:?}");
+        }
+        let line = line.trim_end();
+        if line.is_empty() {
+            break;
+        }
+        if let Some((name, value)) = line.split_once(':') {
+            if name.trim().eq_ignore_ascii_case("authorization") {
+                authorization = Some(value.trim().to_owned());
+            }
+        }
+    }
+
+    let (status, content_type, body) = route(
+        request_line.trim_end(),
+        authorization.as_deref(),
+        token,
+        control,
+    );
+    debug!("HTTP API: {:?} {status}", request_line.trim_end());
+    let response = format!(
+        "HTTP/1.1 {status}\r\nContent-Type: {content_type}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{body}",
+        body.len()
+    );
+    (&stream).write_all(response.as_bytes())?;
+    Ok(())
+}
+
+/// the status line, content type and body of the response to the request.
+fn route(
+    request_line: &str,
+    authorization: Option<&str>,
+    token: &str,
+    control: &ipc::Control,
+) -> (&'static str, &'static str, String) {
+    const TEXT: &str = "text/plain; charset=utf-8";
+    let mut parts = request_line.split(' ');
+    let (Some(method), Some(path)) = (parts.next(), parts.next()) else {
+        return ("400 Bad Request", TEXT, "bad request\n".to_owned());
+    };
+    let authorized = authorization
+        .and_then(|authorization| authorization.strip_prefix("Bearer "))
+        .is_some_and(|given| is_same(given.trim(), token));
+    if !authorized {
+        return (
+            "401 Unauthorized",
+            TEXT,
+            "missing or wrong token\n".to_owned(),
+        );
+    }
+
+    let command = match (method, path) {
+        ("GET", "/status") => "status".to_owned(),
+        ("GET", "/metrics") => "metrics".to_owned(),
+        ("POST", "/pause") => "pause".to_owned(),
+        ("POST", "/resume") => "resume".to_owned(),
+        ("POST", path) if path.starts_with("/trigger/") => {
+            format!("trigger {}", &path["/trigger/".len()..])
+        }
+        _ => return ("404 Not Found", TEXT, "not found\n".to_owned()),
+    };
+    let content_type = match command.as_str() {
+        "status" => "application/json",
+        _ => TEXT,
+    };
+    match ipc::respond(control, &command) {
+        Ok(body) => ("200 OK", content_type, body),
+        Err(err) => ("400 Bad Request", TEXT, format!("{err:#}\n")),
+    }
+}
+
+/// compares the whole token, so the time taken doesn't tell how much of a guess was right.
+fn is_same(given: &str, token: &str) -> bool {
+    given.len() == token.len()
+        && given
+            .bytes()
+            .zip(token.bytes())
+            .fold(0, |diff, (a, b)| diff | (a ^ b))
+            == 0
+}
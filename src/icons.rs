@@ -0,0 +1,269 @@
+use std::{cell::OnceCell, fs, io, path::Path};
+
+use anyhow::{ensure, Context, Result};
+use resvg::{
+    tiny_skia::{Pixmap, Transform},
+    usvg,
+};
+#[allow(unused_imports)]
+use tracing::{debug, error, info, trace, warn};
+
+use crate::{dpi, theme::Theme, tray::Icon, LogExt};
+
+/// the sizes every icon of a pack must contain, the small icon size at 100% and 200% scaling, the
+/// others are scaled from the closest image by Windows.
+const REQUIRED_SIZES: [u32; 2] = [16, 32];
+
+/// what the tray icon shows.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum State {
+    Normal,
+    /// no window receiving the hotkeys is focused.
+    Dimmed,
+    Paused,
+    /// the hotkeys are dormant outside the active hours.
+    Scheduled,
+    /// no hotkey could be registered.
+    Error,
+}
+
+impl State {
+    /// the file of the state in an icon pack, e.g. `paused.ico`.
+    fn name(self) -> &'static str {
+        match self {
+            Self::Normal => "normal",
+            Self::Dimmed => "dimmed",
+            Self::Paused => "paused",
+            Self::Scheduled => "scheduled",
+            Self::Error => "error",
+        }
+    }
+}
+
+impl State {
+    /// renders the built-in icon of the state.
+    fn builtin(self) -> Icon {
+        match self {
+            Self::Normal | Self::Error => builtin_icon(
+                include_bytes!("../assets/icon.svg"),
+                include_bytes!("../assets/icon.ico"),
+            ),
+            Self::Dimmed | Self::Paused | Self::Scheduled => builtin_icon(
+                include_bytes!("../assets/icon-dimmed.svg"),
+                include_bytes!("../assets/icon-dimmed.ico"),
+            ),
+        }
+    }
+}
+
+/// an icon decoded once it's first shown, e.g. the one for errors usually never is.
+#[derive(Default)]
+struct LazyIcon {
+    /// the validated ICO file of an icon pack, the built-in icon is used without.
+    pack: Option<Vec<u8>>,
+    icon: OnceCell<Icon>,
+}
+
+#[derive(Default)]
+pub struct Icons {
+    normal: LazyIcon,
+    dimmed: LazyIcon,
+    paused: LazyIcon,
+    scheduled: LazyIcon,
+    error: LazyIcon,
+}
+
+impl Icons {
+    pub fn builtin() -> Self {
+        Self::default()
+    }
+
+    /// reads the icons of a pack directory, e.g. `normal.ico` or `normal-dark.ico` for a dark
+    /// taskbar, states missing from the pack keep the built-in icon.
+    ///
+    /// note: a pack is only read at startup and when the scaling or the theme of the taskbar
+    /// changes.
+    pub fn load(dir: &Path, theme: Theme) -> Self {
+        let mut icons = Self::builtin();
+        for state in [
+            State::Normal,
+            State::Dimmed,
+            State::Paused,
+            State::Scheduled,
+            State::Error,
+        ] {
+            icons.get_mut(state).pack = read_icon(dir, state, theme)
+                .with_context(|| format!("invalid icon pack: {dir:?}"))
+                .warn()
+                .flatten();
+        }
+        icons
+    }
+
+    /// decodes the icon of the state on first use, it's kept until the icons are loaded again.
+    pub fn get(&self, state: State) -> &Icon {
+        let lazy = match state {
+            State::Normal => &self.normal,
+            State::Dimmed => &self.dimmed,
+            State::Paused => &self.paused,
+            State::Scheduled => &self.scheduled,
+            State::Error => &self.error,
+        };
+        lazy.icon.get_or_init(|| {
+            lazy.pack
+                .as_deref()
+                .and_then(|buffer| {
+                    from_buffer(buffer)
+                        .with_context(|| format!("invalid {state:?} icon in the icon pack"))
+                        .warn()
+                })
+                .unwrap_or_else(|| state.builtin())
+        })
+    }
+
+    fn get_mut(&mut self, state: State) -> &mut LazyIcon {
+        match state {
+            State::Normal => &mut self.normal,
+            State::Dimmed => &mut self.dimmed,
+            State::Paused => &mut self.paused,
+            State::Scheduled => &mut self.scheduled,
+            State::Error => &mut self.error,
+        }
+    }
+}
+
+/// the ICO file of the state for the theme, falling back to the one for any theme.
+fn read_icon(dir: &Path, state: State, theme: Theme) -> Result<Option<Vec<u8>>> {
+    let themed = match theme {
+        Theme::Light => "light",
+        Theme::Dark => "dark",
+    };
+    for file_name in [
+        format!("{}-{themed}.ico", state.name()),
+        format!("{}.ico", state.name()),
+    ] {
+        let path = dir.join(file_name);
+        let buffer = match fs::read(&path) {
+            Ok(buffer) => buffer,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => continue,
+            Err(err) => return Err(err).with_context(|| format!("failed to read {path:?}")),
+        };
+        validate(&buffer).with_context(|| format!("invalid icon: {path:?}"))?;
+        return Ok(Some(buffer));
+    }
+    Ok(None)
+}
+
+/// renders the SVG icon at the tray's size, falling back to the pre-rendered ICO file.
+fn builtin_icon(svg: &[u8], ico: &[u8]) -> Icon {
+    rasterize(svg, dpi::small_icon_size())
+        .context("failed to render the built-in icon")
+        .warn()
+        .unwrap_or_else(|| from_buffer(ico).expect("the bundled ICO is valid"))
+}
+
+/// renders the SVG at exactly `size` pixels, so fractional scaling like 125% stays crisp instead
+/// of using the closest pre-rendered image.
+fn rasterize(svg: &[u8], size: u32) -> Result<Icon> {
+    let tree = usvg::Tree::from_data(svg, &usvg::Options::default())?;
+    let mut pixmap = Pixmap::new(size, size).with_context(|| format!("invalid size: {size}"))?;
+    let scale = size as f32 / tree.size().width().max(tree.size().height());
+    resvg::render(
+        &tree,
+        Transform::from_scale(scale, scale),
+        &mut pixmap.as_mut(),
+    );
+
+    Icon::from_buffer(&ico(size, &pixmap), Some(size), Some(size))
+}
+
+/// wraps the pixels into an ICO file of a single 32-bit image, which `Icon` is created from.
+fn ico(size: u32, pixmap: &Pixmap) -> Vec<u8> {
+    const HEADER_LEN: u32 = 6;
+    const ENTRY_LEN: u32 = 16;
+    const BITMAP_HEADER_LEN: u32 = 40;
+
+    // note: the AND mask is unused by 32-bit images, but still expected after the pixels.
+    let mask_len = size.div_ceil(32) * 4 * size;
+    let bitmap_len = BITMAP_HEADER_LEN + size * size * 4 + mask_len;
+    let mut buffer = Vec::with_capacity((HEADER_LEN + ENTRY_LEN + bitmap_len) as usize);
+    for value in [0u16, 1, 1] {
+        buffer.extend(value.to_le_bytes());
+    }
+    // note: a width of 0 stands for 256.
+    buffer.extend([size as u8, size as u8, 0, 0]);
+    buffer.extend(1u16.to_le_bytes());
+    buffer.extend(32u16.to_le_bytes());
+    buffer.extend(bitmap_len.to_le_bytes());
+    buffer.extend((HEADER_LEN + ENTRY_LEN).to_le_bytes());
+
+    // note: the height of the bitmap counts the AND mask as well.
+    for value in [BITMAP_HEADER_LEN, size, size * 2] {
+        buffer.extend(value.to_le_bytes());
+    }
+    buffer.extend(1u16.to_le_bytes());
+    buffer.extend(32u16.to_le_bytes());
+    for value in [0, bitmap_len - BITMAP_HEADER_LEN, 0, 0, 0, 0] {
+        buffer.extend(value.to_le_bytes());
+    }
+    // note: the rows are stored bottom-up in BGRA, tiny-skia's are top-down in premultiplied RGBA.
+    for row in pixmap.pixels().chunks(size as usize).rev() {
+        for pixel in row {
+            let color = pixel.demultiply();
+            buffer.extend([color.blue(), color.green(), color.red(), color.alpha()]);
+        }
+    }
+    buffer.resize(buffer.len() + mask_len as usize, 0);
+    buffer
+}
+
+/// creates the icon from a multi-image ICO file, Windows picks the image matching the tray's small
+/// icon size at the current scaling.
+///
+/// note: falls back to the image of the default icon size, e.g. when the system metric is
+/// unavailable.
+fn from_buffer(buffer: &[u8]) -> Result<Icon> {
+    let size = dpi::small_icon_size();
+    if size > 0 {
+        match Icon::from_buffer(buffer, Some(size), Some(size)) {
+            Ok(icon) => return Ok(icon),
+            Err(err) => warn!("failed to load the {size}px icon: {err:#}"),
+        }
+    }
+    Icon::from_buffer(buffer, None, None)
+}
+
+/// checks that the ICO file contains every size in `REQUIRED_SIZES`, so it's not scaled blurry.
+fn validate(buffer: &[u8]) -> Result<()> {
+    const HEADER_LEN: usize = 6;
+    const ENTRY_LEN: usize = 16;
+
+    let u16_at = |i: usize| {
+        buffer
+            .get(i..i + 2)
+            .map(|b| u16::from_le_bytes([b[0], b[1]]))
+    };
+    ensure!(
+        u16_at(0) == Some(0) && u16_at(2) == Some(1),
+        "not an ICO file"
+    );
+    let count = u16_at(4).context("not an ICO file")? as usize;
+    ensure!(
+        buffer.len() >= HEADER_LEN + count * ENTRY_LEN,
+        "truncated ICO file"
+    );
+    // note: a width of 0 stands for 256.
+    let sizes: Vec<u32> = (0..count)
+        .map(|i| match buffer[HEADER_LEN + i * ENTRY_LEN] {
+            0 => 256,
+            size => size as u32,
+        })
+        .collect();
+    for size in REQUIRED_SIZES {
+        ensure!(
+            sizes.contains(&size),
+            "no {size}x{size} image, only {sizes:?}"
+        );
+    }
+    Ok(())
+}
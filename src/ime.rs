@@ -0,0 +1,29 @@
+use anyhow::{Context, Result};
+#[allow(unused_imports)]
+use tracing::{debug, error, info, trace, warn};
+use windows::{
+    core::GUID,
+    Win32::UI::TextServices::{
+        CLSID_TF_InputProcessorProfiles, ITfInputProcessorProfileMgr, GUID_TFCAT_TIP_KEYBOARD,
+        TF_INPUTPROCESSORPROFILE, TF_PROFILETYPE_INPUTPROCESSOR,
+    },
+};
+
+use crate::com;
+
+/// the profile GUID of the active TSF keyboard IME, `None` for a plain keyboard layout.
+///
+/// note: this is the input method of the calling thread, which follows the foreground window's
+/// unless "let me use a different input method for each app window" is turned on.
+pub fn active_profile() -> Result<Option<GUID>> {
+    com::with(
+        &CLSID_TF_InputProcessorProfiles,
+        |profiles: &ITfInputProcessorProfileMgr| {
+            let mut profile = TF_INPUTPROCESSORPROFILE::default();
+            unsafe { profiles.GetActiveProfile(&GUID_TFCAT_TIP_KEYBOARD, &mut profile) }
+                .context("failed to query the active input profile")?;
+            Ok((profile.dwProfileType == TF_PROFILETYPE_INPUTPROCESSOR)
+                .then_some(profile.guidProfile))
+        },
+    )
+}
@@ -0,0 +1,427 @@
+use std::{
+    collections::BTreeMap,
+    fmt, mem,
+    str::FromStr,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Mutex,
+    },
+    thread,
+    time::Duration,
+};
+
+use anyhow::{bail, ensure, Context, Result};
+use serde::{Deserialize, Serialize};
+#[allow(unused_imports)]
+use tracing::{debug, error, info, trace, warn};
+use windows::Win32::{
+    Foundation::{ERROR_ACCESS_DENIED, ERROR_INVALID_WINDOW_HANDLE, HWND, LPARAM, WPARAM},
+    UI::{
+        Input::KeyboardAndMouse::{
+            MapVirtualKeyW, SendInput, INPUT, INPUT_0, INPUT_KEYBOARD, KEYBDINPUT,
+            KEYBD_EVENT_FLAGS, KEYEVENTF_KEYUP, KEYEVENTF_SCANCODE, MAPVK_VK_TO_VSC, VIRTUAL_KEY,
+            VK_F1, VK_OEM_3, VK_RETURN,
+        },
+        WindowsAndMessaging::{
+            GetForegroundWindow, IsWindow, PostMessageA, PostMessageW, WM_CHAR, WM_KEYDOWN,
+            WM_KEYUP,
+        },
+    },
+};
+
+use crate::{
+    config::Config,
+    hotkey::{self, Hotkey},
+    keybindings::{self, Binding},
+    metrics, procs, remote, telemetry, uia, LockExt,
+};
+
+/// the scan code of the physical key left of 「1」, which is 「`」 on the US layout.
+pub const SCANCODE_OEM_3: u16 = 0x29;
+
+/// how long the hotkeys stay unregistered so that the injected input doesn't trigger them again.
+const HOTKEY_RESUME_DELAY: Duration = Duration::from_millis(10);
+
+/// what a hotkey makes VSCode do.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Action {
+    ToggleTerminal,
+    NewTerminal,
+    FocusTerminal,
+    TogglePanel,
+    FocusEditor,
+}
+
+/// every action, e.g. to pass one as an index in a message.
+#[cfg(feature = "ipc")]
+pub const ACTIONS: [Action; 5] = [
+    Action::ToggleTerminal,
+    Action::NewTerminal,
+    Action::FocusTerminal,
+    Action::TogglePanel,
+    Action::FocusEditor,
+];
+
+impl FromStr for Action {
+    type Err = anyhow::Error;
+
+    /// parses the name used in the config file, e.g. `toggle_terminal`.
+    fn from_str(s: &str) -> Result<Self> {
+        toml::Value::String(s.to_owned())
+            .try_into()
+            .with_context(|| format!("unknown action: {s:?}"))
+    }
+}
+
+impl fmt::Display for Action {
+    /// the name `FromStr` parses.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match toml::Value::try_from(self) {
+            Ok(toml::Value::String(name)) => f.write_str(&name),
+            _ => write!(f, "{self:?}"),
+        }
+    }
+}
+
+impl Action {
+    /// the command palette entry of the action.
+    pub fn command(self) -> &'static str {
+        match self {
+            Self::ToggleTerminal => "View: Toggle Terminal",
+            Self::NewTerminal => "Terminal: Create New Terminal",
+            Self::FocusTerminal => "Terminal: Focus Terminal",
+            Self::TogglePanel => "View: Toggle Panel Visibility",
+            Self::FocusEditor => "View: Focus Active Editor Group",
+        }
+    }
+
+    /// the id of the command in VSCode's keybindings, e.g. `workbench.action.terminal.toggleTerminal`.
+    pub fn command_id(self) -> &'static str {
+        match self {
+            Self::ToggleTerminal => "workbench.action.terminal.toggleTerminal",
+            Self::NewTerminal => "workbench.action.terminal.new",
+            Self::FocusTerminal => "workbench.action.terminal.focus",
+            Self::TogglePanel => "workbench.action.togglePanel",
+            Self::FocusEditor => "workbench.action.focusActiveEditorGroup",
+        }
+    }
+
+    /// the menu items leading to the action in VSCode's English menu, if it has any.
+    pub fn menu_path(self) -> &'static [&'static str] {
+        match self {
+            Self::ToggleTerminal => &["View", "Terminal"],
+            Self::NewTerminal => &["Terminal", "New Terminal"],
+            Self::FocusTerminal | Self::FocusEditor => &[],
+            Self::TogglePanel => &["View", "Appearance", "Panel"],
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Backend {
+    /// posts the key messages to the window directly, bypassing the IME.
+    PostMessage,
+    /// synthesizes the physical key press, only works for the foreground window.
+    SendInput,
+    /// runs the toggle command through the command palette, independent of the keybinding.
+    CommandPalette,
+    /// invokes the action's item of VSCode's menu via UI Automation, independent of the keyboard
+    /// layout and IME.
+    ///
+    /// note: the VSCode CLI can't run a command in an existing window, so this is the way to go.
+    Uia,
+}
+
+impl Backend {
+    /// whether the backend presses the action's keys, rather than running it independent of them.
+    fn presses_keys(self) -> bool {
+        matches!(self, Self::PostMessage | Self::SendInput)
+    }
+
+    /// the implementation of the backend.
+    pub fn injector(self) -> &'static dyn Injector {
+        match self {
+            Self::PostMessage => &Posted,
+            Self::SendInput => &Synthesized,
+            Self::CommandPalette => &Palette,
+            Self::Uia => &Menu,
+        }
+    }
+}
+
+/// what an injection is for.
+#[derive(Debug, Clone, Copy)]
+pub struct Target<'a> {
+    pub config: &'a Config,
+    pub hwnd: HWND,
+    pub hotkey: &'a Hotkey,
+    /// the keys the window's VSCode profile binds the action to, empty if it isn't bound to keys
+    /// pressed with the modifiers the user holds.
+    pub keys: &'a [VIRTUAL_KEY],
+}
+
+/// makes a VSCode window perform the action of a hotkey, one way or another.
+pub trait Injector {
+    fn inject(&self, target: &Target) -> Result<()>;
+}
+
+/// `Backend::PostMessage`.
+pub struct Posted;
+
+/// `Backend::SendInput`.
+pub struct Synthesized;
+
+/// `Backend::CommandPalette`.
+pub struct Palette;
+
+/// `Backend::Uia`.
+pub struct Menu;
+
+impl Injector for Posted {
+    fn inject(&self, target: &Target) -> Result<()> {
+        post_keys_with(
+            target.hwnd,
+            target.keys.iter().copied(),
+            remote::key_delay(target.config),
+        )
+    }
+}
+
+impl Injector for Synthesized {
+    fn inject(&self, target: &Target) -> Result<()> {
+        send_input(target.hwnd, target.keys.iter().copied())
+    }
+}
+
+impl Injector for Palette {
+    fn inject(&self, target: &Target) -> Result<()> {
+        command_palette(target.hwnd, target.hotkey.action)
+    }
+}
+
+impl Injector for Menu {
+    /// note: the menu path of the config wins over the English one.
+    fn inject(&self, target: &Target) -> Result<()> {
+        let action = target.hotkey.action;
+        match target.config.uia_menu_paths.get(&action) {
+            Some(path) => uia::invoke_menu(target.hwnd, path),
+            None => {
+                let path = action.menu_path();
+                ensure!(!path.is_empty(), "{action:?} has no menu item");
+                uia::invoke_menu(target.hwnd, path)
+            }
+        }
+    }
+}
+
+/// the default fallback chain.
+pub const BACKENDS: [Backend; 4] = [
+    Backend::PostMessage,
+    Backend::SendInput,
+    Backend::CommandPalette,
+    Backend::Uia,
+];
+
+/// the backend that last succeeded for a window by its handle and process, it's tried first the
+/// next time.
+///
+/// note: the process tells a window apart from a later one reusing its handle.
+static SUCCEEDED: Mutex<BTreeMap<(isize, u32), Backend>> = Mutex::new(BTreeMap::new());
+
+/// whether a key was blocked by UIPI since `take_blocked`.
+static BLOCKED: AtomicBool = AtomicBool::new(false);
+
+/// tries the backends in order until one succeeds and returns it.
+///
+/// note: the backends pressing the keys rely on the user holding the modifiers, so a gesture, an
+/// action triggered without a key press, or one the window's VSCode profile binds to other
+/// modifiers skips them.
+pub fn mock_key_press(
+    config: &Config,
+    hwnd: HWND,
+    hotkey: &Hotkey,
+    backends: &[Backend],
+) -> Result<Backend> {
+    let window = (hwnd.0, procs::of_window(hwnd));
+    // note: a backend no longer configured, or left out by a quirk, isn't tried at all.
+    let remembered = SUCCEEDED
+        .locked()
+        .get(&window)
+        .copied()
+        .filter(|backend| backends.contains(backend));
+    let keys = if hotkey.without_modifiers() {
+        None
+    } else {
+        profile_keys(hwnd, hotkey)
+    };
+    let mut tried = Vec::new();
+    for backend in remembered.into_iter().chain(backends.iter().copied()) {
+        if tried.contains(&backend) || (keys.is_none() && backend.presses_keys()) {
+            continue;
+        }
+        let target = Target {
+            config,
+            hwnd,
+            hotkey,
+            keys: keys.as_deref().unwrap_or_default(),
+        };
+        let result = backend.injector().inject(&target);
+        telemetry::record_injection(backend, result.is_ok());
+        match result {
+            Ok(()) => {
+                let mut succeeded = SUCCEEDED.locked();
+                succeeded.retain(|&(hwnd, _), _| unsafe { IsWindow(HWND(hwnd)) }.as_bool());
+                succeeded.insert(window, backend);
+                return Ok(backend);
+            }
+            Err(err) => {
+                metrics::record_injection_failure();
+                warn!("injection via {backend:?} failed: {err:?}");
+            }
+        }
+        tried.push(backend);
+    }
+    bail!("no injection backend succeeded for {hwnd:?}, tried {tried:?}")
+}
+
+/// the keys to press for the action in the window's VSCode profile, `None` if it isn't bound to
+/// keys pressed with the modifiers the user holds.
+fn profile_keys(hwnd: HWND, hotkey: &Hotkey) -> Option<Vec<VIRTUAL_KEY>> {
+    match keybindings::of(hwnd, hotkey.action) {
+        Binding::Default => Some(hotkey.keys().collect()),
+        Binding::Chords(chords) => {
+            let held = hotkey.then.unwrap_or(hotkey.chord).modifiers;
+            let keys = chords.iter().all(|chord| chord.modifiers == held);
+            debug!("the VSCode profile binds {:?} to {chords:?}", hotkey.action);
+            keys.then(|| chords.iter().map(|chord| chord.vk).collect())
+        }
+        Binding::Other => {
+            debug!(
+                "the VSCode profile doesn't bind {:?} to keys",
+                hotkey.action
+            );
+            None
+        }
+    }
+}
+
+/// forgets the backend that succeeded for the window, e.g. because it turned out to be a no-op.
+pub fn forget(hwnd: HWND) {
+    SUCCEEDED.locked().remove(&(hwnd.0, procs::of_window(hwnd)));
+}
+
+/// posts the key presses to the window one after another, the modifiers are still held by the user.
+pub fn post_keys(hwnd: HWND, keys: impl IntoIterator<Item = VIRTUAL_KEY>) -> Result<()> {
+    post_keys_with(hwnd, keys, Duration::ZERO)
+}
+
+/// like `post_keys`, pausing between the messages, e.g. in a remote desktop session.
+fn post_keys_with(
+    hwnd: HWND,
+    keys: impl IntoIterator<Item = VIRTUAL_KEY>,
+    delay: Duration,
+) -> Result<()> {
+    let messages = keys
+        .into_iter()
+        .flat_map(|vk| [(WM_KEYDOWN, vk), (WM_KEYUP, vk)]);
+    for (i, (action, vk)) in messages.enumerate() {
+        if i > 0 && !delay.is_zero() {
+            thread::sleep(delay);
+        }
+        post(hwnd, action, vk, key_lparam(vk))?;
+    }
+    Ok(())
+}
+
+fn post_key(hwnd: HWND, vk: VIRTUAL_KEY, lparam: LPARAM) -> Result<()> {
+    for action in [WM_KEYDOWN, WM_KEYUP] {
+        post(hwnd, action, vk, lparam)?;
+    }
+    Ok(())
+}
+
+/// posts a key message, telling a window running elevated, which UIPI keeps us from reaching,
+/// apart from one that's gone.
+fn post(hwnd: HWND, action: u32, vk: VIRTUAL_KEY, lparam: LPARAM) -> Result<()> {
+    let Err(err) = (unsafe { PostMessageA(hwnd, action, WPARAM(vk.0 as usize), lparam) }) else {
+        return Ok(());
+    };
+    if err.code() == ERROR_ACCESS_DENIED.to_hresult() {
+        BLOCKED.store(true, Ordering::Relaxed);
+        Err(err).context("blocked by UIPI, the window runs as administrator but we don't")
+    } else if err.code() == ERROR_INVALID_WINDOW_HANDLE.to_hresult() {
+        Err(err).context("the window was closed")
+    } else {
+        Err(err.into())
+    }
+}
+
+/// whether a key was blocked by UIPI since the last call, e.g. to explain it once.
+pub fn take_blocked() -> bool {
+    BLOCKED.swap(false, Ordering::Relaxed)
+}
+
+fn scan_code(vk: VIRTUAL_KEY) -> u16 {
+    match vk {
+        VK_OEM_3 => SCANCODE_OEM_3,
+        vk => unsafe { MapVirtualKeyW(vk.0 as u32, MAPVK_VK_TO_VSC) as u16 },
+    }
+}
+
+pub fn key_lparam(vk: VIRTUAL_KEY) -> LPARAM {
+    match vk {
+        VK_OEM_3 => LPARAM(1 | 0b10 << 16),
+        vk => LPARAM(1 | (scan_code(vk) as isize) << 16),
+    }
+}
+
+/// synthesizes the key presses, which go through the IME like physical ones.
+pub fn send_input(hwnd: HWND, keys: impl IntoIterator<Item = VIRTUAL_KEY>) -> Result<()> {
+    ensure!(
+        unsafe { GetForegroundWindow() } == hwnd,
+        "SendInput requires {hwnd:?} to be the foreground window"
+    );
+
+    let key = |vk: VIRTUAL_KEY, flags: KEYBD_EVENT_FLAGS| INPUT {
+        r#type: INPUT_KEYBOARD,
+        Anonymous: INPUT_0 {
+            ki: KEYBDINPUT {
+                wVk: VIRTUAL_KEY(0),
+                wScan: scan_code(vk),
+                dwFlags: KEYEVENTF_SCANCODE | flags,
+                time: 0,
+                dwExtraInfo: 0,
+            },
+        },
+    };
+    let inputs: Vec<_> = keys
+        .into_iter()
+        .flat_map(|vk| [key(vk, KEYBD_EVENT_FLAGS(0)), key(vk, KEYEVENTF_KEYUP)])
+        .collect();
+
+    // note: the user still holds the modifiers, so our own hotkey would swallow the injected key.
+    let suspended = hotkey::suspend();
+    let sent = unsafe { SendInput(&inputs, mem::size_of::<INPUT>() as i32) };
+    thread::sleep(HOTKEY_RESUME_DELAY);
+    drop(suspended);
+
+    ensure!(
+        sent as usize == inputs.len(),
+        "SendInput was blocked: {}",
+        windows::core::Error::from_win32()
+    );
+    Ok(())
+}
+
+/// opens the command palette with F1, which no IME intercepts, types the command and runs it.
+///
+/// note: posted messages are processed in order, so the palette is open before the text arrives.
+fn command_palette(hwnd: HWND, action: Action) -> Result<()> {
+    post_key(hwnd, VK_F1, key_lparam(VK_F1))?;
+    for unit in action.command().encode_utf16() {
+        unsafe { PostMessageW(hwnd, WM_CHAR, WPARAM(unit as usize), LPARAM(1))? };
+    }
+    post_key(hwnd, VK_RETURN, key_lparam(VK_RETURN))
+}
@@ -0,0 +1,76 @@
+use anyhow::{Context, Result};
+use windows::{
+    core::{HSTRING, PWSTR},
+    Win32::{
+        Foundation::{CloseHandle, GetLastError, LocalFree, ERROR_ALREADY_EXISTS, HANDLE, HLOCAL},
+        Security::{
+            Authorization::ConvertSidToStringSidW, GetTokenInformation, TokenUser, TOKEN_QUERY,
+            TOKEN_USER,
+        },
+        System::{
+            RemoteDesktop::ProcessIdToSessionId,
+            Threading::{CreateMutexW, GetCurrentProcess, GetCurrentProcessId, OpenProcessToken},
+        },
+    },
+};
+
+use crate::{LogExt, PACKAGE_NAME};
+
+/// the string SID of the user we run as, e.g. `S-1-5-21-…-1001`.
+pub fn user_sid() -> Result<String> {
+    unsafe {
+        let mut token = HANDLE::default();
+        OpenProcessToken(GetCurrentProcess(), TOKEN_QUERY, &mut token)?;
+        let mut buffer = [0u64; 64];
+        let mut buffer_used = 0;
+        let result = GetTokenInformation(
+            token,
+            TokenUser,
+            Some(buffer.as_mut_ptr().cast()),
+            std::mem::size_of_val(&buffer) as u32,
+            &mut buffer_used,
+        );
+        CloseHandle(token).warn();
+        result.context("failed to query the token user")?;
+
+        let user = &*(buffer.as_ptr() as *const TOKEN_USER);
+        let mut sid = PWSTR::null();
+        ConvertSidToStringSidW(user.User.Sid, &mut sid)?;
+        let string = sid.to_string();
+        LocalFree(HLOCAL(sid.0.cast())).warn();
+        Ok(string?)
+    }
+}
+
+/// a name unique to this user in this session, for kernel objects and pipes shared between users.
+///
+/// note: pipes live in a global namespace, so the session is part of the name as well.
+pub fn scoped_name(kind: &str) -> Result<String> {
+    let mut session_id = 0;
+    unsafe { ProcessIdToSessionId(GetCurrentProcessId(), &mut session_id)? };
+    Ok(format!(
+        "{PACKAGE_NAME}-{kind}-{session_id}-{}",
+        user_sid()?
+    ))
+}
+
+/// held for as long as we run, so a second instance of the same user in the same session can tell.
+pub struct Instance(HANDLE);
+
+impl Drop for Instance {
+    fn drop(&mut self) {
+        unsafe { CloseHandle(self.0) }.warn();
+    }
+}
+
+/// takes the single-instance mutex, `None` if another instance of the user already runs.
+pub fn acquire() -> Result<Option<Instance>> {
+    let name = HSTRING::from(format!(r"Local\{}", scoped_name("instance")?));
+    let mutex = unsafe { CreateMutexW(None, false, &name)? };
+    let instance = Instance(mutex);
+    if matches!(unsafe { GetLastError() }, Err(err) if err.code() == ERROR_ALREADY_EXISTS.to_hresult())
+    {
+        return Ok(None);
+    }
+    Ok(Some(instance))
+}
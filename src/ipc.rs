@@ -0,0 +1,231 @@
+use std::{
+    io::{self, Write},
+    sync::mpsc,
+    thread,
+    time::Duration,
+};
+
+use anyhow::{bail, ensure, Context, Result};
+#[allow(unused_imports)]
+use tracing::{debug, error, info, trace, warn};
+use windows::{
+    core::HSTRING,
+    Win32::{
+        Foundation::{
+            CloseHandle, ERROR_BROKEN_PIPE, ERROR_FILE_NOT_FOUND, ERROR_PIPE_CONNECTED,
+            GENERIC_READ, GENERIC_WRITE, HANDLE, LPARAM, WPARAM,
+        },
+        Storage::FileSystem::{
+            CreateFileW, FlushFileBuffers, ReadFile, WriteFile, FILE_FLAGS_AND_ATTRIBUTES,
+            FILE_SHARE_NONE, OPEN_EXISTING, PIPE_ACCESS_DUPLEX,
+        },
+        System::Pipes::{
+            ConnectNamedPipe, CreateNamedPipeW, DisconnectNamedPipe, PIPE_READMODE_BYTE,
+            PIPE_REJECT_REMOTE_CLIENTS, PIPE_TYPE_BYTE, PIPE_UNLIMITED_INSTANCES, PIPE_WAIT,
+        },
+        UI::WindowsAndMessaging::{AllowSetForegroundWindow, PostThreadMessageW, ASFW_ANY, WM_APP},
+    },
+};
+
+use crate::{
+    bus::{Command, Event},
+    inject::{Action, ACTIONS},
+    instance, metrics, status, LogExt,
+};
+
+/// posted to the message pump with the index of the action in `ACTIONS` in `wParam`, to perform
+/// it as if its hotkey was pressed.
+pub const WM_TRIGGER: u32 = WM_APP + 15;
+
+/// the longest command a client may send.
+const MAX_COMMAND_LEN: usize = 256;
+
+/// how long the server waits before creating the pipe again after it failed.
+const ERROR_DELAY: Duration = Duration::from_secs(1);
+
+/// the pipe of the running instance, e.g. `\\.\pipe\vscode-cjk-toggle-terminal-fixer-ipc-1-S-1-5-…`.
+fn pipe_name() -> Result<HSTRING> {
+    Ok(HSTRING::from(format!(
+        r"\\.\pipe\{}",
+        instance::scoped_name("ipc")?
+    )))
+}
+
+/// what the commands act on, the message pump and the tray, which owns the pause.
+#[derive(Clone)]
+pub struct Control {
+    pub tid: u32,
+    pub tx: mpsc::Sender<Event>,
+}
+
+/// answers the commands of `request` on a thread of its own, e.g. `--status` run from a script.
+///
+/// note: the pipe rejects remote clients and is scoped to the user and session like the instance.
+pub fn serve(control: Control) -> Result<()> {
+    let name = pipe_name()?;
+    thread::Builder::new()
+        .name("ipc".to_owned())
+        .spawn(move || loop {
+            if accept(&name, &control).warn().is_none() {
+                thread::sleep(ERROR_DELAY);
+            }
+        })?;
+    Ok(())
+}
+
+/// waits for a client and answers its command, one client at a time.
+fn accept(name: &HSTRING, control: &Control) -> Result<()> {
+    let pipe = unsafe {
+        CreateNamedPipeW(
+            name,
+            PIPE_ACCESS_DUPLEX,
+            PIPE_TYPE_BYTE | PIPE_READMODE_BYTE | PIPE_WAIT | PIPE_REJECT_REMOTE_CLIENTS,
+            PIPE_UNLIMITED_INSTANCES,
+            0,
+            0,
+            0,
+            None,
+        )
+    };
+    if pipe.is_invalid() {
+        bail!(
+            "failed to create the pipe: {}",
+            windows::core::Error::from_win32()
+        );
+    }
+    let result = answer(pipe, control);
+    unsafe {
+        DisconnectNamedPipe(pipe).warn();
+        CloseHandle(pipe).warn();
+    }
+    result
+}
+
+fn answer(pipe: HANDLE, control: &Control) -> Result<()> {
+    match unsafe { ConnectNamedPipe(pipe, None) } {
+        // note: a client connecting before we wait for it is reported as an error.
+        Err(err) if err.code() != ERROR_PIPE_CONNECTED.to_hresult() => {
+            return Err(err).context("failed to wait for a client")
+        }
+        _ => {}
+    }
+    let command = read(pipe, Some(MAX_COMMAND_LEN))?;
+    let command = command.trim();
+    debug!("ipc command: {command:?}");
+    let response = match respond(control, command) {
+        Ok(response) => response,
+        Err(err) => format!("error: {err:#}\n"),
+    };
+    unsafe {
+        WriteFile(pipe, Some(response.as_bytes()), None, None)?;
+        FlushFileBuffers(pipe)?;
+    }
+    Ok(())
+}
+
+/// runs a command, e.g. `trigger toggle_terminal`, and returns the response.
+pub fn respond(control: &Control, command: &str) -> Result<String> {
+    let (name, argument) = command.split_once(' ').unwrap_or((command, ""));
+    match (name, argument) {
+        ("status", "") => status::json(),
+        ("metrics", "") => Ok(metrics::text()),
+        ("pause", "") => set_paused(control, true),
+        ("resume", "") => set_paused(control, false),
+        ("trigger", action) => trigger(control, action.parse()?),
+        _ => bail!("unknown command: {command:?}"),
+    }
+}
+
+/// note: the tray owns the pause, it's toggled as if clicked unless it's as requested already.
+fn set_paused(control: &Control, paused: bool) -> Result<String> {
+    control
+        .tx
+        .send(Event::Ipc(Command::SetPaused(paused)))
+        .context("the tray has exited")?;
+    Ok("ok\n".to_owned())
+}
+
+/// note: performed even while paused, the pause is about the hotkeys.
+fn trigger(control: &Control, action: Action) -> Result<String> {
+    let index = ACTIONS
+        .iter()
+        .position(|&a| a == action)
+        .expect("ACTIONS lists every action");
+    unsafe { PostThreadMessageW(control.tid, WM_TRIGGER, WPARAM(index), LPARAM(0))? };
+    Ok("ok\n".to_owned())
+}
+
+/// sends the command to the running instance and returns its response, `None` if none runs.
+pub fn request(command: &str) -> Result<Option<String>> {
+    let pipe = unsafe {
+        CreateFileW(
+            &pipe_name()?,
+            (GENERIC_READ | GENERIC_WRITE).0,
+            FILE_SHARE_NONE,
+            None,
+            OPEN_EXISTING,
+            FILE_FLAGS_AND_ATTRIBUTES(0),
+            HANDLE(0),
+        )
+    };
+    let pipe = match pipe {
+        Ok(pipe) => pipe,
+        Err(err) if err.code() == ERROR_FILE_NOT_FOUND.to_hresult() => return Ok(None),
+        Err(err) => return Err(err).context("failed to connect to the running instance"),
+    };
+    // note: the line ends the command, the server answers once it has read it.
+    let result = unsafe { WriteFile(pipe, Some(format!("{command}\n").as_bytes()), None, None) }
+        .context("failed to send the command")
+        .and_then(|()| read(pipe, None));
+    unsafe { CloseHandle(pipe).warn() };
+    let response = result?;
+    if let Some(err) = response.strip_prefix("error: ") {
+        bail!("the running instance failed: {}", err.trim());
+    }
+    Ok(Some(response))
+}
+
+/// asks the running instance to perform the action, failing if none runs.
+///
+/// note: we were started by the key press, so we may set the foreground window, the right is
+/// passed on for activating VSCode.
+pub fn send_trigger(action: Action) -> Result<()> {
+    unsafe { AllowSetForegroundWindow(ASFW_ANY) }.warn();
+    request(&format!("trigger {action}"))?.context("not running")?;
+    Ok(())
+}
+
+/// prints the running instance's response to the command, failing if none runs.
+///
+/// note: a release build has no console, so its standard output has to be redirected.
+pub fn print(command: &str) -> Result<()> {
+    let response = request(command)?.context("not running")?;
+    io::stdout()
+        .write_all(response.as_bytes())
+        .context("failed to write the standard output")
+}
+
+/// reads until the other end closes the pipe, or a command up to its line ending if `limit` is set.
+fn read(pipe: HANDLE, limit: Option<usize>) -> Result<String> {
+    let mut text = Vec::new();
+    let mut buffer = [0u8; 4096];
+    loop {
+        let mut read = 0;
+        match unsafe { ReadFile(pipe, Some(&mut buffer), Some(&mut read), None) } {
+            Ok(()) if read == 0 => break,
+            Ok(()) => text.extend_from_slice(&buffer[..read as usize]),
+            Err(err) if err.code() == ERROR_BROKEN_PIPE.to_hresult() => break,
+            Err(err) => return Err(err).context("failed to read from the pipe"),
+        }
+        if let Some(limit) = limit {
+            if text.contains(&b'\n') {
+                break;
+            }
+            ensure!(
+                text.len() <= limit,
+                "the command is longer than {limit} bytes"
+            );
+        }
+    }
+    String::from_utf8(text).context("invalid UTF-8 from the pipe")
+}
@@ -0,0 +1,449 @@
+use std::{
+    collections::BTreeMap,
+    env, fs, io,
+    path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Mutex,
+    },
+    thread,
+};
+
+use anyhow::{Context, Result};
+use serde::Deserialize;
+#[allow(unused_imports)]
+use tracing::{debug, error, info, trace, warn};
+use windows::{
+    core::HSTRING,
+    Win32::{
+        Foundation::HWND,
+        Storage::FileSystem::{
+            FindFirstChangeNotificationW, FindNextChangeNotification, FILE_NOTIFY_CHANGE_DIR_NAME,
+            FILE_NOTIFY_CHANGE_FILE_NAME, FILE_NOTIFY_CHANGE_LAST_WRITE,
+        },
+        System::Threading::{WaitForSingleObject, INFINITE},
+        UI::WindowsAndMessaging::IsWindow,
+    },
+};
+
+use crate::{hotkey::Chord, inject::Action, window, LockExt, LogExt};
+
+/// how the action is bound in the keybindings of a window's VSCode profile.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Binding {
+    /// VSCode's default keybinding applies.
+    Default,
+    /// the chords pressed one after another, e.g. `ctrl+k ctrl+t`.
+    Chords(Vec<Chord>),
+    /// the default keybinding was removed, or the action is bound to keys we can't name.
+    Other,
+}
+
+/// `%APPDATA%\Code\User`, which holds the default profile's settings.
+fn user_dir() -> Option<PathBuf> {
+    Some(PathBuf::from(env::var_os("APPDATA")?).join(r"Code\User"))
+}
+
+/// the profiles VSCode keeps in `globalStorage\storage.json`.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+struct Storage {
+    user_data_profiles: Vec<Profile>,
+    profile_associations: Associations,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Profile {
+    name: String,
+    /// the folder of the profile below `profiles`, e.g. `-6b3a6f0b`.
+    location: String,
+    #[serde(default)]
+    use_default_flags: UseDefaultFlags,
+}
+
+/// what a profile shares with the default profile.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+struct UseDefaultFlags {
+    keybindings: bool,
+}
+
+/// the profile each folder was last opened with, e.g. `file:///c%3A/src/crate` to `-6b3a6f0b`.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+struct Associations {
+    workspaces: BTreeMap<String, String>,
+}
+
+/// an entry of `keybindings.json`, a command starting with `-` removes a keybinding.
+#[derive(Debug, Clone, Deserialize)]
+struct Entry {
+    #[serde(default)]
+    key: String,
+    command: String,
+}
+
+/// the parsed files by path, `None` if missing, until `forget`.
+static FILES: Mutex<BTreeMap<PathBuf, Option<Cached>>> = Mutex::new(BTreeMap::new());
+
+/// the bindings resolved for a window by its handle, until `forget`.
+static BINDINGS: Mutex<BTreeMap<(isize, Action), Binding>> = Mutex::new(BTreeMap::new());
+
+/// whether `watch` keeps an eye on the user dir, nothing is cached otherwise as it'd go stale.
+static WATCHING: AtomicBool = AtomicBool::new(false);
+
+#[derive(Debug, Clone)]
+enum Cached {
+    Storage(Storage),
+    Keybindings(Vec<Entry>),
+}
+
+/// how the action is bound in the profile of the window.
+///
+/// note: on the path of every hotkey, so it's resolved once per window until the user dir changes,
+/// and anything unreadable falls back to the default keybinding.
+pub fn of(hwnd: HWND, action: Action) -> Binding {
+    let key = (hwnd.0, action);
+    let known = BINDINGS.locked().get(&key).cloned();
+    if let Some(binding) = known {
+        return binding;
+    }
+    let binding = match path(hwnd).map(|path| cached(&path, parse_keybindings)) {
+        Some(Ok(Some(Cached::Keybindings(entries)))) => resolve(&entries, action),
+        Some(Ok(_)) | None => Binding::Default,
+        Some(Err(err)) => {
+            debug!("{err:#}");
+            Binding::Default
+        }
+    };
+    if WATCHING.load(Ordering::Relaxed) {
+        // note: the handles of closed windows are reused by later ones.
+        let mut bindings = BINDINGS.locked();
+        bindings.retain(|&(hwnd, _), _| unsafe { IsWindow(HWND(hwnd)) }.as_bool());
+        bindings.insert(key, binding.clone());
+    }
+    binding
+}
+
+/// drops everything resolved whenever a file in the user dir changes, e.g. `keybindings.json` was
+/// saved or another profile became active.
+pub fn watch() -> Result<()> {
+    let user_dir = user_dir().context("unknown %APPDATA%")?;
+    let change = unsafe {
+        FindFirstChangeNotificationW(
+            &HSTRING::from(user_dir.to_string_lossy().as_ref()),
+            true,
+            FILE_NOTIFY_CHANGE_FILE_NAME
+                | FILE_NOTIFY_CHANGE_DIR_NAME
+                | FILE_NOTIFY_CHANGE_LAST_WRITE,
+        )
+    }
+    .with_context(|| format!("failed to watch {user_dir:?}"))?;
+    WATCHING.store(true, Ordering::Relaxed);
+    thread::Builder::new()
+        .name("keybindings".to_owned())
+        .spawn(move || loop {
+            unsafe { WaitForSingleObject(change, INFINITE) };
+            forget();
+            if unsafe { FindNextChangeNotification(change) }
+                .warn()
+                .is_none()
+            {
+                WATCHING.store(false, Ordering::Relaxed);
+                return;
+            }
+        })?;
+    Ok(())
+}
+
+/// drops everything resolved, it's resolved again on the next press.
+pub fn forget() {
+    FILES.locked().clear();
+    BINDINGS.locked().clear();
+}
+
+/// the `keybindings.json` of the window's profile, the default profile's unless another is active.
+pub fn path(hwnd: HWND) -> Option<PathBuf> {
+    let user_dir = user_dir()?;
+    let dir = match profile(&user_dir, hwnd) {
+        Some(profile) if !profile.use_default_flags.keybindings => {
+            user_dir.join("profiles").join(profile.location)
+        }
+        _ => user_dir,
+    };
+    Some(dir.join("keybindings.json"))
+}
+
+/// the profile of the window, named in its title by VSCode's default `window.title`, or else the
+/// one its folder was last opened with.
+fn profile(user_dir: &Path, hwnd: HWND) -> Option<Profile> {
+    let storage = user_dir.join(r"globalStorage\storage.json");
+    let storage = match cached(&storage, parse_storage) {
+        Ok(Some(Cached::Storage(storage))) => storage,
+        Ok(_) => return None,
+        Err(err) => {
+            debug!("{err:#}");
+            return None;
+        }
+    };
+    if storage.user_data_profiles.is_empty() {
+        return None;
+    }
+
+    let title = window::title(hwnd);
+    // note: e.g. `main.rs - crate - Work - Visual Studio Code`, the app name is never the profile.
+    let mut segments = title.rsplit(" - ").skip(1).map(str::trim);
+    if let Some(profile) = segments.clone().find_map(|segment| {
+        storage
+            .user_data_profiles
+            .iter()
+            .find(|profile| profile.name == segment)
+    }) {
+        return Some(profile.clone());
+    }
+    let folder = segments.next()?;
+    let location = storage
+        .profile_associations
+        .workspaces
+        .iter()
+        .find(|(uri, _)| {
+            let name = uri.trim_end_matches('/').rsplit('/').next().unwrap_or(uri);
+            percent_decode(name).eq_ignore_ascii_case(folder)
+        })
+        .map(|(_, location)| location)?;
+    storage
+        .user_data_profiles
+        .into_iter()
+        .find(|profile| &profile.location == location)
+}
+
+/// resolves the entries like VSCode, later ones win and a removal drops the earlier bindings.
+fn resolve(entries: &[Entry], action: Action) -> Binding {
+    let command = action.command_id();
+    let mut bound = Vec::new();
+    let mut removed_default = false;
+    for entry in entries {
+        if entry.command == command {
+            bound.push(entry.key.as_str());
+        } else if entry.command.strip_prefix('-') == Some(command) {
+            if entry.key.is_empty() {
+                bound.clear();
+                removed_default = true;
+            } else {
+                let count = bound.len();
+                bound.retain(|key| !key.eq_ignore_ascii_case(&entry.key));
+                removed_default |= bound.len() == count;
+            }
+        }
+    }
+    match bound.last() {
+        Some(key) => key
+            .split_whitespace()
+            .map(str::parse)
+            .collect::<Result<_>>()
+            .map_or(Binding::Other, Binding::Chords),
+        None if removed_default => Binding::Other,
+        None => Binding::Default,
+    }
+}
+
+/// the parsed file, `None` if it doesn't exist, e.g. a profile without keybindings of its own.
+fn cached(path: &Path, parse: fn(&str) -> Result<Cached>) -> Result<Option<Cached>> {
+    let known = FILES.locked().get(path).cloned();
+    if let Some(parsed) = known {
+        return Ok(parsed);
+    }
+    let parsed = match fs::read_to_string(path) {
+        Ok(text) => {
+            Some(parse(&strip_comments(&text)).with_context(|| format!("invalid {path:?}"))?)
+        }
+        Err(err) if err.kind() == io::ErrorKind::NotFound => None,
+        Err(err) => return Err(err).with_context(|| format!("failed to read {path:?}")),
+    };
+    if WATCHING.load(Ordering::Relaxed) {
+        let mut files = FILES.locked();
+        files.insert(path.to_owned(), parsed.clone());
+    }
+    Ok(parsed)
+}
+
+fn parse_storage(text: &str) -> Result<Cached> {
+    Ok(Cached::Storage(serde_json::from_str(text)?))
+}
+
+fn parse_keybindings(text: &str) -> Result<Cached> {
+    Ok(Cached::Keybindings(serde_json::from_str(text)?))
+}
+
+/// turns VSCode's JSON with comments into JSON, dropping the comments and trailing commas.
+fn strip_comments(text: &str) -> String {
+    let mut json = String::with_capacity(text.len());
+    let mut chars = text.chars().peekable();
+    let mut in_string = false;
+    while let Some(c) = chars.next() {
+        if in_string {
+            json.push(c);
+            match c {
+                '\\' => json.extend(chars.next()),
+                '"' => in_string = false,
+                _ => {}
+            }
+            continue;
+        }
+        match (c, chars.peek()) {
+            ('"', _) => {
+                in_string = true;
+                json.push(c);
+            }
+            ('/', Some('/')) => while chars.next_if(|&c| c != '\n').is_some() {},
+            ('/', Some('*')) => {
+                chars.next();
+                let mut last = ' ';
+                for c in chars.by_ref() {
+                    if (last, c) == ('*', '/') {
+                        break;
+                    }
+                    last = c;
+                }
+            }
+            ('}' | ']', _) => {
+                let end = json.trim_end().len();
+                if json[..end].ends_with(',') {
+                    json.truncate(end - 1);
+                }
+                json.push(c);
+            }
+            _ => json.push(c),
+        }
+    }
+    json
+}
+
+/// decodes the `%XX` escapes of a URI, e.g. the folder name of `file:///c%3A/src/%E6%97%A5`.
+fn percent_decode(text: &str) -> String {
+    let mut bytes = Vec::with_capacity(text.len());
+    let mut rest = text.as_bytes();
+    while let Some((&byte, tail)) = rest.split_first() {
+        let escaped = tail
+            .get(..2)
+            .and_then(|hex| std::str::from_utf8(hex).ok())
+            .and_then(|hex| u8::from_str_radix(hex, 16).ok());
+        match escaped {
+            Some(decoded) if byte == b'%' => {
+                bytes.push(decoded);
+                rest = &tail[2..];
+            }
+            _ => {
+                bytes.push(byte);
+                rest = tail;
+            }
+        }
+    }
+    String::from_utf8_lossy(&bytes).into_owned()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fuzz;
+
+    #[test]
+    fn strip_comments_keeps_json() {
+        let text = r#"// Place your key bindings in this file
+[
+    { "key": "ctrl+k ctrl+t", "command": "workbench.action.terminal.toggleTerminal" }, /* ours */
+    { "key": "ctrl+`", "command": "-workbench.action.terminal.toggleTerminal", },
+    { "key": "ctrl+/", "command": "a // b, /* c */" },
+]
+"#;
+        assert_eq!(
+            strip_comments(text),
+            r#"
+[
+    { "key": "ctrl+k ctrl+t", "command": "workbench.action.terminal.toggleTerminal" }, 
+    { "key": "ctrl+`", "command": "-workbench.action.terminal.toggleTerminal"},
+    { "key": "ctrl+/", "command": "a // b, /* c */" }]
+"#
+        );
+    }
+
+    #[test]
+    fn strip_comments_takes_arbitrary_input() {
+        let pieces = [
+            "//", "/*", "*/", "*", "/", "\"", "\\", ",", "}", "]", "{", " ", "a",
+        ];
+        for text in fuzz::strings(&pieces) {
+            let json = strip_comments(&text);
+            assert!(json.len() <= text.len(), "{text:?}");
+        }
+    }
+
+    #[test]
+    fn percent_decode_decodes_escapes() {
+        assert_eq!(percent_decode("c%3A"), "c:");
+        assert_eq!(percent_decode("%E6%97%A5%E6%9C%AC"), "日本");
+        assert_eq!(percent_decode("100%"), "100%");
+        assert_eq!(percent_decode("%zz%4"), "%zz%4");
+    }
+
+    #[test]
+    fn percent_decode_takes_arbitrary_input() {
+        let pieces = ["%", "2", "F", "e6", "97", "a5", "zz", "a", "/"];
+        for text in fuzz::strings(&pieces) {
+            percent_decode(&text);
+        }
+    }
+
+    /// the entries of a keybindings.json, `(key, command)` in file order.
+    fn file(entries: &[(&str, &str)]) -> Vec<Entry> {
+        entries
+            .iter()
+            .map(|&(key, command)| Entry {
+                key: key.to_owned(),
+                command: command.to_owned(),
+            })
+            .collect()
+    }
+
+    fn chords(keys: &str) -> Binding {
+        Binding::Chords(keys.split(' ').map(|key| key.parse().unwrap()).collect())
+    }
+
+    const TOGGLE: &str = "workbench.action.terminal.toggleTerminal";
+
+    #[test]
+    fn later_bindings_win() {
+        let entries = file(&[
+            ("ctrl+j", TOGGLE),
+            ("ctrl+k ctrl+t", TOGGLE),
+            ("ctrl+q", "workbench.action.terminal.new"),
+        ]);
+        assert_eq!(
+            resolve(&entries, Action::ToggleTerminal),
+            chords("Ctrl+K Ctrl+T")
+        );
+        assert_eq!(resolve(&entries, Action::NewTerminal), chords("Ctrl+Q"));
+        assert_eq!(resolve(&entries, Action::FocusTerminal), Binding::Default);
+    }
+
+    #[test]
+    fn removals_drop_earlier_bindings() {
+        let removed = format!("-{TOGGLE}");
+        // note: removing a binding of the file itself leaves the default in place.
+        let entries = file(&[("ctrl+j", TOGGLE), ("CTRL+J", &removed)]);
+        assert_eq!(resolve(&entries, Action::ToggleTerminal), Binding::Default);
+        let entries = file(&[("ctrl+`", &removed)]);
+        assert_eq!(resolve(&entries, Action::ToggleTerminal), Binding::Other);
+        let entries = file(&[("ctrl+j", TOGGLE), ("", &removed)]);
+        assert_eq!(resolve(&entries, Action::ToggleTerminal), Binding::Other);
+        let entries = file(&[("", &removed), ("ctrl+j", TOGGLE)]);
+        assert_eq!(resolve(&entries, Action::ToggleTerminal), chords("Ctrl+J"));
+    }
+
+    #[test]
+    fn unnameable_keys_are_other() {
+        let entries = file(&[("ctrl+j", TOGGLE), ("ctrl+pagedown", TOGGLE)]);
+        assert_eq!(resolve(&entries, Action::ToggleTerminal), Binding::Other);
+    }
+}
@@ -0,0 +1,159 @@
+use std::{cell::Cell, mem};
+
+use anyhow::{ensure, Result};
+#[allow(unused_imports)]
+use tracing::{debug, error, info, trace, warn};
+use windows::{
+    core::{w, PCWSTR},
+    Win32::{
+        Foundation::{HANDLE, HWND, LPARAM, LRESULT, WPARAM},
+        System::LibraryLoader::GetModuleHandleW,
+        UI::{
+            Input::{
+                GetRawInputData, GetRawInputDeviceInfoW, RegisterRawInputDevices, HRAWINPUT,
+                RAWINPUT, RAWINPUTDEVICE, RAWINPUTHEADER, RIDEV_INPUTSINK, RIDI_DEVICENAME,
+                RID_INPUT, RIM_TYPEKEYBOARD,
+            },
+            WindowsAndMessaging::{
+                CreateWindowExW, DefWindowProcW, DestroyWindow, RegisterClassW, HMENU,
+                HWND_MESSAGE, RI_KEY_BREAK, WINDOW_EX_STYLE, WINDOW_STYLE, WM_INPUT, WNDCLASSW,
+            },
+        },
+    },
+};
+
+use crate::{config::Config, LogExt};
+
+/// the HID usage of keyboards on the generic desktop page.
+const HID_USAGE_PAGE_GENERIC: u16 = 0x01;
+const HID_USAGE_GENERIC_KEYBOARD: u16 = 0x06;
+
+thread_local! {
+    /// the device that produced the latest key press, `0` for injected input.
+    static LAST_DEVICE: Cell<HANDLE> = const { Cell::new(HANDLE(0)) };
+}
+
+/// starts telling apart the keyboards keys are pressed on via Raw Input.
+///
+/// note: Raw Input needs a window, so a message-only one is created on the calling thread.
+pub fn watch() -> Result<HWND> {
+    unsafe {
+        let instance = GetModuleHandleW(PCWSTR::null())?;
+        let class = WNDCLASSW {
+            lpfnWndProc: Some(on_message),
+            hInstance: instance.into(),
+            lpszClassName: w!("vscode-cjk-toggle-terminal-fixer-keyboard"),
+            ..Default::default()
+        };
+        ensure!(
+            RegisterClassW(&class) != 0,
+            "failed to register the window class: {}",
+            windows::core::Error::from_win32()
+        );
+        let hwnd = CreateWindowExW(
+            WINDOW_EX_STYLE(0),
+            class.lpszClassName,
+            PCWSTR::null(),
+            WINDOW_STYLE(0),
+            0,
+            0,
+            0,
+            0,
+            HWND_MESSAGE,
+            HMENU(0),
+            instance,
+            None,
+        );
+        ensure!(
+            hwnd != HWND(0),
+            "failed to create the message window: {}",
+            windows::core::Error::from_win32()
+        );
+        // note: the input sink receives the key presses while other applications are focused.
+        let device = RAWINPUTDEVICE {
+            usUsagePage: HID_USAGE_PAGE_GENERIC,
+            usUsage: HID_USAGE_GENERIC_KEYBOARD,
+            dwFlags: RIDEV_INPUTSINK,
+            hwndTarget: hwnd,
+        };
+        RegisterRawInputDevices(&[device], mem::size_of::<RAWINPUTDEVICE>() as u32)?;
+        Ok(hwnd)
+    }
+}
+
+pub fn unwatch(hwnd: HWND) {
+    unsafe { DestroyWindow(hwnd) }.warn();
+}
+
+/// whether the hotkey just pressed came from one of the configured keyboards.
+///
+/// note: the raw input of a key press is reported before the hotkey it completes.
+pub fn intercepts(config: &Config) -> bool {
+    if config.keyboards.is_empty() {
+        return true;
+    }
+    let Some(name) = device_name(LAST_DEVICE.with(Cell::get)) else {
+        debug!("hotkey from an unknown keyboard");
+        return false;
+    };
+    info!("hotkey from keyboard {name}");
+    let name = name.to_ascii_lowercase();
+    config
+        .keyboards
+        .iter()
+        .any(|keyboard| name.contains(&keyboard.to_ascii_lowercase()))
+}
+
+/// the device interface path, e.g. `\\?\HID#VID_04FE&PID_0021&MI_00#…`.
+fn device_name(device: HANDLE) -> Option<String> {
+    if device == HANDLE(0) {
+        return None;
+    }
+    let mut len = 0;
+    unsafe { GetRawInputDeviceInfoW(device, RIDI_DEVICENAME, None, &mut len) };
+    let mut name = vec![0u16; len as usize];
+    let copied = unsafe {
+        GetRawInputDeviceInfoW(
+            device,
+            RIDI_DEVICENAME,
+            Some(name.as_mut_ptr().cast()),
+            &mut len,
+        )
+    };
+    if copied == u32::MAX {
+        warn!(
+            "failed to get the name of {device:?}: {}",
+            windows::core::Error::from_win32()
+        );
+        return None;
+    }
+    let name = String::from_utf16_lossy(&name[..copied as usize]);
+    Some(name.trim_end_matches('\0').to_owned())
+}
+
+unsafe extern "system" fn on_message(
+    hwnd: HWND,
+    msg: u32,
+    wparam: WPARAM,
+    lparam: LPARAM,
+) -> LRESULT {
+    if msg == WM_INPUT {
+        let mut input: RAWINPUT = mem::zeroed();
+        let mut size = mem::size_of::<RAWINPUT>() as u32;
+        let copied = GetRawInputData(
+            HRAWINPUT(lparam.0),
+            RID_INPUT,
+            Some(&mut input as *mut RAWINPUT as *mut _),
+            &mut size,
+            mem::size_of::<RAWINPUTHEADER>() as u32,
+        );
+        if copied != u32::MAX
+            && input.header.dwType == RIM_TYPEKEYBOARD.0
+            && (input.data.keyboard.Flags as u32 & RI_KEY_BREAK) == 0
+        {
+            LAST_DEVICE.with(|device| device.set(input.header.hDevice));
+        }
+    }
+    // note: the system frees the raw input only when it's passed on.
+    DefWindowProcW(hwnd, msg, wparam, lparam)
+}
@@ -0,0 +1,105 @@
+use std::cell::Cell;
+
+use anyhow::Result;
+#[allow(unused_imports)]
+use tracing::{debug, error, info, trace, warn};
+use windows::Win32::{
+    Foundation::{HINSTANCE, LPARAM, LRESULT, WPARAM},
+    UI::{
+        Input::KeyboardAndMouse::VIRTUAL_KEY,
+        WindowsAndMessaging::{
+            CallNextHookEx, SetWindowsHookExW, UnhookWindowsHookEx, HHOOK, KBDLLHOOKSTRUCT,
+            LLKHF_INJECTED, WH_KEYBOARD_LL, WM_KEYDOWN, WM_KEYUP, WM_SYSKEYDOWN, WM_SYSKEYUP,
+        },
+    },
+};
+
+use crate::{chord, gesture, peek, LogExt};
+
+/// what listens to the keys through the shared hook.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Listener {
+    /// the second chord of a two-step hotkey.
+    Chord,
+    /// the release of a held hotkey.
+    Peek,
+    /// the taps of a double-tap gesture.
+    Gesture,
+}
+
+impl Listener {
+    fn bit(self) -> u8 {
+        1 << self as u8
+    }
+}
+
+/// a key pressed or released by the user, injected ones never reach the listeners.
+#[derive(Debug, Clone, Copy)]
+pub struct Key {
+    pub vk: VIRTUAL_KEY,
+    pub scan_code: u32,
+    pub down: bool,
+    /// the time of the event in `GetTickCount` milliseconds.
+    pub time: u32,
+}
+
+thread_local! {
+    static HOOK: Cell<HHOOK> = const { Cell::new(HHOOK(0)) };
+    /// the listeners by `Listener::bit`.
+    static LISTENING: Cell<u8> = const { Cell::new(0) };
+}
+
+/// starts or stops handing the keys to the listener, the hook is installed on the calling thread
+/// while anything listens.
+///
+/// note: a low-level hook costs every key press of the session, so there's one for all listeners.
+pub fn listen(listener: Listener, on: bool) -> Result<()> {
+    let listening = LISTENING.get();
+    let listening = if on {
+        listening | listener.bit()
+    } else {
+        listening & !listener.bit()
+    };
+    let hook = HOOK.get();
+    if listening != 0 && hook.0 == 0 {
+        HOOK.set(unsafe { SetWindowsHookExW(WH_KEYBOARD_LL, Some(on_key), HINSTANCE(0), 0)? });
+    } else if listening == 0 && hook.0 != 0 {
+        unsafe { UnhookWindowsHookEx(hook) }.warn();
+        HOOK.set(HHOOK(0));
+    }
+    LISTENING.set(listening);
+    Ok(())
+}
+
+fn is_listening(listener: Listener) -> bool {
+    LISTENING.get() & listener.bit() != 0
+}
+
+unsafe extern "system" fn on_key(code: i32, wparam: WPARAM, lparam: LPARAM) -> LRESULT {
+    let down = matches!(wparam.0 as u32, WM_KEYDOWN | WM_SYSKEYDOWN);
+    let up = matches!(wparam.0 as u32, WM_KEYUP | WM_SYSKEYUP);
+    if code >= 0 && (down || up) {
+        let info = &*(lparam.0 as *const KBDLLHOOKSTRUCT);
+        // note: our own injected keys mustn't complete or interrupt anything.
+        if (info.flags.0 & LLKHF_INJECTED.0) == 0 {
+            let key = Key {
+                vk: VIRTUAL_KEY(info.vkCode as u16),
+                scan_code: info.scanCode,
+                down,
+                time: info.time,
+            };
+            // note: every listener sees the key, even one another listener swallows.
+            let swallowed = is_listening(Listener::Chord) && chord::on_key(&key);
+            if is_listening(Listener::Peek) {
+                peek::on_key(&key);
+            }
+            if is_listening(Listener::Gesture) {
+                gesture::on_key(&key);
+            }
+            if swallowed {
+                return LRESULT(1);
+            }
+        }
+    }
+    CallNextHookEx(HHOOK(0), code, wparam, lparam)
+}
@@ -0,0 +1,63 @@
+use std::{
+    env,
+    path::PathBuf,
+    process::Command,
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+use anyhow::{Context, Result};
+#[allow(unused_imports)]
+use tracing::{debug, error, info, trace, warn};
+use windows::Win32::UI::WindowsAndMessaging::WM_APP;
+
+use crate::{config::Config, hotkey::Hotkey, LockExt};
+
+/// how long we wait for the launched VSCode window before giving up on the pending action.
+const LAUNCH_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// posted to the message pump with the window of the launched VSCode in `wParam`.
+pub const WM_LAUNCHED: u32 = WM_APP + 1;
+
+static PENDING: Mutex<Option<(Instant, Hotkey)>> = Mutex::new(None);
+
+/// launches VSCode and remembers to perform the action once its window shows up.
+///
+/// note: VSCode restores the last workspace by itself, so no arguments are passed.
+pub fn vscode(config: &Config, hotkey: Hotkey) -> Result<()> {
+    let code_path = config
+        .code_path
+        .clone()
+        .or_else(detect_code_path)
+        .context("VSCode executable not found, set `code_path` in config")?;
+    info!("launching {code_path:?}");
+    Command::new(&code_path)
+        .spawn()
+        .with_context(|| format!("failed to launch {code_path:?}"))?;
+    *PENDING.locked() = Some((Instant::now(), hotkey));
+    Ok(())
+}
+
+/// returns whether a launched VSCode is still waiting for its action.
+pub fn is_pending() -> bool {
+    PENDING
+        .locked()
+        .is_some_and(|(launched_at, _)| launched_at.elapsed() < LAUNCH_TIMEOUT)
+}
+
+/// takes the hotkey a launched VSCode is still waiting for.
+pub fn take_pending() -> Option<Hotkey> {
+    PENDING
+        .locked()
+        .take()
+        .filter(|(launched_at, _)| launched_at.elapsed() < LAUNCH_TIMEOUT)
+        .map(|(_, hotkey)| hotkey)
+}
+
+pub fn detect_code_path() -> Option<PathBuf> {
+    [("LOCALAPPDATA", "Programs"), ("ProgramFiles", "")]
+        .into_iter()
+        .filter_map(|(var, dir)| Some(PathBuf::from(env::var_os(var)?).join(dir)))
+        .map(|base| base.join("Microsoft VS Code").join("Code.exe"))
+        .find(|path| path.is_file())
+}
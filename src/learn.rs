@@ -0,0 +1,84 @@
+use std::{collections::BTreeSet, path::PathBuf, sync::Mutex, thread};
+
+#[allow(unused_imports)]
+use tracing::{debug, error, info, trace, warn};
+use windows::{
+    core::HSTRING,
+    Win32::{
+        Foundation::{HWND, LPARAM, WPARAM},
+        UI::WindowsAndMessaging::{
+            GetClassNameW, GetWindowTextW, MessageBoxW, PostThreadMessageW, IDYES, MB_ICONQUESTION,
+            MB_SETFOREGROUND, MB_YESNO,
+        },
+    },
+};
+
+use crate::{
+    config::{self, Config},
+    procs, window, LockExt, LogExt, PACKAGE_NAME,
+};
+
+struct Learning {
+    /// the config file the answered apps are added to.
+    path: PathBuf,
+    /// the thread whose message pump reloads the config.
+    tid: u32,
+    /// the executables asked about already, each is asked about once per learning session.
+    asked: BTreeSet<String>,
+}
+
+static LEARNING: Mutex<Option<Learning>> = Mutex::new(None);
+
+/// starts or stops asking whether to fix the app on each hotkey pressed outside of a target.
+pub fn set(path: PathBuf, tid: u32, enabled: bool) {
+    let learning = enabled.then(|| Learning {
+        path,
+        tid,
+        asked: BTreeSet::new(),
+    });
+    *LEARNING.locked() = learning;
+    info!("learning mode {}", if enabled { "on" } else { "off" });
+}
+
+/// logs the window the hotkey was pressed in and asks whether to add its app to the targets, unless
+/// it's one already.
+pub fn observe(config: &Config, hwnd: HWND) {
+    let mut learning = LEARNING.locked();
+    let Some(learning) = learning.as_mut() else {
+        return;
+    };
+    let name = procs::name(procs::of_window(hwnd)).unwrap_or_default();
+    let (title, class) = (
+        text(hwnd, GetWindowTextW::<HWND>),
+        text(hwnd, GetClassNameW::<HWND>),
+    );
+    info!("learning: {name} {title:?} {class:?}");
+    if name.is_empty() || window::is_target(config, hwnd) || !learning.asked.insert(name.clone()) {
+        return;
+    }
+
+    let (path, tid) = (learning.path.clone(), learning.tid);
+    // note: the message box blocks until answered, the message pump mustn't wait for it.
+    thread::spawn(move || {
+        let answer = unsafe {
+            MessageBoxW(
+                HWND(0),
+                &HSTRING::from(format!("Should I fix {name}?\n\n{title}")),
+                &HSTRING::from(PACKAGE_NAME),
+                MB_YESNO | MB_ICONQUESTION | MB_SETFOREGROUND,
+            )
+        };
+        if answer != IDYES {
+            return;
+        }
+        if window::add_target(&path, hwnd).warn().is_some() {
+            unsafe { PostThreadMessageW(tid, config::WM_RELOAD, WPARAM(0), LPARAM(0)) }.warn();
+        }
+    });
+}
+
+fn text(hwnd: HWND, get: unsafe fn(HWND, &mut [u16]) -> i32) -> String {
+    let mut buffer = [0u16; 512];
+    let len = unsafe { get(hwnd, &mut buffer) } as usize;
+    String::from_utf16_lossy(&buffer[..len])
+}
@@ -0,0 +1,1222 @@
+mod ahk;
+mod alternate;
+mod archive;
+mod autostart;
+mod backup;
+mod bench;
+mod bus;
+mod chord;
+mod cleanup;
+mod cli;
+mod com;
+mod compat;
+pub mod config;
+mod conflict;
+mod crashloop;
+mod desktop;
+mod diagnostics;
+mod dpi;
+mod errors;
+#[cfg(test)]
+mod fuzz;
+mod gesture;
+mod heartbeat;
+mod history;
+pub mod hotkey;
+mod http;
+#[cfg(feature = "http-api")]
+mod http_api;
+mod icons;
+mod ime;
+mod inject;
+mod instance;
+#[cfg(feature = "ipc")]
+mod ipc;
+mod keybindings;
+mod keyboard;
+mod keyhook;
+mod launch;
+mod learn;
+mod lifecycle;
+mod logfile;
+mod metrics;
+mod migration;
+mod overrides;
+mod peek;
+mod powertoys;
+mod presentation;
+mod preset;
+mod probe;
+mod procs;
+mod pump;
+mod quake;
+mod quiet;
+mod quirk;
+mod recent;
+mod remote;
+mod restart;
+mod retry;
+mod rules;
+mod scancode;
+mod schedule;
+mod session;
+mod snapshot;
+mod state;
+mod status;
+mod summary;
+mod telemetry;
+mod theme;
+mod timer;
+mod timestamp;
+mod tooltip;
+mod tray;
+mod troubleshoot;
+mod uia;
+mod upgrade;
+mod verify;
+#[cfg(feature = "settings-ui")]
+mod viewer;
+mod window;
+mod workspace;
+
+use std::{
+    env, mem,
+    path::{Path, PathBuf},
+    process,
+    sync::{mpsc, Mutex, MutexGuard, PoisonError},
+    thread,
+    time::{Duration, Instant},
+};
+
+use anyhow::{Context, Result};
+#[allow(unused_imports)]
+use tracing::{debug, error, field, info, info_span, trace, warn, Span};
+use tracing_subscriber::fmt::{format::FmtSpan, writer::MakeWriterExt};
+use windows::{
+    core::HSTRING,
+    Win32::{
+        Foundation::{BOOL, HWND, LPARAM, WPARAM},
+        System::Threading::{
+            GetCurrentThread, GetCurrentThreadId, SetThreadPriority, THREAD_PRIORITY_ABOVE_NORMAL,
+        },
+        UI::WindowsAndMessaging::{
+            GetForegroundWindow, GetMessageW, MessageBoxW, PostThreadMessageW, MB_ICONINFORMATION,
+            MB_OK, MSG, WM_QUIT, WM_TIMER,
+        },
+    },
+};
+
+use crate::{
+    autostart::Autostart,
+    bus::{Bus, Change, Event, Flow, MenuAction, Timer, Timers},
+    config::Config,
+    history::Outcome,
+    hotkey::Hotkey,
+    icons::Icons,
+    inject::Action,
+    lifecycle::Phase,
+    logfile::{LogFile, Logging},
+    state::State,
+    theme::MenuIcon,
+    tooltip::Tooltip,
+    tray::{Item, Menu, Tray},
+    window::Hidden,
+};
+
+const PACKAGE_NAME: &'static str = env!("CARGO_PKG_NAME");
+const PACKAGE_VERSION: &'static str = env!("CARGO_PKG_VERSION");
+
+/// how long the tray waits for more display changes before reloading the icons, e.g. while a
+/// monitor is plugged in.
+const DISPLAY_SETTLE_DELAY: Duration = Duration::from_millis(500);
+
+/// how long a hotkey may take from the key press until its action is injected.
+const LATENCY_BUDGET: Duration = Duration::from_millis(50);
+
+/// the whole app, `main.rs` only picks the subsystem.
+///
+/// note: a library so the fuzz targets in `fuzz/` can link against the parsers.
+pub fn run() -> Result<()> {
+    let app_path = env::current_exe();
+    // note: read again by `logged_main`, which logs what's wrong with it.
+    let config = app_path
+        .as_deref()
+        .ok()
+        .and_then(|app_path| Config::load(&Config::path(app_path)).ok())
+        .unwrap_or_default();
+    let logging = overrides::logging();
+    let log_path = match logging
+        .as_ref()
+        .and_then(|logging| logging.as_ref().ok())
+        .copied()
+        .unwrap_or(config.logging)
+    {
+        Logging::File => Some(match app_path.as_deref() {
+            Ok(app_path) => app_path.with_file_name(log_file_name()),
+            Err(_) => PathBuf::from(log_file_name()),
+        }),
+        Logging::Off => None,
+    };
+
+    tracing_subscriber::fmt()
+        .with_ansi(false)
+        .with_timer(timestamp::Timestamp(config.log_timezone))
+        // note: logs how long each hotkey took, see `on_hotkey`.
+        .with_span_events(FmtSpan::CLOSE)
+        // note: the recent events are kept for "Show Recent Events" in the tray.
+        .with_writer(LogFile::new(log_path).and(recent::Recent))
+        .init();
+    if let Some(Err(err)) = logging {
+        warn!("{err:?}");
+    }
+
+    let app_path = app_path.warn();
+    let result = logged_main(app_path.as_deref());
+    if let Err(ref err) = result {
+        error!("{err:?}");
+    }
+
+    result
+}
+
+/// the log file next to the executable.
+///
+/// note: users sharing the install directory get a log file each.
+fn log_file_name() -> String {
+    match instance::user_sid() {
+        Ok(sid) => format!("{}-{}-{}.log", PACKAGE_NAME, PACKAGE_VERSION, sid),
+        Err(_) => format!("{}-{}.log", PACKAGE_NAME, PACKAGE_VERSION),
+    }
+}
+
+fn logged_main(app_path: Option<&Path>) -> Result<()> {
+    let started = Instant::now();
+    // note: before creating any window, they keep the DPI awareness they were created with.
+    dpi::set_aware().warn();
+    let args = cli::Args::parse(env::args().skip(1))?;
+    if args.cleanup {
+        let app_path = app_path.context("unknown executable path")?;
+        return cleanup::run(app_path, args.remove_config);
+    }
+    if let Some(path) = &args.backup {
+        let app_path = app_path.context("unknown executable path")?;
+        return backup::create(app_path, path);
+    }
+    if let Some(path) = &args.restore {
+        let app_path = app_path.context("unknown executable path")?;
+        return backup::restore(app_path, path);
+    }
+    if args.print_default_config {
+        return Config::print_default(args.default_config_path.as_deref());
+    }
+    #[cfg(feature = "ipc")]
+    {
+        if args.status {
+            return status::print(args.json);
+        }
+        if args.metrics {
+            return ipc::print("metrics");
+        }
+        if let Some(action) = args.trigger {
+            return ipc::send_trigger(action);
+        }
+    }
+    #[cfg(not(feature = "ipc"))]
+    if args.status || args.metrics || args.trigger.is_some() {
+        anyhow::bail!("this build has no pipe to reach the running instance");
+    }
+    if args.bench {
+        return bench::run();
+    }
+    // note: a second instance couldn't register the hotkeys anyway, so it leaves quietly.
+    let instance = instance::acquire().warn();
+    if matches!(instance, Some(None)) {
+        info!("already running for this user in this session");
+        return Ok(());
+    }
+    if args.silent_setup {
+        if let Some(app_path) = app_path {
+            if Config::create(&Config::path(app_path)).warn() == Some(true) {
+                info!("created {:?}", Config::path(app_path));
+            }
+        }
+    }
+    if let Some(app_path) = app_path {
+        migration::upgrade(&Config::path(app_path)).warn();
+    }
+    let safe_mode = app_path.is_some_and(crashloop::enter);
+    let config = app_path
+        .filter(|_| !safe_mode)
+        .and_then(|app_path| Config::load(&Config::path(app_path)).warn())
+        .unwrap_or_default();
+    info!("{config:?}");
+    let state_path = app_path.map(State::path);
+    let mut state = state_path
+        .as_deref()
+        .and_then(|state_path| State::load(state_path).warn())
+        .unwrap_or_default();
+    state.paused |= args.paused;
+    telemetry::restore(state.usage.clone());
+    telemetry::report_if_due(&config);
+    summary::restore(state.summary.clone());
+    quiet::configure(config.quiet);
+    configure_snapshots(&config, app_path);
+    keybindings::watch().warn();
+    summary::show_if_due(&config);
+    check_conflicts(&mut state);
+    hotkey::switch_layout(hotkey::foreground_layout());
+    schedule::configure(config.active_hours.clone());
+    if !state.paused && !schedule::is_off() {
+        hotkey::register(&hotkey::table(&config), config.trigger)?;
+        gesture::hook(&config).warn();
+    }
+    report_hotkeys(&config, state.paused);
+    restart::register(state.paused).warn();
+    let foreground_hook = config.tracks_foreground().then(window::track_foreground);
+    let keyboard_window = if config.keyboards.is_empty() {
+        None
+    } else {
+        keyboard::watch().warn()
+    };
+    let auto_launch = app_path
+        .and_then(|app_path| {
+            app_path
+                .to_str()
+                .with_context(|| format!("non-utf8 path: {app_path:?}"))
+                .warn()
+        })
+        .and_then(|app_path| Autostart::new(app_path).warn().flatten());
+    if args.autostart {
+        // note: package managers run us non-interactively, so a refusal is only logged.
+        if let Some(autostart) = auto_launch.as_ref() {
+            autostart.enable().warn();
+        }
+    }
+    report_autostart(auto_launch.as_ref());
+    // note: once the status is known, the first heartbeat is written right away.
+    heartbeat::configure(config.heartbeat_path.clone());
+    theme::allow_dark_menus().warn();
+    let (tx, rx) = mpsc::channel::<Event>();
+    let icon_pack = config.icon_pack.clone();
+    let mut icon_size = dpi::small_icon_size();
+    let mut icon_theme = theme::taskbar();
+    // note: explained once per run, the log has every blocked key.
+    let mut explained_blocked = false;
+    let mut explained_hidden = false;
+    let mut icons = load_icons(icon_pack.as_deref());
+    let mut focused = true;
+    let auto_launched = auto_launch.as_ref().and_then(|al| al.is_enabled().warn());
+    // note: owned by the main thread, which pumps the messages of its window.
+    let tray = Tray::new(
+        tx.clone(),
+        Event::Tray(MenuAction::ShowMenu),
+        icons.get(icon_state(lifecycle::get(), focused)),
+        &tooltip(lifecycle::get(), focused),
+        tray_menu(
+            &config,
+            app_path,
+            &MenuState::new(state.paused, auto_launched, false, Duration::ZERO),
+            Some(theme::menus()),
+        ),
+    )?;
+
+    if config.dim_when_unfocused {
+        let focused = window::is_target(&config, window::foreground());
+        tx.send(Event::System(Change::Focused(focused))).warn();
+    }
+
+    let session_window = session::watch().warn();
+    let dpi_window = dpi::watch().warn();
+
+    quirk::prepare();
+    // note: the toggle shouldn't lag behind under heavy load, the pump is idle otherwise.
+    unsafe { SetThreadPriority(GetCurrentThread(), THREAD_PRIORITY_ABOVE_NORMAL) }.warn();
+    let relaunch = thread::scope(|s| -> usize {
+        let tid: u32 = unsafe { GetCurrentThreadId() };
+        #[cfg(feature = "ipc")]
+        {
+            let control = ipc::Control {
+                tid,
+                tx: tx.clone(),
+            };
+            #[cfg(feature = "http-api")]
+            http_api::serve(&config, control.clone()).warn();
+            ipc::serve(control).warn();
+        }
+        #[cfg(not(feature = "http-api"))]
+        if config.http_api_port != 0 {
+            warn!("http_api_port is set, but this build has no HTTP API");
+        }
+        // note: kept running until exit, the thread's timers end with it.
+        schedule::watch().warn();
+        upgrade::watch().warn();
+        heartbeat::watch().warn();
+        // note: kept until exit, the changes stop with it.
+        let _colors = theme::watch_colors(tid).warn();
+        let tray = &tray;
+
+        s.spawn(move || {
+            let mut bus = Bus::new(rx);
+            bus.timers().start(Timer::Status, status::REFRESH_INTERVAL);
+            bus.with(move |event: Event, _: &mut Timers| {
+                let relaunch = match event {
+                    Event::Tray(MenuAction::Exit) => 0,
+                    Event::Tray(MenuAction::Restart) => restart::RELAUNCH,
+                    Event::Tray(MenuAction::RelaunchElevated) => restart::RELAUNCH_ELEVATED,
+                    _ => return Flow::Continue,
+                };
+                match unsafe { PostThreadMessageW(tid, WM_QUIT, WPARAM(relaunch), LPARAM(0)) }
+                    .warn()
+                {
+                    Some(_) => Flow::Stop,
+                    None => process::exit(-1),
+                }
+            })
+            .with(move |event: Event, timers: &mut Timers| {
+                match event {
+                    Event::Hotkey { .. }
+                    | Event::System(Change::Status)
+                    | Event::Timer(Timer::Status) => {
+                        // note: covers the transitions as well, they change the status.
+                        let phase = lifecycle::get();
+                        tray.set_icon(icons.get(icon_state(phase, focused))).warn();
+                        tray.set_tooltip(&tooltip(phase, focused)).warn();
+                        for line in status::LINES {
+                            let label = line.label(phase == Phase::Paused, started.elapsed());
+                            tray.set_label(Event::Tray(MenuAction::Status(line)), &label)
+                                .warn();
+                        }
+                        timers.start(Timer::Status, status::REFRESH_INTERVAL);
+                    }
+                    Event::System(Change::Focused(now_focused)) if focused != now_focused => {
+                        focused = now_focused;
+                        let phase = lifecycle::get();
+                        tray.set_icon(icons.get(icon_state(phase, focused))).warn();
+                        tray.set_tooltip(&tooltip(phase, focused)).warn();
+                    }
+                    Event::System(Change::Revalidate) => {
+                        tray.set_icon(icons.get(icon_state(lifecycle::get(), focused)))
+                            .warn();
+                    }
+                    Event::System(Change::Display) => {
+                        timers.start(Timer::Display, DISPLAY_SETTLE_DELAY);
+                    }
+                    Event::Timer(Timer::Display) => {
+                        let (size, theme) = (dpi::small_icon_size(), theme::taskbar());
+                        if (size, theme) != (icon_size, icon_theme) {
+                            info!("tray icons: {icon_size}px {icon_theme:?} -> {size}px {theme:?}");
+                            (icon_size, icon_theme) = (size, theme);
+                            icons = load_icons(icon_pack.as_deref());
+                            tray.set_icon(icons.get(icon_state(lifecycle::get(), focused)))
+                                .warn();
+                        }
+                    }
+                    _ => {}
+                }
+                Flow::Continue
+            })
+            .with(move |event: Event, _: &mut Timers| {
+                let action = match event {
+                    Event::Tray(action) => action,
+                    #[cfg(feature = "ipc")]
+                    Event::Ipc(bus::Command::SetPaused(paused))
+                        if paused != tray.is_checked(Event::Tray(MenuAction::Pause)) =>
+                    {
+                        MenuAction::Pause
+                    }
+                    Event::System(Change::Blocked) if !explained_blocked => {
+                        explained_blocked = true;
+                        tray.show_balloon(
+                            "VSCode runs as administrator",
+                            "Windows blocks the keys sent to it from a program that doesn't. \
+                             Click here or choose \"Restart as Administrator\" in the tray menu \
+                             to restart vscode-cjk-toggle-terminal-fixer as administrator too.",
+                            app_path
+                                .is_some()
+                                .then_some(Event::Tray(MenuAction::RelaunchElevated)),
+                        )
+                        .warn();
+                        return Flow::Continue;
+                    }
+                    Event::System(Change::Hidden) if !explained_hidden => {
+                        explained_hidden = true;
+                        tray.show_balloon(
+                            "VSCode is minimized",
+                            "The hotkey was skipped as nothing would visibly happen in a \
+                             minimized window or one on another desktop. Set \
+                             restore_hidden_windows = true or other_desktop = \"switch\" in the \
+                             config to show it first.",
+                            None,
+                        )
+                        .warn();
+                        return Flow::Continue;
+                    }
+                    _ => return Flow::Continue,
+                };
+                let id = Event::Tray(action);
+                match action {
+                    // note: handled by the lifecycle handler.
+                    MenuAction::Exit | MenuAction::Restart | MenuAction::RelaunchElevated => {}
+                    MenuAction::AutoLaunch => {
+                        auto_launch.as_ref().and_then(|al| {
+                            if al.is_enabled().warn()? {
+                                al.disable()
+                                    .warn()
+                                    .and_then(|_| tray.set_checked(id, false).warn())
+                            } else {
+                                al.enable()
+                                    .warn()
+                                    .and_then(|_| tray.set_checked(id, true).warn())
+                            }
+                        });
+                        report_autostart(auto_launch.as_ref());
+                    }
+                    MenuAction::Pause => {
+                        let paused = !tray.is_checked(id);
+                        if tray.set_checked(id, paused).warn().is_some() {
+                            unsafe {
+                                PostThreadMessageW(
+                                    tid,
+                                    hotkey::WM_PAUSE,
+                                    WPARAM(paused as usize),
+                                    LPARAM(0),
+                                )
+                            }
+                            .warn();
+                        }
+                    }
+                    MenuAction::Status(_) => {}
+                    MenuAction::ShowMenu => {
+                        dpi::track_tray_click();
+                        tray.show_menu().warn();
+                    }
+                    MenuAction::Telemetry => {
+                        let Some(app_path) = app_path else {
+                            return Flow::Continue;
+                        };
+                        let values = format!("telemetry = {}", telemetry::ask_consent());
+                        if Config::update(&Config::path(app_path), &values)
+                            .warn()
+                            .is_some()
+                        {
+                            unsafe {
+                                PostThreadMessageW(tid, config::WM_RELOAD, WPARAM(0), LPARAM(0))
+                            }
+                            .warn();
+                        }
+                    }
+                    MenuAction::Troubleshoot => troubleshoot::show(),
+                    #[cfg(feature = "settings-ui")]
+                    MenuAction::RecentEvents => recent::show(),
+                    #[cfg(feature = "settings-ui")]
+                    MenuAction::TriggerHistory => history::show(),
+                    MenuAction::ReportProblem => {
+                        let Some(app_path) = app_path else {
+                            return Flow::Continue;
+                        };
+                        let log_path = app_path.with_file_name(log_file_name());
+                        diagnostics::report_problem(&Config::path(app_path), &log_path).warn();
+                    }
+                    MenuAction::ImportPowerToys => {
+                        let Some(app_path) = app_path else {
+                            return Flow::Continue;
+                        };
+                        let text = match powertoys::import(&Config::path(app_path)) {
+                            Ok(0) => "Found no new mappings for VSCode.".to_owned(),
+                            Ok(imported) => {
+                                unsafe {
+                                    PostThreadMessageW(tid, config::WM_RELOAD, WPARAM(0), LPARAM(0))
+                                }
+                                .warn();
+                                // note: both tools would handle the keys otherwise.
+                                format!("Imported {imported} mappings, remove them from PowerToys.")
+                            }
+                            Err(err) => errors::describe(&err),
+                        };
+                        notify(&text);
+                    }
+                    MenuAction::ExportAhk => {
+                        let Some(app_path) = app_path else {
+                            return Flow::Continue;
+                        };
+                        let text = match ahk::export(app_path) {
+                            Ok(path) => format!("Exported the hotkeys to {}.", path.display()),
+                            Err(err) => errors::describe(&err),
+                        };
+                        notify(&text);
+                    }
+                    MenuAction::AddTarget => {
+                        let Some(app_path) = app_path else {
+                            return Flow::Continue;
+                        };
+                        // note: the tray has the focus now, so it's the window focused before.
+                        let Some(hwnd) = window::last_foreground_window() else {
+                            notify("Found no window, focus the app first.");
+                            return Flow::Continue;
+                        };
+                        let text = match window::add_target(&Config::path(app_path), hwnd) {
+                            Ok(name) => {
+                                unsafe {
+                                    PostThreadMessageW(tid, config::WM_RELOAD, WPARAM(0), LPARAM(0))
+                                }
+                                .warn();
+                                format!("Added {name}, its windows receive the hotkeys from now on.")
+                            }
+                            Err(err) => errors::describe(&err),
+                        };
+                        notify(&text);
+                    }
+                    MenuAction::Learning => {
+                        let Some(app_path) = app_path else {
+                            return Flow::Continue;
+                        };
+                        let enabled = !tray.is_checked(id);
+                        learn::set(Config::path(app_path), tid, enabled);
+                        tray.set_checked(id, enabled).warn();
+                    }
+                    MenuAction::DisableWorkspace => {
+                        let Some(app_path) = app_path else {
+                            return Flow::Continue;
+                        };
+                        // note: the tray has the focus now, so it's the VSCode window focused
+                        // before.
+                        let Some(name) = workspace::last_active() else {
+                            notify("Found no VSCode workspace, focus its window first.");
+                            return Flow::Continue;
+                        };
+                        let text = match workspace::disable(&Config::path(app_path), &name) {
+                            Ok(()) => {
+                                unsafe {
+                                    PostThreadMessageW(tid, config::WM_RELOAD, WPARAM(0), LPARAM(0))
+                                }
+                                .warn();
+                                format!(
+                                    "Disabled for 「{name}」, remove it from disabled_workspaces in the config file to enable it again."
+                                )
+                            }
+                            Err(err) => errors::describe(&err),
+                        };
+                        notify(&text);
+                    }
+                    MenuAction::Alternate(alternate) => {
+                        let Some(app_path) = app_path else {
+                            return Flow::Continue;
+                        };
+                        let enabled = !tray.is_checked(id);
+                        if alternate::set(&Config::path(app_path), alternate, enabled)
+                            .warn()
+                            .is_some()
+                        {
+                            tray.set_checked(id, enabled).warn();
+                            unsafe {
+                                PostThreadMessageW(tid, config::WM_RELOAD, WPARAM(0), LPARAM(0))
+                            }
+                            .warn();
+                        }
+                    }
+                    MenuAction::Rule(rule) => {
+                        let Some(app_path) = app_path else {
+                            return Flow::Continue;
+                        };
+                        let enabled = !tray.is_checked(id);
+                        if rules::set(&Config::path(app_path), rule, enabled)
+                            .warn()
+                            .is_some()
+                        {
+                            tray.set_checked(id, enabled).warn();
+                            unsafe {
+                                PostThreadMessageW(tid, config::WM_RELOAD, WPARAM(0), LPARAM(0))
+                            }
+                            .warn();
+                        }
+                    }
+                    MenuAction::Preset(preset) => {
+                        // note: the menu only offers presets when the config path is known.
+                        let Some(app_path) = app_path else {
+                            return Flow::Continue;
+                        };
+                        if preset::apply(&Config::path(app_path), preset)
+                            .warn()
+                            .is_some()
+                        {
+                            unsafe {
+                                PostThreadMessageW(tid, config::WM_RELOAD, WPARAM(0), LPARAM(0))
+                            }
+                            .warn();
+                        }
+                    }
+                }
+                Flow::Continue
+            })
+            .run()
+        });
+
+        let mut pump = pump::Pump::new(config, app_path, tx);
+        let mut platform = pump::Win32::new(
+            tray,
+            app_path,
+            state_path.as_deref(),
+            started,
+            auto_launched.is_some(),
+            foreground_hook,
+            keyboard_window,
+        );
+        let mut msg: MSG = unsafe { mem::zeroed() };
+        loop {
+            let hr = unsafe { GetMessageW(&mut msg, HWND(0), 0, 0) };
+            if matches!(hr, BOOL(0 | -1)) {
+                // note: -1 is an error state but is unreachable here so we don't handle it.
+                break;
+            }
+
+            // note: a timer stands in for the message it was started for.
+            if msg.message == WM_TIMER && msg.hwnd == HWND(0) {
+                let Some((message, wparam)) = timer::elapsed(msg.wParam.0) else {
+                    continue;
+                };
+                (msg.message, msg.wParam, msg.lParam) = (message, wparam, LPARAM(0));
+            }
+            pump.handle(&mut state, &msg, &mut platform);
+        }
+        match msg.message {
+            WM_QUIT => msg.wParam.0,
+            _ => 0,
+        }
+    });
+
+    lifecycle::shut_down();
+    // note: removed before relaunching, the new instance adds its own.
+    drop(tray);
+    save(&mut state, state_path.as_deref());
+    if let Some(hwnd) = session_window {
+        session::unwatch(hwnd);
+    }
+    if let Some(hwnd) = dpi_window {
+        dpi::unwatch(hwnd);
+    }
+    heartbeat::stop();
+    if let Some(app_path) = app_path {
+        crashloop::leave(app_path).warn();
+    }
+    if relaunch != 0 {
+        hotkey::unregister();
+        drop(instance);
+        if let Some(app_path) = app_path {
+            restart::relaunch(app_path, relaunch == restart::RELAUNCH_ELEVATED)?;
+        }
+    }
+
+    Ok(())
+}
+
+/// replaces the registered hotkeys with the configured ones, or none while paused.
+fn register_hotkeys(config: &Config, paused: bool) {
+    hotkey::unregister();
+    if !paused && !schedule::is_off() {
+        hotkey::register(&hotkey::table(config), config.trigger).warn();
+        gesture::hook(config).warn();
+    }
+    report_hotkeys(config, paused);
+}
+
+/// updates the status and the lifecycle after the hotkeys were registered.
+fn report_hotkeys(config: &Config, paused: bool) {
+    status::update(|status| {
+        status.hotkeys = hotkey::count();
+        status.paused = paused;
+        status.targets = config.target_processes.clone();
+    });
+    lifecycle::settle(paused);
+}
+
+/// tells `--status` whether we're launched on logon.
+fn report_autostart(autostart: Option<&Autostart>) {
+    let autostart = autostart.and_then(|autostart| {
+        Some(status::Autostart {
+            backend: autostart.backend().to_owned(),
+            enabled: autostart.is_enabled().warn()?,
+        })
+    });
+    status::update(|status| status.autostart = autostart);
+}
+
+/// the icons of the pack if configured, for the current scaling and theme of the taskbar.
+fn load_icons(icon_pack: Option<&Path>) -> Icons {
+    match icon_pack {
+        Some(dir) => Icons::load(dir, theme::taskbar()),
+        None => Icons::builtin(),
+    }
+}
+
+/// what the tray menu shows besides the config.
+struct MenuState {
+    paused: bool,
+    /// `None` without the "Auto Launch" item.
+    auto_launched: Option<bool>,
+    learning: bool,
+    /// whether we run as administrator, `None` if unknown.
+    elevated: Option<bool>,
+    /// the labels of `status::LINES`.
+    status: [String; 6],
+}
+
+impl MenuState {
+    /// looks up the rest, e.g. the status lines.
+    fn new(paused: bool, auto_launched: Option<bool>, learning: bool, uptime: Duration) -> Self {
+        Self {
+            paused,
+            auto_launched,
+            learning,
+            elevated: procs::is_elevated(process::id()).warn(),
+            status: status::LINES.map(|line| line.label(paused, uptime)),
+        }
+    }
+}
+
+/// the tray menu reflecting the config, with its icons in `icons` unless `None`.
+///
+/// note: everything looked up from the system is in `state`, so it's built the same in tests.
+/// the items opening the windows of `viewer`, none in builds without them.
+#[cfg(feature = "settings-ui")]
+fn viewer_items(menu: Menu<Event>) -> Menu<Event> {
+    menu.item("Show Recent Events", Event::Tray(MenuAction::RecentEvents))
+        .item(
+            "Show Trigger History",
+            Event::Tray(MenuAction::TriggerHistory),
+        )
+}
+
+#[cfg(not(feature = "settings-ui"))]
+fn viewer_items(menu: Menu<Event>) -> Menu<Event> {
+    menu
+}
+
+fn tray_menu(
+    config: &Config,
+    app_path: Option<&Path>,
+    state: &MenuState,
+    icons: Option<theme::Theme>,
+) -> Menu<Event> {
+    Menu::default()
+        .submenu(
+            "Status",
+            status::LINES.into_iter().zip(&state.status).fold(
+                Menu::default(),
+                |menu, (line, label)| {
+                    menu.with(Item::Command {
+                        id: Event::Tray(MenuAction::Status(line)),
+                        label: label.clone(),
+                        checked: None,
+                        disabled: true,
+                        icon: None,
+                    })
+                },
+            ),
+        )
+        .separator()
+        .with(Item::Command {
+            id: Event::Tray(MenuAction::Pause),
+            label: "Pause".into(),
+            checked: Some(state.paused),
+            disabled: false,
+            icon: icons.map(|theme| MenuIcon::Pause.icon(theme)),
+        })
+        .when(|menu| match state.auto_launched {
+            Some(enabled) => menu.with(Item::Command {
+                id: Event::Tray(MenuAction::AutoLaunch),
+                label: "Auto Launch".into(),
+                checked: Some(enabled),
+                disabled: false,
+                icon: icons.map(|theme| MenuIcon::Autostart.icon(theme)),
+            }),
+            None => menu,
+        })
+        .when(|menu| match app_path {
+            Some(_) => alternate::ALTERNATES
+                .into_iter()
+                .fold(menu, |menu, alternate| {
+                    let enabled = alternate.is_enabled(config);
+                    menu.checkable(
+                        alternate.name(),
+                        enabled,
+                        Event::Tray(MenuAction::Alternate(alternate)),
+                    )
+                }),
+            None => menu,
+        })
+        .when(|menu| match app_path {
+            Some(_) => menu
+                .with(Item::Submenu {
+                    label: "Presets".into(),
+                    icon: icons.map(|theme| MenuIcon::Presets.icon(theme)),
+                    menu: preset::PRESETS
+                        .into_iter()
+                        .fold(Menu::default(), |menu, preset| {
+                            menu.item(preset.name(), Event::Tray(MenuAction::Preset(preset)))
+                        }),
+                })
+                .when(|menu| {
+                    if rules::any(config) {
+                        menu.submenu(
+                            "Rules",
+                            rules::menu(config, |rule| Event::Tray(MenuAction::Rule(rule))),
+                        )
+                    } else {
+                        menu
+                    }
+                })
+                .item(
+                    "Add Current Window's App as Target",
+                    Event::Tray(MenuAction::AddTarget),
+                )
+                .checkable(
+                    "Learning Mode",
+                    state.learning,
+                    Event::Tray(MenuAction::Learning),
+                )
+                .item(
+                    "Disable for This Workspace",
+                    Event::Tray(MenuAction::DisableWorkspace),
+                )
+                .item(
+                    "Import from PowerToys",
+                    Event::Tray(MenuAction::ImportPowerToys),
+                )
+                .item(
+                    "Export as AutoHotkey Script",
+                    Event::Tray(MenuAction::ExportAhk),
+                )
+                .item("Usage Statistics…", Event::Tray(MenuAction::Telemetry))
+                .item("Report a Problem…", Event::Tray(MenuAction::ReportProblem)),
+            None => menu,
+        })
+        .item(
+            "Why Isn't It Working?",
+            Event::Tray(MenuAction::Troubleshoot),
+        )
+        .when(viewer_items)
+        .separator()
+        .item("Restart", Event::Tray(MenuAction::Restart))
+        .when(|menu| {
+            // note: what the balloon explaining `Change::Blocked` offers, reachable by keyboard.
+            if app_path.is_some() && state.elevated == Some(false) {
+                menu.item(
+                    "Restart as Administrator",
+                    Event::Tray(MenuAction::RelaunchElevated),
+                )
+            } else {
+                menu
+            }
+        })
+        .item("Exit", Event::Tray(MenuAction::Exit))
+}
+
+/// builds the tray menu again, e.g. once the config changed, keeping what's checked.
+fn rebuild_menu(
+    tray: &Tray<Event>,
+    config: &Config,
+    app_path: Option<&Path>,
+    offers_auto_launch: bool,
+    uptime: Duration,
+) {
+    let state = MenuState::new(
+        tray.is_checked(Event::Tray(MenuAction::Pause)),
+        offers_auto_launch.then(|| tray.is_checked(Event::Tray(MenuAction::AutoLaunch))),
+        tray.is_checked(Event::Tray(MenuAction::Learning)),
+        uptime,
+    );
+    let menu = tray_menu(config, app_path, &state, Some(theme::menus()));
+    tray.set_menu(menu).warn();
+}
+
+fn icon_state(phase: Phase, focused: bool) -> icons::State {
+    match phase {
+        Phase::Paused => icons::State::Paused,
+        Phase::Dormant => icons::State::Scheduled,
+        Phase::Degraded => icons::State::Error,
+        Phase::Active if !focused => icons::State::Dimmed,
+        Phase::Initializing | Phase::Active | Phase::ShuttingDown => icons::State::Normal,
+    }
+}
+
+/// the tray tooltip, led by what's different from usual.
+///
+/// note: tells whatever the icon shows in words as well, e.g. for Narrator or whoever can't tell
+/// the dimmed icon apart.
+fn tooltip(phase: Phase, focused: bool) -> String {
+    let tooltip = Tooltip::new(
+        "Fixing the issue where 「Ctrl+`」 doesn't work with some CJK keyboards/IMEs in VSCode.",
+    );
+    match phase {
+        Phase::Paused => tooltip.status("Paused").build(),
+        Phase::Dormant => tooltip.status("Off Outside Active Hours").build(),
+        Phase::Degraded => tooltip.status("No Hotkey Registered").build(),
+        Phase::Active if !focused => tooltip.status("Idle, No VSCode Window Focused").build(),
+        Phase::Initializing | Phase::Active | Phase::ShuttingDown => tooltip.build(),
+    }
+}
+
+/// tells the user about the outcome of something they did from the tray.
+fn notify(text: &str) {
+    unsafe {
+        MessageBoxW(
+            HWND(0),
+            &HSTRING::from(text),
+            &HSTRING::from(PACKAGE_NAME),
+            MB_OK | MB_ICONINFORMATION,
+        )
+    };
+}
+
+/// logs conflicting tools and tells the user about new ones.
+fn check_conflicts(state: &mut State) {
+    let conflicts = conflict::detect();
+    for conflict in &conflicts {
+        warn!("conflict: {conflict}");
+    }
+    conflict::show(
+        conflicts
+            .into_iter()
+            .filter(|conflict| state.warned_conflicts.insert(conflict.to_string()))
+            .collect(),
+    );
+}
+
+fn configure_snapshots(config: &Config, app_path: Option<&Path>) {
+    if let Some(app_path) = app_path {
+        snapshot::configure(
+            config.snapshot_failures,
+            Config::path(app_path),
+            app_path.with_file_name(log_file_name()),
+        );
+    }
+}
+
+/// writes the state file, including the usage counted so far.
+fn save(state: &mut State, state_path: Option<&Path>) {
+    state.usage = telemetry::usage();
+    state.summary = summary::week();
+    if let Some(state_path) = state_path {
+        state.save(state_path).warn();
+    }
+}
+
+/// follows up on a press outside of `on_hotkey`, with the same trigger id.
+fn trigger_span(hotkey: &Hotkey) -> Span {
+    info_span!("hotkey", trigger = hotkey.trigger, chord = %hotkey.chord)
+}
+
+/// performs the hotkey's action on the target window, returns the window unless there's none.
+fn dispatch(config: &Config, hotkey: &Hotkey) -> Option<HWND> {
+    let h_active_wnd = window::foreground();
+    if config.pause_while_presenting && presentation::is_presenting(config, h_active_wnd) {
+        info!("ignored {hotkey:?} while presenting");
+        history::note(Outcome::Skipped("presenting"));
+        return None;
+    }
+    learn::observe(config, h_active_wnd);
+    if workspace::is_disabled(config, h_active_wnd) {
+        // note: the window gets the hotkey as if it wasn't registered, e.g. for its own binding.
+        info!("passed {hotkey:?} through, its workspace is disabled");
+        history::note(Outcome::Skipped("the workspace is disabled"));
+        inject::post_keys(h_active_wnd, hotkey.keys()).warn();
+        return None;
+    }
+    if config.target_windows == window::Policy::AllVisible {
+        return dispatch_all(config, hotkey, h_active_wnd);
+    }
+    match window::target(config) {
+        Some(h_target_wnd) if workspace::is_disabled(config, h_target_wnd) => {
+            info!("ignored {hotkey:?}, the workspace of {h_target_wnd:?} is disabled");
+            history::note(Outcome::Skipped("the workspace is disabled"));
+            None
+        }
+        Some(h_target_wnd)
+            if h_target_wnd == h_active_wnd && probe::is_due(config, h_target_wnd, hotkey) =>
+        {
+            if probe::start(h_target_wnd, *hotkey).warn().is_some() {
+                history::note(Outcome::Probing);
+            } else {
+                perform(config, h_target_wnd, hotkey);
+            }
+            Some(h_target_wnd)
+        }
+        Some(h_target_wnd) => {
+            perform(config, h_target_wnd, hotkey);
+            // note: pressing the hotkey on the summoned window dismisses it.
+            if config.quake_mode && h_target_wnd == h_active_wnd {
+                quake::restore(h_target_wnd).warn();
+            }
+            Some(h_target_wnd)
+        }
+        None if config.launch_if_missing && window::find_vscode_window().is_none() => {
+            if launch::vscode(config, *hotkey).warn().is_some() {
+                history::note(Outcome::Launched);
+            }
+            None
+        }
+        None => None,
+    }
+}
+
+/// performs the hotkey's action on every visible target window, returns the foreground window if it's
+/// one of them, or else the topmost.
+///
+/// note: neither probing nor the quake mode apply, they're about a single window.
+fn dispatch_all(config: &Config, hotkey: &Hotkey, h_active_wnd: HWND) -> Option<HWND> {
+    let windows: Vec<_> = window::all_visible(config)
+        .into_iter()
+        .filter(|&hwnd| !workspace::is_disabled(config, hwnd))
+        .collect();
+    if windows.is_empty() {
+        if config.launch_if_missing
+            && window::find_vscode_window().is_none()
+            && launch::vscode(config, *hotkey).warn().is_some()
+        {
+            history::note(Outcome::Launched);
+        }
+        return None;
+    }
+    info!("sending {hotkey:?} to {} windows", windows.len());
+    for &hwnd in &windows {
+        perform(config, hwnd, hotkey);
+    }
+    windows
+        .iter()
+        .copied()
+        .find(|&hwnd| hwnd == h_active_wnd)
+        .or(windows.first().copied())
+}
+
+/// injects the hotkey's action into the window, or once it responds again if it's busy.
+fn perform(config: &Config, hwnd: HWND, hotkey: &Hotkey) {
+    match window::hidden(hwnd) {
+        // note: Windows switches to the desktop of the window it activates.
+        Some(Hidden::OtherDesktop) if config.other_desktop == desktop::Policy::Switch => {
+            info!("switching to the desktop of {hwnd:?}");
+            window::activate(hwnd).warn();
+        }
+        Some(hidden @ (Hidden::Minimized | Hidden::Cloaked)) if config.restore_hidden_windows => {
+            info!("restoring {hwnd:?}, it's {hidden}");
+            window::activate(hwnd).warn();
+        }
+        Some(hidden) => {
+            info!("skipped {hotkey:?}, {hwnd:?} is {hidden}");
+            history::note(Outcome::Skipped(match hidden {
+                Hidden::Minimized => "the window is minimized",
+                Hidden::OtherDesktop => "the window is on another desktop",
+                Hidden::Cloaked => "the window is cloaked",
+            }));
+            window::report_hidden();
+            return;
+        }
+        None => {}
+    }
+    let hotkey = &direct(config, hwnd, hotkey);
+    // note: only the keys pressed into the focused window are typed as text, the command palette
+    // and UI Automation close the input first.
+    if config.skip_in_text_inputs
+        && !hotkey.without_modifiers()
+        && hwnd == unsafe { GetForegroundWindow() }
+    {
+        if let Some(input) = uia::focused_input().warn().flatten() {
+            info!("skipped {hotkey:?}, a text input is focused: {input}");
+            history::note(Outcome::Skipped("a text input is focused"));
+            return;
+        }
+    }
+    if window::is_responsive(hwnd) {
+        inject_into(config, hwnd, hotkey);
+    } else if config.busy_retry_ms == 0 {
+        warn!("skipped {hotkey:?}, {hwnd:?} isn't responding");
+        history::note(Outcome::Skipped("the window isn't responding"));
+        summary::record_failure(summary::NOT_RESPONDING);
+    } else {
+        info!("deferred {hotkey:?}, {hwnd:?} isn't responding");
+        history::note(Outcome::Deferred);
+        retry::schedule(hwnd, *hotkey, Duration::from_millis(config.busy_retry_ms));
+    }
+}
+
+/// the hotkey as performed with `return_to_editor_hotkey`, which moves the focus between the editor
+/// and the terminal, or with `full_screen_actions`, by running VSCode's commands, so the pressed
+/// keys are never injected.
+fn direct(config: &Config, hwnd: HWND, hotkey: &Hotkey) -> Hotkey {
+    let action = if hotkey.id == hotkey::RETURN_TO_EDITOR_ID {
+        Action::FocusEditor
+    } else if let Some(&action) = config
+        .full_screen_actions
+        .get(&hotkey.action)
+        .filter(|_| window::is_full_screen(hwnd))
+    {
+        debug!("{hwnd:?} is full screen");
+        action
+    } else if config.return_to_editor_hotkey.is_some()
+        && hotkey.action == Action::ToggleTerminal
+        && !hotkey.without_modifiers()
+        && hwnd == unsafe { GetForegroundWindow() }
+        && uia::is_editor_focused().warn() == Some(true)
+    {
+        Action::FocusTerminal
+    } else {
+        return *hotkey;
+    };
+    debug!("performing {action:?} for {hotkey:?}");
+    Hotkey {
+        trigger: hotkey.trigger,
+        ..hotkey::unpressed(action)
+    }
+}
+
+fn inject_into(config: &Config, hwnd: HWND, hotkey: &Hotkey) {
+    let injection = quirk::resolve(config, hwnd);
+    if !injection.delay.is_zero() {
+        thread::sleep(injection.delay);
+    }
+    let focus = if config.verify_injection {
+        uia::focus().warn()
+    } else {
+        None
+    };
+    let Some(backend) = inject::mock_key_press(config, hwnd, hotkey, &injection.backends).warn()
+    else {
+        history::note(Outcome::Failed);
+        summary::record_failure("no injection backend succeeded");
+        return;
+    };
+    history::note(Outcome::Injected(backend));
+    status::update(|status| status.backend = Some(backend));
+    if let Some(focus) = focus {
+        verify::schedule(hwnd, *hotkey, backend, injection.backends, focus);
+    }
+}
+
+trait LogExt<T> {
+    fn warn(self) -> Option<T>;
+}
+
+impl<T, E: std::fmt::Debug + 'static> LogExt<T> for std::result::Result<T, E> {
+    fn warn(self) -> Option<T> {
+        if let Err(ref err) = self {
+            match errors::hint(err) {
+                Some(hint) => warn!("{err:?}\n({hint})"),
+                None => warn!("{err:?}"),
+            }
+        }
+        self.ok()
+    }
+}
+
+trait LockExt<T> {
+    fn locked(&self) -> MutexGuard<'_, T>;
+}
+
+impl<T> LockExt<T> for Mutex<T> {
+    /// locks the mutex, taking the data over from a thread that panicked while holding it.
+    ///
+    /// note: every mutex here guards data that is whole again after each statement, so a panic
+    /// while holding one never leaves it half written.
+    fn locked(&self) -> MutexGuard<'_, T> {
+        self.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
@@ -0,0 +1,71 @@
+use std::sync::Mutex;
+
+#[allow(unused_imports)]
+use tracing::{debug, error, info, trace, warn};
+
+use crate::{hotkey, schedule, status, LockExt};
+
+/// where the app is in its life, each transition is logged.
+///
+/// note: shared as the tray and the status are refreshed on other threads, only the message pump
+/// changes it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Phase {
+    /// until the hotkeys were registered the first time.
+    Initializing,
+    /// the hotkeys are registered.
+    Active,
+    /// paused by the user, the hotkeys are unregistered.
+    Paused,
+    /// outside the active hours, the hotkeys are unregistered.
+    Dormant,
+    /// no hotkey could be registered, e.g. they're all taken by other applications.
+    Degraded,
+    /// the message pump quit, everything is being cleaned up.
+    ShuttingDown,
+}
+
+static PHASE: Mutex<Phase> = Mutex::new(Phase::Initializing);
+
+pub fn get() -> Phase {
+    *PHASE.locked()
+}
+
+/// moves to the phase the hotkeys were just registered for, or not.
+pub fn settle(paused: bool) {
+    let phase = if paused {
+        Phase::Paused
+    } else if schedule::is_off() {
+        Phase::Dormant
+    } else if hotkey::count() == 0 {
+        Phase::Degraded
+    } else {
+        Phase::Active
+    };
+    transition(phase);
+}
+
+pub fn shut_down() {
+    transition(Phase::ShuttingDown);
+}
+
+/// note: there's no way back from shutting down, e.g. a late `WM_PAUSE` is ignored.
+fn transition(to: Phase) {
+    let mut phase = PHASE.locked();
+    let from = *phase;
+    if from == to {
+        return;
+    }
+    if from == Phase::ShuttingDown {
+        debug!("ignored the transition to {to:?} while shutting down");
+        return;
+    }
+    match to {
+        Phase::Degraded => warn!("lifecycle: {from:?} -> {to:?}"),
+        _ => info!("lifecycle: {from:?} -> {to:?}"),
+    }
+    *phase = to;
+    drop(phase);
+    // note: refreshes the tray, which shows the phase.
+    status::update(|_| {});
+}
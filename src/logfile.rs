@@ -0,0 +1,79 @@
+use std::{
+    fs::{File, OpenOptions},
+    io::{self, Write},
+    path::PathBuf,
+    sync::{Mutex, OnceLock},
+};
+
+use serde::{Deserialize, Serialize};
+use tracing_subscriber::fmt::MakeWriter;
+
+use crate::LockExt;
+
+/// where the log goes besides the recent events in memory.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Logging {
+    /// the log file next to the executable.
+    #[default]
+    File,
+    /// nowhere, nothing is written to disk.
+    Off,
+}
+
+/// appends to the log file, which is only created once the first event is logged.
+pub struct LogFile {
+    path: Option<PathBuf>,
+    file: OnceLock<Option<Mutex<File>>>,
+}
+
+impl LogFile {
+    /// `None` discards the events.
+    pub fn new(path: Option<PathBuf>) -> Self {
+        Self {
+            path,
+            file: OnceLock::new(),
+        }
+    }
+
+    /// note: a file that can't be opened isn't tried again, the events are still kept in memory.
+    fn file(&self) -> Option<&Mutex<File>> {
+        self.file
+            .get_or_init(|| {
+                let path = self.path.as_ref()?;
+                OpenOptions::new()
+                    .create(true)
+                    .append(true)
+                    .open(path)
+                    .ok()
+                    .map(Mutex::new)
+            })
+            .as_ref()
+    }
+}
+
+impl<'a> MakeWriter<'a> for LogFile {
+    type Writer = Event<'a>;
+
+    fn make_writer(&'a self) -> Self::Writer {
+        Event(self)
+    }
+}
+
+pub struct Event<'a>(&'a LogFile);
+
+impl Write for Event<'_> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        match self.0.file() {
+            Some(file) => file.locked().write(buf),
+            None => Ok(buf.len()),
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        match self.0.file() {
+            Some(file) => file.locked().flush(),
+            None => Ok(()),
+        }
+    }
+}
@@ -0,0 +1,51 @@
+use std::sync::atomic::{AtomicU64, Ordering};
+
+/// the hotkeys pressed since we started.
+static TRIGGERS: AtomicU64 = AtomicU64::new(0);
+/// the injections that failed since we started, one per backend tried.
+static INJECTIONS_FAILED: AtomicU64 = AtomicU64::new(0);
+/// how often the hotkeys were registered since we started, the first time included.
+static REGISTRATIONS: AtomicU64 = AtomicU64::new(0);
+
+pub fn record_trigger() {
+    TRIGGERS.fetch_add(1, Ordering::Relaxed);
+}
+
+pub fn record_injection_failure() {
+    INJECTIONS_FAILED.fetch_add(1, Ordering::Relaxed);
+}
+
+pub fn record_registration() {
+    REGISTRATIONS.fetch_add(1, Ordering::Relaxed);
+}
+
+/// the counters in the Prometheus text format, the running instance's answer to the "metrics"
+/// command.
+///
+/// note: the counters start from 0 on each start, which Prometheus handles as a counter reset.
+#[cfg(feature = "ipc")]
+pub fn text() -> String {
+    let counters = [
+        (
+            "triggers_total",
+            "Hotkeys pressed.",
+            TRIGGERS.load(Ordering::Relaxed),
+        ),
+        (
+            "injections_failed_total",
+            "Injections that failed, one per backend tried.",
+            INJECTIONS_FAILED.load(Ordering::Relaxed),
+        ),
+        (
+            "hotkey_reregistrations_total",
+            "Hotkeys registered again, e.g. after a keyboard layout switch or resuming.",
+            REGISTRATIONS.load(Ordering::Relaxed).saturating_sub(1),
+        ),
+    ];
+    counters
+        .into_iter()
+        .map(|(name, help, value)| {
+            format!("# HELP {name} {help}\n# TYPE {name} counter\n{name} {value}\n")
+        })
+        .collect()
+}
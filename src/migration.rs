@@ -0,0 +1,134 @@
+use std::{fs, io, path::Path};
+
+use anyhow::{Context, Result};
+use toml_edit::{value, DocumentMut, Item};
+#[allow(unused_imports)]
+use tracing::{debug, error, info, trace, warn};
+
+/// the version of the config format, written as `config_version` once the file was migrated.
+pub const CONFIG_VERSION: u32 = 1;
+
+/// the key holding the version, a file without it is of version 0.
+const VERSION_KEY: &str = "config_version";
+
+/// upgrades a document of the version at its index to the next one.
+///
+/// note: never reorder or remove one, a file of any past version must still be upgraded.
+const MIGRATIONS: [fn(&mut DocumentMut); CONFIG_VERSION as usize] = [target_windows];
+
+/// the version of the document's format.
+fn version(document: &DocumentMut) -> u32 {
+    document
+        .get(VERSION_KEY)
+        .and_then(Item::as_integer)
+        .and_then(|version| u32::try_from(version).ok())
+        .unwrap_or(0)
+}
+
+/// upgrades the document to the current version, returns the version it had.
+///
+/// note: a document of a newer version is left alone, its unknown keys are ignored.
+pub fn apply(document: &mut DocumentMut) -> u32 {
+    let from = version(document);
+    if from > CONFIG_VERSION {
+        warn!("the config is of version {from}, newer than ours, {CONFIG_VERSION}");
+        return from;
+    }
+    for migration in &MIGRATIONS[from as usize..] {
+        migration(document);
+    }
+    document.insert(VERSION_KEY, value(i64::from(CONFIG_VERSION)));
+    from
+}
+
+/// upgrades the config file to the current version, keeping the previous file next to it, e.g.
+/// `vscode-cjk-toggle-terminal-fixer.v0.toml.bak`, returns whether it was upgraded.
+///
+/// note: a backup is never overwritten, it's the user's file as last written by themselves.
+pub fn upgrade(path: &Path) -> Result<bool> {
+    let text = match fs::read_to_string(path) {
+        Ok(text) => text,
+        Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(false),
+        Err(err) => return Err(err).with_context(|| format!("failed to read config: {path:?}")),
+    };
+    let mut document: DocumentMut = text
+        .parse()
+        .with_context(|| format!("invalid config: {path:?}"))?;
+    let from = version(&document);
+    if from >= CONFIG_VERSION {
+        return Ok(false);
+    }
+    let backup = path.with_extension(format!("v{from}.toml.bak"));
+    if !backup.exists() {
+        fs::write(&backup, &text)
+            .with_context(|| format!("failed to back up config: {backup:?}"))?;
+    }
+    apply(&mut document);
+    fs::write(path, document.to_string())
+        .with_context(|| format!("failed to write config: {path:?}"))?;
+    info!("upgraded the config from version {from} to {CONFIG_VERSION}, the previous one is {backup:?}");
+    Ok(true)
+}
+
+/// version 1: `target_last_active = true` became `target_windows = "last_active"`.
+fn target_windows(document: &mut DocumentMut) {
+    let Some(item) = document.remove("target_last_active") else {
+        return;
+    };
+    if item.as_bool() == Some(true) && !document.contains_key("target_windows") {
+        document.insert("target_windows", value("last_active"));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fuzz;
+
+    fn migrated(text: &str) -> (u32, String) {
+        let mut document: DocumentMut = text.parse().unwrap();
+        let from = apply(&mut document);
+        (from, document.to_string())
+    }
+
+    #[test]
+    fn target_last_active_becomes_target_windows() {
+        let (from, text) = migrated("target_last_active = true\n");
+        assert_eq!(from, 0);
+        assert_eq!(
+            text,
+            "target_windows = \"last_active\"\nconfig_version = 1\n"
+        );
+        let (_, text) = migrated("target_last_active = false\n");
+        assert_eq!(text, "config_version = 1\n");
+    }
+
+    #[test]
+    fn newer_versions_are_left_alone() {
+        let text = "config_version = 2\ntarget_last_active = true\n";
+        assert_eq!(migrated(text), (2, text.to_owned()));
+    }
+
+    #[test]
+    fn migrating_twice_changes_nothing() {
+        let pieces = [
+            "target_last_active = true\n",
+            "target_last_active = false\n",
+            "target_windows = \"all\"\n",
+            "config_version = 0\n",
+            "config_version = 1\n",
+            "busy_retry_ms = 0\n",
+            "[quirks]\n",
+            "# note\n",
+        ];
+        for text in fuzz::strings(&pieces) {
+            let Ok(mut document) = text.parse::<DocumentMut>() else {
+                continue;
+            };
+            apply(&mut document);
+            let once = document.to_string();
+            assert_eq!(apply(&mut document), CONFIG_VERSION, "{text:?}");
+            assert_eq!(document.to_string(), once, "{text:?}");
+        }
+    }
+}
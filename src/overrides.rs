@@ -0,0 +1,43 @@
+use std::{env, path::PathBuf};
+
+use anyhow::{Context, Result};
+#[allow(unused_imports)]
+use tracing::{debug, error, info, trace, warn};
+
+use crate::{hotkey::Chord, logfile::Logging, LogExt};
+
+/// the config file to use instead of the one next to the executable, e.g. on a share.
+const CONFIG: &str = "CJK_FIXER_CONFIG";
+/// overrides `logging`, "file" or "off".
+const LOG: &str = "CJK_FIXER_LOG";
+/// the chord toggling the terminal instead of 「Ctrl+`」, e.g. "Ctrl+Alt+`".
+const HOTKEY: &str = "CJK_FIXER_HOTKEY";
+
+/// reads an override, an empty variable counts as unset.
+fn var(name: &str) -> Option<String> {
+    env::var(name).ok().filter(|value| !value.trim().is_empty())
+}
+
+pub fn config_path() -> Option<PathBuf> {
+    var(CONFIG).map(PathBuf::from)
+}
+
+/// note: read before logging is set up, so the caller logs the error once it is.
+pub fn logging() -> Option<Result<Logging>> {
+    let value = var(LOG)?;
+    Some(
+        toml::Value::String(value.trim().to_ascii_lowercase())
+            .try_into()
+            .with_context(|| format!("invalid {LOG}: {value:?}, expected \"file\" or \"off\"")),
+    )
+}
+
+pub fn hotkey() -> Option<Chord> {
+    let value = var(HOTKEY)?;
+    let chord = value
+        .parse()
+        .with_context(|| format!("invalid {HOTKEY}: {value:?}"))
+        .warn()?;
+    info!("{HOTKEY} toggles the terminal with {chord}");
+    Some(chord)
+}
@@ -0,0 +1,102 @@
+use std::{cell::RefCell, time::Duration};
+
+use anyhow::Result;
+#[allow(unused_imports)]
+use tracing::{debug, error, info, trace, warn};
+use windows::Win32::{
+    Foundation::{HWND, LPARAM, WPARAM},
+    System::Threading::GetCurrentThreadId,
+    UI::{
+        Input::KeyboardAndMouse::VK_OEM_3,
+        WindowsAndMessaging::{PostThreadMessageW, WM_APP},
+    },
+};
+
+use crate::{
+    chord,
+    hotkey::Hotkey,
+    inject::SCANCODE_OEM_3,
+    keyhook::{self, Key, Listener},
+    LogExt,
+};
+
+/// posted to the message pump once the held hotkey is released, the terminal is toggled back then.
+pub const WM_PEEK: u32 = WM_APP + 13;
+
+/// how long the hotkey has to be held for a peek, a shorter press toggles the terminal for good.
+const HOLD_THRESHOLD: Duration = Duration::from_millis(400);
+
+struct Held {
+    hotkey: Hotkey,
+    hwnd: HWND,
+    /// the time of the press in `GetTickCount` milliseconds.
+    pressed: u32,
+}
+
+thread_local! {
+    static HELD: RefCell<Option<Held>> = const { RefCell::new(None) };
+    static RELEASED: RefCell<Option<(HWND, Hotkey)>> = const { RefCell::new(None) };
+}
+
+/// starts waiting for the release of the hotkey that just showed the terminal of the window.
+pub fn begin(hotkey: Hotkey, hwnd: HWND, pressed: u32) -> Result<()> {
+    HELD.with(|held| {
+        *held.borrow_mut() = Some(Held {
+            hotkey,
+            hwnd,
+            pressed,
+        })
+    });
+    keyhook::listen(Listener::Peek, true)
+}
+
+/// whether the hotkey is still held since it started a peek, its repeated presses are ignored then.
+pub fn is_held(id: usize) -> bool {
+    HELD.with(|held| {
+        held.borrow()
+            .as_ref()
+            .is_some_and(|held| held.hotkey.id == id)
+    })
+}
+
+/// the window and the hotkey to toggle the terminal back with, once released after a peek.
+pub fn finish() -> Option<(HWND, Hotkey)> {
+    RELEASED.with(|released| released.borrow_mut().take())
+}
+
+/// finishes the peek once the held hotkey's key is released.
+pub fn on_key(key: &Key) {
+    if key.down {
+        return;
+    }
+    let is_key = |held: &Held| match held.hotkey.chord.vk {
+        // note: 「`」 is the physical key, whatever virtual key the layout maps it to.
+        VK_OEM_3 => key.scan_code == SCANCODE_OEM_3 as u32,
+        vk => key.vk == vk,
+    };
+    let released = HELD.with(|held| {
+        let mut held = held.borrow_mut();
+        match &*held {
+            Some(current) if is_key(current) => held.take(),
+            _ => None,
+        }
+    });
+    let Some(held) = released else {
+        return;
+    };
+    keyhook::listen(Listener::Peek, false).warn();
+    let duration = Duration::from_millis(key.time.wrapping_sub(held.pressed) as u64);
+    if duration < HOLD_THRESHOLD {
+        debug!("{} was tapped, not held", held.hotkey.chord);
+    } else if chord::held_modifiers() != held.hotkey.chord.modifiers {
+        // note: the toggle is pressed with the user's modifiers, without them a bare key would be
+        // typed.
+        info!(
+            "the modifiers of {} were released first, the terminal stays",
+            held.hotkey.chord
+        );
+    } else {
+        RELEASED.with(|released| *released.borrow_mut() = Some((held.hwnd, held.hotkey)));
+        unsafe { PostThreadMessageW(GetCurrentThreadId(), WM_PEEK, WPARAM(0), LPARAM(0)) }.warn();
+    }
+}
@@ -0,0 +1,143 @@
+use std::{
+    env, fs,
+    path::{Path, PathBuf},
+};
+
+use anyhow::{Context, Result};
+use serde::Deserialize;
+use windows::Win32::UI::Input::KeyboardAndMouse::VIRTUAL_KEY;
+
+use crate::{
+    config::Config,
+    hotkey::{self, Chord, ChordRule},
+};
+
+/// the mappings of PowerToys Keyboard Manager, as far as we care about them.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+pub struct KeyboardManager {
+    pub remap_keys: KeyRemaps,
+    pub remap_shortcuts: ShortcutRemaps,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+pub struct KeyRemaps {
+    pub in_process: Vec<Remap>,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+pub struct ShortcutRemaps {
+    pub global: Vec<Remap>,
+    pub app_specific: Vec<Remap>,
+}
+
+/// a single mapping, the keys are virtual key codes joined by `;`, e.g. `17;192` for 「Ctrl+`」.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+pub struct Remap {
+    pub original_keys: String,
+    pub new_remap_keys: String,
+    /// the executable without extension the mapping is limited to, e.g. `code`.
+    pub target_app: String,
+}
+
+impl Remap {
+    pub fn original(&self) -> Vec<VIRTUAL_KEY> {
+        parse_keys(&self.original_keys)
+    }
+
+    pub fn new_keys(&self) -> Vec<VIRTUAL_KEY> {
+        parse_keys(&self.new_remap_keys)
+    }
+}
+
+fn parse_keys(keys: &str) -> Vec<VIRTUAL_KEY> {
+    keys.split(';')
+        .filter_map(|key| key.trim().parse().ok())
+        .map(VIRTUAL_KEY)
+        .collect()
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+struct Settings {
+    properties: Properties,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+struct Properties {
+    active_configuration: Value,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+struct Value {
+    value: String,
+}
+
+/// where Keyboard Manager keeps its settings for the current user.
+fn dir() -> Option<PathBuf> {
+    env::var_os("LOCALAPPDATA").map(|local_app_data| {
+        PathBuf::from(local_app_data).join(r"Microsoft\PowerToys\Keyboard Manager")
+    })
+}
+
+/// the active mapping configuration, `None` if Keyboard Manager was never set up.
+pub fn keyboard_manager() -> Result<Option<KeyboardManager>> {
+    let Some(dir) = dir().filter(|dir| dir.is_dir()) else {
+        return Ok(None);
+    };
+    // note: the settings only name the active configuration, `default` unless the user renamed it.
+    let active = fs::read_to_string(dir.join("settings.json"))
+        .ok()
+        .and_then(|text| serde_json::from_str::<Settings>(&text).ok())
+        .map(|settings| settings.properties.active_configuration.value)
+        .filter(|name| !name.is_empty())
+        .unwrap_or_else(|| "default".into());
+    let path = dir.join(active).with_extension("json");
+    let text = fs::read_to_string(&path).with_context(|| format!("failed to read {path:?}"))?;
+    let config =
+        serde_json::from_str(&text).with_context(|| format!("invalid mappings: {path:?}"))?;
+    Ok(Some(config))
+}
+
+/// converts the shortcut remaps for VSCode onto one of the chords we fix into rules, e.g. a remap of
+/// 「Alt+T」 to 「Ctrl+`」 becomes `{ keys = ["Alt+T"], action = "toggle_terminal" }`.
+///
+/// note: remaps for other applications or onto other keys have no equivalent and are skipped.
+pub fn rules(mappings: &KeyboardManager) -> Vec<ChordRule> {
+    mappings
+        .remap_shortcuts
+        .app_specific
+        .iter()
+        .filter(|remap| remap.target_app.eq_ignore_ascii_case("code"))
+        .filter_map(|remap| {
+            let action = hotkey::builtin_action(Chord::from_keys(&remap.new_keys())?)?;
+            Some(ChordRule {
+                keys: vec![Chord::from_keys(&remap.original())?],
+                action,
+                enabled: true,
+            })
+        })
+        .collect()
+}
+
+/// adds the rules converted from Keyboard Manager to the config file, returns how many were new.
+pub fn import(config_path: &Path) -> Result<usize> {
+    let mappings = keyboard_manager()?.context("PowerToys Keyboard Manager isn't set up")?;
+    let mut chords = Config::load(config_path)?.chords;
+    let before = chords.len();
+    for rule in rules(&mappings) {
+        if !chords.iter().any(|chord| chord.is_same(&rule)) {
+            chords.push(rule);
+        }
+    }
+    let imported = chords.len() - before;
+    if imported > 0 {
+        Config::update(config_path, &hotkey::chords_value(&chords))?;
+    }
+    Ok(imported)
+}
@@ -0,0 +1,22 @@
+use windows::Win32::{
+    Foundation::HWND,
+    UI::Shell::{
+        SHQueryUserNotificationState, QUNS_BUSY, QUNS_PRESENTATION_MODE,
+        QUNS_RUNNING_D3D_FULL_SCREEN,
+    },
+};
+
+use crate::{config::Config, window, LogExt};
+
+/// whether the user is presenting, e.g. in presentation mode or a PowerPoint slide show, so a
+/// hotkey shouldn't pop up a terminal on the shared screen.
+///
+/// note: there's no public API telling whether the screen is captured, e.g. shared in Teams.
+pub fn is_presenting(config: &Config, foreground: HWND) -> bool {
+    match unsafe { SHQueryUserNotificationState() }.warn() {
+        Some(QUNS_PRESENTATION_MODE | QUNS_RUNNING_D3D_FULL_SCREEN) => true,
+        // note: a full-screen VSCode is busy as well, but the user is looking at the terminal then.
+        Some(QUNS_BUSY) => !window::is_target(config, foreground),
+        _ => false,
+    }
+}
@@ -0,0 +1,71 @@
+use std::path::Path;
+
+use anyhow::{Context, Result};
+
+use crate::config::Config;
+
+/// a curated set of config values, applied on top of the config file so it can be tweaked afterwards.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Preset {
+    VscodeOnly,
+    VscodeAndTerminals,
+    JetBrains,
+    AggressiveIme,
+}
+
+pub const PRESETS: [Preset; 4] = [
+    Preset::VscodeOnly,
+    Preset::VscodeAndTerminals,
+    Preset::JetBrains,
+    Preset::AggressiveIme,
+];
+
+impl Preset {
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::VscodeOnly => "VSCode only",
+            Self::VscodeAndTerminals => "VSCode + terminals",
+            Self::JetBrains => "JetBrains too",
+            Self::AggressiveIme => "Aggressive IME handling",
+        }
+    }
+
+    /// the keys the preset sets, every other key of the config file is left alone.
+    fn values(self) -> &'static str {
+        match self {
+            Self::VscodeOnly => {
+                r#"
+                target_processes = []
+                new_terminal_hotkey = false
+                chords = []
+                "#
+            }
+            Self::VscodeAndTerminals => {
+                r#"
+                target_processes = []
+                new_terminal_hotkey = true
+                chords = [{ keys = ["Ctrl+K", "Ctrl+`"], action = "focus_terminal" }]
+                "#
+            }
+            Self::JetBrains => {
+                r#"
+                target_processes = [
+                    "idea64.exe", "pycharm64.exe", "webstorm64.exe", "phpstorm64.exe", "clion64.exe",
+                    "goland64.exe", "rider64.exe", "rustrover64.exe", "rubymine64.exe", "datagrip64.exe",
+                ]
+                "#
+            }
+            Self::AggressiveIme => {
+                r#"
+                verify_injection = true
+                backends = ["send_input", "post_message", "command_palette", "uia"]
+                "#
+            }
+        }
+    }
+}
+
+/// writes the preset into the config file, keeping the user's other keys and comments.
+pub fn apply(path: &Path, preset: Preset) -> Result<()> {
+    Config::update(path, preset.values()).with_context(|| format!("failed to apply {preset:?}"))
+}
@@ -0,0 +1,88 @@
+use std::{cell::RefCell, collections::BTreeSet};
+
+use anyhow::Result;
+#[allow(unused_imports)]
+use tracing::{debug, error, info, trace, warn};
+use windows::Win32::{
+    Foundation::{HWND, WPARAM},
+    UI::WindowsAndMessaging::WM_APP,
+};
+
+use crate::{
+    config::Config,
+    hotkey::{self, Hotkey},
+    inject::{self, Action},
+    timer, uia, verify, window, LogExt,
+};
+
+/// posted to the message pump once VSCode had time to react to the raw key.
+pub const WM_PROBE: u32 = WM_APP + 14;
+
+/// what became of the raw key.
+pub enum Verdict {
+    /// VSCode toggled the terminal on its own, the fix only gets in the way with this layout.
+    Native,
+    /// nothing happened, the action still has to be injected into the window.
+    Swallowed(HWND, Hotkey),
+}
+
+struct Pending {
+    hwnd: HWND,
+    hotkey: Hotkey,
+    focus: String,
+}
+
+thread_local! {
+    /// the keyboard layouts probed this session, by their `HKL`.
+    static PROBED: RefCell<BTreeSet<isize>> = const { RefCell::new(BTreeSet::new()) };
+    static PENDING: RefCell<Option<Pending>> = const { RefCell::new(None) };
+}
+
+/// whether the hotkey pressed in the focused window should probe VSCode instead, once per keyboard
+/// layout.
+pub fn is_due(config: &Config, hwnd: HWND, hotkey: &Hotkey) -> bool {
+    config.probe_native_hotkey
+        && hotkey.action == Action::ToggleTerminal
+        && hotkey.then.is_none()
+        && !hotkey.without_modifiers()
+        && window::is_vscode_window(hwnd)
+        && PROBED.with(|probed| !probed.borrow().contains(&hotkey::foreground_layout().0))
+}
+
+/// hands the raw key to the window as if it wasn't registered, and schedules `WM_PROBE` to check
+/// whether the terminal reacted to it.
+pub fn start(hwnd: HWND, hotkey: Hotkey) -> Result<()> {
+    let hkl = hotkey::foreground_layout();
+    PROBED.with(|probed| probed.borrow_mut().insert(hkl.0));
+    let focus = uia::focus()?;
+    info!(
+        "probing whether {hwnd:?} handles {} with {hkl:?}",
+        hotkey.chord
+    );
+    // note: the raw key goes through the IME like a physical one, our hotkey is suspended meanwhile.
+    inject::send_input(hwnd, hotkey.keys())?;
+    PENDING.with(|pending| {
+        *pending.borrow_mut() = Some(Pending {
+            hwnd,
+            hotkey,
+            focus,
+        })
+    });
+    timer::once(verify::VERIFY_DELAY, WM_PROBE, WPARAM(0))?;
+    Ok(())
+}
+
+/// whether the focus moved since the raw key was handed to the window.
+pub fn finish() -> Option<Verdict> {
+    let pending = PENDING.with(|pending| pending.borrow_mut().take())?;
+    let focus = uia::focus().warn()?;
+    if focus == pending.focus {
+        info!("{:?} swallowed {}", pending.hwnd, pending.hotkey.chord);
+        return Some(Verdict::Swallowed(pending.hwnd, pending.hotkey));
+    }
+    warn!(
+        "{:?} handled {} on its own: the focus moved from {:?} to {focus:?}",
+        pending.hwnd, pending.hotkey.chord, pending.focus
+    );
+    Some(Verdict::Native)
+}
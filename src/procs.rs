@@ -0,0 +1,94 @@
+use std::{mem, path::Path};
+
+use anyhow::Result;
+use windows::{
+    core::PWSTR,
+    Win32::{
+        Foundation::{CloseHandle, HANDLE, HWND},
+        Security::{GetTokenInformation, TokenElevation, TOKEN_ELEVATION, TOKEN_QUERY},
+        System::{
+            ProcessStatus::K32EnumProcesses,
+            Threading::{
+                OpenProcess, OpenProcessToken, QueryFullProcessImageNameW, PROCESS_NAME_WIN32,
+                PROCESS_QUERY_LIMITED_INFORMATION,
+            },
+        },
+        UI::WindowsAndMessaging::GetWindowThreadProcessId,
+    },
+};
+
+use crate::LogExt;
+
+/// the id of the process owning the window.
+pub fn of_window(hwnd: HWND) -> u32 {
+    let mut pid = 0;
+    unsafe { GetWindowThreadProcessId(hwnd, Some(&mut pid)) };
+    pid
+}
+
+/// the executable file name of the process, e.g. `Code.exe`.
+pub fn name(pid: u32) -> Option<String> {
+    unsafe {
+        let handle = OpenProcess(PROCESS_QUERY_LIMITED_INFORMATION, false, pid).ok()?;
+        let mut buffer = [0u16; 1024];
+        let mut buffer_used_count = buffer.len() as u32;
+        let result = QueryFullProcessImageNameW(
+            handle,
+            PROCESS_NAME_WIN32,
+            PWSTR(buffer.as_mut_ptr()),
+            &mut buffer_used_count,
+        );
+        CloseHandle(handle).warn();
+        result.ok()?;
+
+        let path = String::from_utf16_lossy(&buffer[..buffer_used_count as usize]);
+        Path::new(&path)
+            .file_name()
+            .map(|name| name.to_string_lossy().into_owned())
+    }
+}
+
+/// names of all running processes we are allowed to query.
+pub fn running() -> Vec<String> {
+    let mut pids = vec![0u32; 4096];
+    let mut bytes_used = 0;
+    let ok = unsafe {
+        K32EnumProcesses(
+            pids.as_mut_ptr(),
+            (pids.len() * mem::size_of::<u32>()) as u32,
+            &mut bytes_used,
+        )
+    };
+    if !ok.as_bool() {
+        return Vec::new();
+    }
+    pids.truncate(bytes_used as usize / mem::size_of::<u32>());
+    pids.into_iter().filter_map(name).collect()
+}
+
+/// whether the process runs as administrator, input from a process that doesn't is blocked then.
+///
+/// note: the token of an elevated process can't be opened from a non-elevated one, which is why an
+/// error is likely an elevated process.
+pub fn is_elevated(pid: u32) -> Result<bool> {
+    unsafe {
+        let process = OpenProcess(PROCESS_QUERY_LIMITED_INFORMATION, false, pid)?;
+        let mut token = HANDLE::default();
+        let opened = OpenProcessToken(process, TOKEN_QUERY, &mut token);
+        CloseHandle(process).warn();
+        opened?;
+
+        let mut elevation = TOKEN_ELEVATION::default();
+        let mut len = 0;
+        let result = GetTokenInformation(
+            token,
+            TokenElevation,
+            Some(&mut elevation as *mut TOKEN_ELEVATION as *mut _),
+            mem::size_of::<TOKEN_ELEVATION>() as u32,
+            &mut len,
+        );
+        CloseHandle(token).warn();
+        result?;
+        Ok(elevation.TokenIsElevated != 0)
+    }
+}
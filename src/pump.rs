@@ -0,0 +1,845 @@
+use std::{
+    collections::BTreeSet,
+    path::Path,
+    sync::mpsc,
+    thread,
+    time::{Duration, Instant},
+};
+
+#[allow(unused_imports)]
+use tracing::{debug, error, field, info, info_span, trace, warn};
+use windows::Win32::{
+    Foundation::HWND,
+    System::SystemInformation::GetTickCount,
+    UI::{
+        Accessibility::{UnhookWinEvent, HWINEVENTHOOK},
+        TextServices::HKL,
+        WindowsAndMessaging::{
+            DispatchMessageW, GetForegroundWindow, TranslateMessage, MSG, PBT_APMRESUMEAUTOMATIC,
+            PBT_APMSUSPEND, WM_HOTKEY, WTS_CONSOLE_CONNECT, WTS_REMOTE_CONNECT, WTS_SESSION_LOCK,
+            WTS_SESSION_UNLOCK,
+        },
+    },
+};
+
+#[cfg(feature = "ipc")]
+use crate::ipc;
+use crate::{
+    bus::{Change, Event, MenuAction},
+    chord,
+    config::{self, Config},
+    dpi, gesture, heartbeat, history,
+    hotkey::{self, Hotkey},
+    inject, keybindings, keyboard, launch, metrics, peek, probe, procs, quiet, quirk, restart,
+    retry, schedule, session,
+    state::State,
+    status, summary, telemetry, theme, timestamp,
+    tray::Tray,
+    upgrade, verify, window, LogExt, LATENCY_BUDGET,
+};
+
+/// what the message pump does to Windows, the simulation in the tests fakes it.
+///
+/// note: the follow-ups of a press, e.g. `WM_PROBE`, take their hotkey from their own module, which
+/// only Windows fills, so they aren't part of it.
+pub trait Platform {
+    /// the time in `GetTickCount` milliseconds, messages carry it as well.
+    fn now(&self) -> u32;
+    /// the window the user works in, see `window::foreground`.
+    fn foreground(&self) -> HWND;
+    /// whether the window receives the hotkeys, e.g. one of VSCode.
+    fn is_target(&self, config: &Config, hwnd: HWND) -> bool;
+    /// a registered hotkey, by the id `WM_HOTKEY` carries.
+    fn find_hotkey(&self, id: usize) -> Option<Hotkey>;
+    /// replaces the registered hotkeys with the configured ones, or none while paused.
+    fn register_hotkeys(&mut self, config: &Config, paused: bool);
+    /// lets the chords of the hotkeys through until `resume_hotkeys`, e.g. while locked.
+    fn suspend_hotkeys(&mut self);
+    fn resume_hotkeys(&mut self);
+    /// posts the chord of the hotkey to the focused window as if it wasn't registered.
+    fn pass_through(&mut self, hotkey: &Hotkey);
+    /// performs the hotkey's action, returns the target window unless there's none.
+    fn dispatch(&mut self, config: &Config, hotkey: &Hotkey) -> Option<HWND>;
+    /// keeps the terminal open for as long as the hotkey is held.
+    fn begin_peek(&mut self, hotkey: &Hotkey, hwnd: HWND, pressed: u32);
+    /// counts the performed hotkey, e.g. for the history and the usage report.
+    fn record(&mut self, config: &Config, hotkey: &Hotkey, target: Option<HWND>, latency: Duration);
+    /// looks up the running processes again, for the quirks of the next hotkey.
+    fn prepare_quirks(&mut self);
+    /// installs what the config needs watched, e.g. the foreground window for dimming the icon.
+    fn watch(&mut self, config: &Config);
+    /// builds the tray menu again, e.g. once the config changed, keeping what's checked.
+    fn rebuild_menu(&mut self, config: &Config);
+    /// writes the state file.
+    fn save(&mut self, state: &mut State);
+    /// hands a message nothing here handles to its window, e.g. the tray's.
+    fn dispatch_message(&mut self, msg: &MSG);
+}
+
+/// why the user is away, the hotkeys are let through meanwhile.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+enum Away {
+    Locked,
+    Asleep,
+}
+
+/// what the message pump keeps between messages besides the `State`.
+pub struct Pump<'a> {
+    config: Config,
+    app_path: Option<&'a Path>,
+    tx: mpsc::Sender<Event>,
+    away: BTreeSet<Away>,
+}
+
+impl<'a> Pump<'a> {
+    pub fn new(config: Config, app_path: Option<&'a Path>, tx: mpsc::Sender<Event>) -> Self {
+        Self {
+            config,
+            app_path,
+            tx,
+            away: BTreeSet::new(),
+        }
+    }
+
+    /// handles a message of the pump, a timer's already stands in for the message it was started
+    /// for.
+    pub fn handle(&mut self, state: &mut State, msg: &MSG, platform: &mut impl Platform) {
+        // note: the id of the hotkey performed while handling the message, if any.
+        let mut performed = None;
+        match msg.message {
+            WM_HOTKEY => match platform.find_hotkey(msg.wParam.0) {
+                // note: a held hotkey repeats, which would toggle the peeking terminal again.
+                Some(hotkey) if peek::is_held(hotkey.id) => {}
+                Some(hotkey) if !keyboard::intercepts(&self.config) => {
+                    // note: the focused window gets the chord as if it wasn't registered.
+                    platform.pass_through(&hotkey);
+                }
+                Some(hotkey) if hotkey.then.is_some() => {
+                    chord::begin(hotkey).warn();
+                }
+                Some(hotkey) => {
+                    on_hotkey(&self.config, state, &hotkey, msg.time, platform);
+                    performed = Some(hotkey.id);
+                }
+                None => warn!("unknown hotkey: {:?}", msg.wParam),
+            },
+            schedule::WM_SCHEDULE => {
+                if schedule::check() {
+                    match schedule::is_off() {
+                        true => info!("outside the active hours"),
+                        false => info!("within the active hours"),
+                    }
+                    platform.register_hotkeys(&self.config, state.paused);
+                    self.send(Event::System(Change::Status));
+                }
+            }
+            upgrade::WM_UPGRADE => {
+                if upgrade::check(&self.config, &mut state.vscode_version) {
+                    platform.save(state);
+                }
+            }
+            #[cfg(feature = "ipc")]
+            ipc::WM_TRIGGER => {
+                if let Some(&action) = inject::ACTIONS.get(msg.wParam.0) {
+                    let hotkey = hotkey::unpressed(action);
+                    on_hotkey(&self.config, state, &hotkey, msg.time, platform);
+                    performed = Some(hotkey.id);
+                }
+            }
+            chord::WM_CHORD => {
+                if let Some(hotkey) = platform.find_hotkey(msg.wParam.0) {
+                    on_hotkey(&self.config, state, &hotkey, msg.time, platform);
+                    performed = Some(hotkey.id);
+                }
+            }
+            gesture::WM_GESTURE => match gesture::find(msg.wParam.0) {
+                Some(hotkey) if keyboard::intercepts(&self.config) => {
+                    on_hotkey(&self.config, state, &hotkey, msg.time, platform);
+                    performed = Some(hotkey.id);
+                }
+                _ => {}
+            },
+            peek::WM_PEEK => {
+                if let Some((hwnd, hotkey)) = peek::finish() {
+                    let _span = crate::trigger_span(&hotkey).entered();
+                    crate::perform(&self.config, hwnd, &hotkey);
+                }
+            }
+            launch::WM_LAUNCHED => {
+                if let Some(hotkey) = launch::take_pending() {
+                    let _span = crate::trigger_span(&hotkey).entered();
+                    crate::perform(
+                        &self.config,
+                        HWND(msg.wParam.0 as isize),
+                        &hotkey.deferred(),
+                    );
+                }
+            }
+            config::WM_RELOAD => {
+                let Some(reloaded) = self
+                    .app_path
+                    .and_then(|app_path| Config::load(&Config::path(app_path)).warn())
+                else {
+                    return;
+                };
+                info!("{reloaded:?}");
+                schedule::configure(reloaded.active_hours.clone());
+                heartbeat::configure(reloaded.heartbeat_path.clone());
+                platform.register_hotkeys(&reloaded, state.paused);
+                platform.watch(&reloaded);
+                if self.config.dim_when_unfocused != reloaded.dim_when_unfocused {
+                    let focused = is_focused(&reloaded, platform);
+                    self.send(Event::System(Change::Focused(focused)));
+                }
+                quiet::configure(reloaded.quiet);
+                keybindings::forget();
+                crate::configure_snapshots(&reloaded, self.app_path);
+                self.config = reloaded;
+                platform.rebuild_menu(&self.config);
+            }
+            session::WM_SESSION => match msg.wParam.0 as u32 {
+                WTS_SESSION_LOCK => self.leave(Away::Locked, platform),
+                WTS_SESSION_UNLOCK => {
+                    self.come_back(Away::Locked, state, platform);
+                    summary::show_if_due(&self.config);
+                }
+                WTS_CONSOLE_CONNECT | WTS_REMOTE_CONNECT if self.away.is_empty() => {
+                    self.revalidate(state, platform);
+                }
+                _ => {}
+            },
+            session::WM_POWER => match msg.wParam.0 as u32 {
+                PBT_APMSUSPEND => self.leave(Away::Asleep, platform),
+                // note: the keyboard hooks may be gone after sleeping, they're installed again with
+                // the hotkeys.
+                PBT_APMRESUMEAUTOMATIC => self.come_back(Away::Asleep, state, platform),
+                _ => {}
+            },
+            window::WM_FOREGROUND => {
+                platform.prepare_quirks();
+                let hwnd = HWND(msg.wParam.0 as isize);
+                if self.config.dim_when_unfocused {
+                    let focused = platform.is_target(&self.config, hwnd);
+                    self.send(Event::System(Change::Focused(focused)));
+                }
+            }
+            dpi::WM_DPI | theme::WM_THEME | crate::tray::WM_TASKBAR_CREATED => {
+                // note: the glyphs of the menu follow the theme of applications.
+                if msg.message == theme::WM_THEME {
+                    platform.rebuild_menu(&self.config);
+                }
+                // note: Explorer may come back with another scaling or theme of the taskbar.
+                self.send(Event::System(Change::Display));
+            }
+            hotkey::WM_LAYOUT => {
+                if hotkey::switch_layout(HKL(msg.lParam.0)) && self.away.is_empty() {
+                    platform.register_hotkeys(&self.config, state.paused);
+                }
+            }
+            hotkey::WM_PAUSE => {
+                state.paused = msg.wParam.0 != 0;
+                info!("paused: {}", state.paused);
+                platform.register_hotkeys(&self.config, state.paused);
+                restart::register(state.paused).warn();
+                platform.save(state);
+            }
+            probe::WM_PROBE => match probe::finish() {
+                Some(probe::Verdict::Native) if !state.paused => {
+                    // note: the tray owns the pause, it's toggled as if clicked.
+                    self.send(Event::Tray(MenuAction::Pause));
+                    thread::spawn(|| {
+                        crate::notify(
+                            "VSCode toggles the terminal on its own with this keyboard layout, so the hotkeys are paused to avoid double toggles. Resume them from the tray if needed.",
+                        )
+                    });
+                }
+                Some(probe::Verdict::Swallowed(hwnd, hotkey)) => {
+                    let _span = crate::trigger_span(&hotkey).entered();
+                    crate::perform(&self.config, hwnd, &hotkey.deferred());
+                }
+                _ => {}
+            },
+            retry::WM_RETRY => {
+                if let Some((hwnd, hotkey)) = retry::finish(msg.wParam.0, msg.lParam.0 != 0) {
+                    let _span = crate::trigger_span(&hotkey).entered();
+                    crate::inject_into(&self.config, hwnd, &hotkey.deferred());
+                }
+            }
+            verify::WM_VERIFY => {
+                // note: retried only once, the retry itself isn't verified.
+                if let Some((hwnd, hotkey, backends)) = verify::finish() {
+                    let _span = crate::trigger_span(&hotkey).entered();
+                    crate::check_conflicts(state);
+                    inject::mock_key_press(&self.config, hwnd, &hotkey.deferred(), &backends)
+                        .warn();
+                }
+            }
+            _unhandled_message => platform.dispatch_message(msg),
+        }
+        // note: a hotkey changes the status as well, its event refreshes it.
+        let changed = status::take_changed();
+        match performed {
+            Some(id) => self.send(Event::Hotkey { id }),
+            None if changed => self.send(Event::System(Change::Status)),
+            None => {}
+        }
+        if inject::take_blocked() {
+            self.send(Event::System(Change::Blocked));
+        }
+        if window::take_hidden() {
+            self.send(Event::System(Change::Hidden));
+        }
+    }
+
+    fn send(&self, event: Event) {
+        self.tx.send(event).warn();
+    }
+
+    /// lets the hotkeys through from the first reason to be away on.
+    fn leave(&mut self, away: Away, platform: &mut impl Platform) {
+        if self.away.is_empty() {
+            platform.suspend_hotkeys();
+        }
+        self.away.insert(away);
+    }
+
+    /// registers the hotkeys again once every reason to be away is gone, e.g. waking up to the lock
+    /// screen waits for the unlock.
+    fn come_back(&mut self, away: Away, state: &State, platform: &mut impl Platform) {
+        self.away.remove(&away);
+        if self.away.is_empty() {
+            platform.resume_hotkeys();
+            self.revalidate(state, platform);
+        }
+    }
+
+    /// re-registers the hotkeys and refreshes the tray icon once the session is back with the user.
+    fn revalidate(&self, state: &State, platform: &mut impl Platform) {
+        platform.register_hotkeys(&self.config, state.paused);
+        self.send(Event::System(Change::Revalidate));
+    }
+}
+
+/// whether the tray icon is lit, which is always unless it's dimmed while no window receiving the
+/// hotkeys is focused.
+fn is_focused(config: &Config, platform: &impl Platform) -> bool {
+    !config.dim_when_unfocused || platform.is_target(config, platform.foreground())
+}
+
+/// `pressed` is the time of the key press in `GetTickCount` milliseconds, i.e. the message time.
+fn on_hotkey(
+    config: &Config,
+    state: &mut State,
+    hotkey: &Hotkey,
+    pressed: u32,
+    platform: &mut impl Platform,
+) {
+    let hotkey = &hotkey.pressed();
+    let span = info_span!(
+        "hotkey",
+        trigger = hotkey.trigger,
+        chord = %hotkey.chord,
+        latency_ms = field::Empty
+    );
+    history::begin();
+    let h_target_wnd = span.in_scope(|| platform.dispatch(config, hotkey));
+    let latency = Duration::from_millis(platform.now().wrapping_sub(pressed) as u64);
+    span.record("latency_ms", latency.as_millis() as u64);
+    if latency > LATENCY_BUDGET {
+        warn!(
+            "{} took {latency:?}, over the budget of {LATENCY_BUDGET:?}",
+            hotkey.chord
+        );
+    }
+    drop(span);
+
+    if config.hold_to_peek
+        && hotkey.action == inject::Action::ToggleTerminal
+        && hotkey.then.is_none()
+        && !hotkey.without_modifiers()
+    {
+        if let Some(hwnd) = h_target_wnd {
+            platform.begin_peek(hotkey, hwnd, pressed);
+        }
+    }
+
+    // note: the bookkeeping waits until the action is injected, it's not part of the latency.
+    *state.triggers.entry(hotkey.action).or_default() += 1;
+    platform.record(config, hotkey, h_target_wnd, latency);
+}
+
+/// the platform of the running app.
+pub struct Win32<'a> {
+    tray: &'a Tray<Event>,
+    app_path: Option<&'a Path>,
+    state_path: Option<&'a Path>,
+    started: Instant,
+    offers_auto_launch: bool,
+    suspended: Option<hotkey::Suspended>,
+    foreground_hook: Option<HWINEVENTHOOK>,
+    keyboard_window: Option<HWND>,
+}
+
+impl<'a> Win32<'a> {
+    /// takes over the watchers installed on startup, they're removed once it's dropped.
+    pub fn new(
+        tray: &'a Tray<Event>,
+        app_path: Option<&'a Path>,
+        state_path: Option<&'a Path>,
+        started: Instant,
+        offers_auto_launch: bool,
+        foreground_hook: Option<HWINEVENTHOOK>,
+        keyboard_window: Option<HWND>,
+    ) -> Self {
+        Self {
+            tray,
+            app_path,
+            state_path,
+            started,
+            offers_auto_launch,
+            suspended: None,
+            foreground_hook,
+            keyboard_window,
+        }
+    }
+}
+
+impl Platform for Win32<'_> {
+    fn now(&self) -> u32 {
+        unsafe { GetTickCount() }
+    }
+
+    fn foreground(&self) -> HWND {
+        window::foreground()
+    }
+
+    fn is_target(&self, config: &Config, hwnd: HWND) -> bool {
+        window::is_target(config, hwnd)
+    }
+
+    fn find_hotkey(&self, id: usize) -> Option<Hotkey> {
+        hotkey::find(id)
+    }
+
+    fn register_hotkeys(&mut self, config: &Config, paused: bool) {
+        crate::register_hotkeys(config, paused);
+    }
+
+    fn suspend_hotkeys(&mut self) {
+        self.suspended.get_or_insert_with(hotkey::suspend);
+    }
+
+    fn resume_hotkeys(&mut self) {
+        self.suspended = None;
+    }
+
+    fn pass_through(&mut self, hotkey: &Hotkey) {
+        inject::post_keys(unsafe { GetForegroundWindow() }, [hotkey.chord.vk]).warn();
+    }
+
+    fn dispatch(&mut self, config: &Config, hotkey: &Hotkey) -> Option<HWND> {
+        crate::dispatch(config, hotkey)
+    }
+
+    fn begin_peek(&mut self, hotkey: &Hotkey, hwnd: HWND, pressed: u32) {
+        peek::begin(*hotkey, hwnd, pressed).warn();
+    }
+
+    fn record(
+        &mut self,
+        config: &Config,
+        hotkey: &Hotkey,
+        target: Option<HWND>,
+        latency: Duration,
+    ) {
+        telemetry::record_trigger();
+        metrics::record_trigger();
+        telemetry::report_if_due(config);
+        summary::record_trigger();
+        let target = target.and_then(|hwnd| procs::name(procs::of_window(hwnd)));
+        history::record(hotkey.action, target.clone(), latency);
+        let last_trigger = status::LastTrigger {
+            action: hotkey.action,
+            at: timestamp::now(timestamp::Timezone::Utc),
+        };
+        status::update(|status| {
+            status.target = target;
+            status.last_trigger = Some(last_trigger);
+        });
+        quirk::prepare();
+    }
+
+    fn prepare_quirks(&mut self) {
+        quirk::prepare();
+    }
+
+    /// note: the hooks are kept once installed, they're cheap and only store what they're told.
+    fn watch(&mut self, config: &Config) {
+        if self.foreground_hook.is_none() && config.tracks_foreground() {
+            self.foreground_hook = Some(window::track_foreground());
+        }
+        if self.keyboard_window.is_none() && !config.keyboards.is_empty() {
+            self.keyboard_window = keyboard::watch().warn();
+        }
+    }
+
+    fn rebuild_menu(&mut self, config: &Config) {
+        crate::rebuild_menu(
+            self.tray,
+            config,
+            self.app_path,
+            self.offers_auto_launch,
+            self.started.elapsed(),
+        );
+    }
+
+    fn save(&mut self, state: &mut State) {
+        crate::save(state, self.state_path);
+    }
+
+    fn dispatch_message(&mut self, msg: &MSG) {
+        unsafe {
+            TranslateMessage(msg);
+            DispatchMessageW(msg);
+        }
+    }
+}
+
+impl Drop for Win32<'_> {
+    fn drop(&mut self) {
+        if let Some(hwnd) = self.keyboard_window {
+            keyboard::unwatch(hwnd);
+        }
+        if let Some(hook) = self.foreground_hook {
+            unsafe { UnhookWinEvent(hook) };
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{mem, sync::mpsc::Receiver};
+
+    use windows::Win32::Foundation::{LPARAM, WPARAM};
+
+    use super::*;
+    use crate::inject::Action;
+
+    /// a window of VSCode and one of another app, the fake tells them apart by the handle alone.
+    const VSCODE: HWND = HWND(0x1000);
+    const NOTEPAD: HWND = HWND(0x2000);
+
+    /// what the pump did to Windows.
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    enum Effect {
+        Registered {
+            paused: bool,
+        },
+        Suspended,
+        Resumed,
+        PassedThrough(Action),
+        /// performed while the window was in the foreground.
+        Dispatched(Action, HWND),
+        Recorded(Action, Duration),
+        MenuRebuilt,
+        Saved,
+        Handed(u32),
+    }
+
+    /// Windows as scripted by a test, its clock only moves when told.
+    struct Fake {
+        clock: u32,
+        foreground: HWND,
+        hotkeys: Vec<Hotkey>,
+        /// how long performing an action takes.
+        dispatch_time: u32,
+        effects: Vec<Effect>,
+    }
+
+    impl Platform for Fake {
+        fn now(&self) -> u32 {
+            self.clock
+        }
+
+        fn foreground(&self) -> HWND {
+            self.foreground
+        }
+
+        fn is_target(&self, _: &Config, hwnd: HWND) -> bool {
+            hwnd == VSCODE
+        }
+
+        fn find_hotkey(&self, id: usize) -> Option<Hotkey> {
+            self.hotkeys.iter().find(|hotkey| hotkey.id == id).copied()
+        }
+
+        fn register_hotkeys(&mut self, config: &Config, paused: bool) {
+            self.hotkeys = match paused {
+                true => Vec::new(),
+                false => hotkey::table(config),
+            };
+            self.effects.push(Effect::Registered { paused });
+        }
+
+        fn suspend_hotkeys(&mut self) {
+            self.effects.push(Effect::Suspended);
+        }
+
+        fn resume_hotkeys(&mut self) {
+            self.effects.push(Effect::Resumed);
+        }
+
+        fn pass_through(&mut self, hotkey: &Hotkey) {
+            self.effects.push(Effect::PassedThrough(hotkey.action));
+        }
+
+        fn dispatch(&mut self, _: &Config, hotkey: &Hotkey) -> Option<HWND> {
+            self.clock = self.clock.wrapping_add(self.dispatch_time);
+            self.effects
+                .push(Effect::Dispatched(hotkey.action, self.foreground));
+            Some(VSCODE)
+        }
+
+        fn begin_peek(&mut self, _: &Hotkey, _: HWND, _: u32) {}
+
+        fn record(&mut self, _: &Config, hotkey: &Hotkey, _: Option<HWND>, latency: Duration) {
+            self.effects.push(Effect::Recorded(hotkey.action, latency));
+        }
+
+        fn prepare_quirks(&mut self) {}
+
+        fn watch(&mut self, _: &Config) {}
+
+        fn rebuild_menu(&mut self, _: &Config) {
+            self.effects.push(Effect::MenuRebuilt);
+        }
+
+        fn save(&mut self, _: &mut State) {
+            self.effects.push(Effect::Saved);
+        }
+
+        fn dispatch_message(&mut self, msg: &MSG) {
+            self.effects.push(Effect::Handed(msg.message));
+        }
+    }
+
+    /// the pump running on the fake, as after startup with the config.
+    struct Simulation {
+        pump: Pump<'static>,
+        state: State,
+        fake: Fake,
+        rx: Receiver<Event>,
+    }
+
+    impl Simulation {
+        fn new(config: &str) -> Self {
+            let config: Config = toml::from_str(config).unwrap();
+            let (tx, rx) = mpsc::channel();
+            let fake = Fake {
+                clock: 0,
+                foreground: NOTEPAD,
+                hotkeys: hotkey::table(&config),
+                dispatch_time: 0,
+                effects: Vec::new(),
+            };
+            Self {
+                pump: Pump::new(config, None, tx),
+                state: State::default(),
+                fake,
+                rx,
+            }
+        }
+
+        fn wait(&mut self, ms: u32) {
+            self.fake.clock = self.fake.clock.wrapping_add(ms);
+        }
+
+        /// hands the pump a message posted just now.
+        fn post(&mut self, message: u32, wparam: usize) {
+            let msg = MSG {
+                message,
+                wParam: WPARAM(wparam),
+                lParam: LPARAM(0),
+                time: self.fake.clock,
+                ..Default::default()
+            };
+            self.pump.handle(&mut self.state, &msg, &mut self.fake);
+        }
+
+        fn focus(&mut self, hwnd: HWND) {
+            self.fake.foreground = hwnd;
+            self.post(window::WM_FOREGROUND, hwnd.0 as usize);
+        }
+
+        fn press(&mut self, action: Action) {
+            let id = self
+                .fake
+                .hotkeys
+                .iter()
+                .find(|hotkey| hotkey.action == action)
+                .unwrap()
+                .id;
+            self.post(WM_HOTKEY, id);
+        }
+
+        /// what the pump did to Windows and sent to the tray since the last call.
+        fn take(&mut self) -> (Vec<Effect>, Vec<Event>) {
+            (
+                mem::take(&mut self.fake.effects),
+                self.rx.try_iter().collect(),
+            )
+        }
+    }
+
+    fn toggle_id() -> usize {
+        hotkey::table(&Config::default())[0].id
+    }
+
+    #[test]
+    fn hotkey_while_vscode_is_focused() {
+        let mut sim = Simulation::new("dim_when_unfocused = true");
+        sim.focus(VSCODE);
+        assert_eq!(
+            sim.take(),
+            (vec![], vec![Event::System(Change::Focused(true))])
+        );
+        sim.wait(1000);
+        sim.fake.dispatch_time = 12;
+        sim.press(Action::ToggleTerminal);
+        assert_eq!(
+            sim.take(),
+            (
+                vec![
+                    Effect::Dispatched(Action::ToggleTerminal, VSCODE),
+                    Effect::Recorded(Action::ToggleTerminal, Duration::from_millis(12)),
+                ],
+                vec![Event::Hotkey { id: toggle_id() }]
+            )
+        );
+        assert_eq!(sim.state.triggers[&Action::ToggleTerminal], 1);
+
+        // note: dimmed, but the hotkey still brings VSCode up.
+        sim.focus(NOTEPAD);
+        sim.press(Action::ToggleTerminal);
+        assert_eq!(
+            sim.take(),
+            (
+                vec![
+                    Effect::Dispatched(Action::ToggleTerminal, NOTEPAD),
+                    Effect::Recorded(Action::ToggleTerminal, Duration::from_millis(12)),
+                ],
+                vec![
+                    Event::System(Change::Focused(false)),
+                    Event::Hotkey { id: toggle_id() }
+                ]
+            )
+        );
+        assert_eq!(sim.state.triggers[&Action::ToggleTerminal], 2);
+    }
+
+    #[test]
+    fn latency_counts_from_the_key_press() {
+        let mut sim = Simulation::new("");
+        // note: the tick count wraps after 49.7 days of uptime.
+        sim.wait(u32::MAX - 10);
+        sim.fake.dispatch_time = 80;
+        sim.press(Action::ToggleTerminal);
+        let (effects, _) = sim.take();
+        assert_eq!(
+            effects[1],
+            Effect::Recorded(Action::ToggleTerminal, Duration::from_millis(80))
+        );
+    }
+
+    #[test]
+    fn unknown_hotkeys_are_ignored() {
+        let mut sim = Simulation::new("");
+        sim.post(WM_HOTKEY, 1);
+        assert_eq!(sim.take(), (vec![], vec![]));
+        assert!(sim.state.triggers.is_empty());
+    }
+
+    #[test]
+    fn explorer_restart_rechecks_the_taskbar() {
+        let mut sim = Simulation::new("");
+        sim.post(crate::tray::WM_TASKBAR_CREATED, 0);
+        assert_eq!(sim.take(), (vec![], vec![Event::System(Change::Display)]));
+    }
+
+    #[test]
+    fn theme_changes_rebuild_the_menu_each() {
+        let mut sim = Simulation::new("");
+        for _ in 0..20 {
+            sim.post(theme::WM_THEME, 0);
+            sim.wait(5);
+        }
+        // note: the bus settles the display changes, see `bus::tests`.
+        assert_eq!(
+            sim.take(),
+            (
+                vec![Effect::MenuRebuilt; 20],
+                vec![Event::System(Change::Display); 20]
+            )
+        );
+    }
+
+    #[test]
+    fn sleep_and_resume() {
+        let mut sim = Simulation::new("");
+        sim.post(session::WM_POWER, PBT_APMSUSPEND as usize);
+        assert_eq!(sim.take(), (vec![Effect::Suspended], vec![]));
+        sim.wait(8 * 60 * 60 * 1000);
+        sim.post(session::WM_POWER, PBT_APMRESUMEAUTOMATIC as usize);
+        assert_eq!(
+            sim.take(),
+            (
+                vec![Effect::Resumed, Effect::Registered { paused: false }],
+                vec![Event::System(Change::Revalidate)]
+            )
+        );
+        sim.press(Action::ToggleTerminal);
+        assert_eq!(sim.take().1, [Event::Hotkey { id: toggle_id() }]);
+    }
+
+    #[test]
+    fn resuming_while_paused_registers_nothing() {
+        let mut sim = Simulation::new("");
+        sim.state.paused = true;
+        sim.post(session::WM_POWER, PBT_APMSUSPEND as usize);
+        sim.post(session::WM_POWER, PBT_APMRESUMEAUTOMATIC as usize);
+        assert_eq!(
+            sim.take().0,
+            [
+                Effect::Suspended,
+                Effect::Resumed,
+                Effect::Registered { paused: true }
+            ]
+        );
+        assert_eq!(sim.fake.hotkeys, []);
+    }
+
+    #[test]
+    fn waking_up_to_the_lock_screen_waits_for_the_unlock() {
+        let mut sim = Simulation::new("");
+        sim.post(session::WM_SESSION, WTS_SESSION_LOCK as usize);
+        sim.post(session::WM_POWER, PBT_APMSUSPEND as usize);
+        sim.post(session::WM_POWER, PBT_APMRESUMEAUTOMATIC as usize);
+        sim.post(session::WM_SESSION, WTS_CONSOLE_CONNECT as usize);
+        assert_eq!(sim.take(), (vec![Effect::Suspended], vec![]));
+        sim.post(session::WM_SESSION, WTS_SESSION_UNLOCK as usize);
+        assert_eq!(
+            sim.take(),
+            (
+                vec![Effect::Resumed, Effect::Registered { paused: false }],
+                vec![Event::System(Change::Revalidate)]
+            )
+        );
+    }
+
+    #[test]
+    fn other_messages_go_to_their_window() {
+        let mut sim = Simulation::new("");
+        sim.post(0x0113, 0);
+        assert_eq!(sim.take(), (vec![Effect::Handed(0x0113)], vec![]));
+    }
+}
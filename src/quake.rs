@@ -0,0 +1,69 @@
+use std::{mem, sync::Mutex};
+
+use anyhow::{Context, Result};
+use windows::Win32::{
+    Foundation::{HWND, POINT},
+    Graphics::Gdi::{GetMonitorInfoW, MonitorFromPoint, MONITORINFO, MONITOR_DEFAULTTONEAREST},
+    UI::WindowsAndMessaging::{
+        GetCursorPos, GetWindowPlacement, SetWindowPlacement, SetWindowPos, ShowWindow,
+        SWP_NOACTIVATE, SWP_NOZORDER, SW_MAXIMIZE, SW_RESTORE, WINDOWPLACEMENT,
+    },
+};
+
+use crate::LockExt;
+
+/// the window docked by the last summon and the placement it had before.
+static DOCKED: Mutex<Option<(HWND, WINDOWPLACEMENT)>> = Mutex::new(None);
+
+/// docks the window to the top of the monitor under the cursor, remembering its previous placement.
+pub fn dock(hwnd: HWND, height_percent: u32) -> Result<()> {
+    unsafe {
+        let mut placement = WINDOWPLACEMENT {
+            length: mem::size_of::<WINDOWPLACEMENT>() as u32,
+            ..Default::default()
+        };
+        GetWindowPlacement(hwnd, &mut placement)?;
+
+        let mut cursor = POINT::default();
+        GetCursorPos(&mut cursor)?;
+        let mut monitor_info = MONITORINFO {
+            cbSize: mem::size_of::<MONITORINFO>() as u32,
+            ..Default::default()
+        };
+        GetMonitorInfoW(
+            MonitorFromPoint(cursor, MONITOR_DEFAULTTONEAREST),
+            &mut monitor_info,
+        )
+        .ok()
+        .context("failed to query the monitor under the cursor")?;
+
+        // note: a maximized window ignores SetWindowPos, so it must be restored first.
+        if placement.showCmd == SW_MAXIMIZE.0 as u32 {
+            ShowWindow(hwnd, SW_RESTORE);
+        }
+        let work = monitor_info.rcWork;
+        let height = (work.bottom - work.top) * height_percent.clamp(10, 100) as i32 / 100;
+        SetWindowPos(
+            hwnd,
+            HWND(0),
+            work.left,
+            work.top,
+            work.right - work.left,
+            height,
+            SWP_NOZORDER | SWP_NOACTIVATE,
+        )?;
+
+        *DOCKED.locked() = Some((hwnd, placement));
+    }
+    Ok(())
+}
+
+/// puts a docked window back where it was before it was summoned, returns whether it was docked.
+pub fn restore(hwnd: HWND) -> Result<bool> {
+    let docked = DOCKED.locked().take_if(|(h, _)| *h == hwnd);
+    let Some((_, placement)) = docked else {
+        return Ok(false);
+    };
+    unsafe { SetWindowPlacement(hwnd, &placement)? };
+    Ok(true)
+}
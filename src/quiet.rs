@@ -0,0 +1,52 @@
+use std::{
+    ffi::c_void,
+    sync::atomic::{AtomicBool, Ordering},
+};
+
+#[allow(unused_imports)]
+use tracing::{debug, error, info, trace, warn};
+use windows::Win32::{
+    Foundation::BOOL,
+    UI::WindowsAndMessaging::{
+        SystemParametersInfoW, SPI_GETCLIENTAREAANIMATION, SYSTEM_PARAMETERS_INFO_UPDATE_FLAGS,
+    },
+};
+
+use crate::LogExt;
+
+/// `quiet` of the config, read from any thread.
+static QUIET: AtomicBool = AtomicBool::new(false);
+
+/// applies the config, e.g. once it was reloaded.
+pub fn configure(quiet: bool) {
+    QUIET.store(quiet, Ordering::Relaxed);
+}
+
+/// whether the notifications nobody asked for are left out and the others shown without a sound,
+/// they're logged either way.
+///
+/// note: what tells about something being broken, e.g. the crash loop, is shown regardless.
+pub fn is_on() -> bool {
+    QUIET.load(Ordering::Relaxed)
+}
+
+/// whether to do without animations, in quiet mode or while "Animation effects" are switched off in
+/// the accessibility settings of Windows.
+pub fn reduces_motion() -> bool {
+    is_on() || !animates()
+}
+
+/// note: read each time, the setting changes without a notification we'd act on.
+fn animates() -> bool {
+    let mut enabled = BOOL(1);
+    unsafe {
+        SystemParametersInfoW(
+            SPI_GETCLIENTAREAANIMATION,
+            0,
+            Some(&mut enabled as *mut BOOL as *mut c_void),
+            SYSTEM_PARAMETERS_INFO_UPDATE_FLAGS(0),
+        )
+    }
+    .warn();
+    enabled.as_bool()
+}
@@ -0,0 +1,152 @@
+use std::{
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Mutex,
+    },
+    thread,
+    time::Duration,
+};
+
+use serde::{Deserialize, Serialize};
+#[allow(unused_imports)]
+use tracing::{debug, error, info, trace, warn};
+use windows::Win32::{
+    Foundation::HWND,
+    UI::{
+        Input::KeyboardAndMouse::GetKeyboardLayout, WindowsAndMessaging::GetWindowThreadProcessId,
+    },
+};
+
+use crate::{config::Config, ime, inject, procs, remote, LockExt, LogExt};
+
+/// adjustments for an IME, matched by its keyboard layout, TSF profile and/or a process it runs.
+///
+/// note: a quirk matches only if every criterion it specifies matches, one without any never does.
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+#[serde(default)]
+pub struct Quirk {
+    pub name: String,
+    /// the HKL of the active keyboard layout, e.g. `0x08040804`.
+    pub hkl: Option<u32>,
+    /// the GUID of the active TSF input profile, e.g. `{FA550B04-5AD7-411F-A5AC-CA038EC515D7}` for
+    /// Microsoft Pinyin.
+    pub profile: Option<String>,
+    /// a process running while the IME is in use, e.g. `SogouCloud.exe`.
+    pub process: Option<String>,
+    /// extra delay before the toggle is injected.
+    pub delay_ms: u64,
+    /// tried before the configured backends.
+    pub backend: Option<inject::Backend>,
+}
+
+/// how the toggle is injected into a window once quirks are applied.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Injection {
+    pub backends: Vec<inject::Backend>,
+    pub delay: Duration,
+}
+
+/// the running processes as of the last `prepare`, so a hotkey doesn't wait for enumerating them.
+static RUNNING: Mutex<Option<Vec<String>>> = Mutex::new(None);
+
+/// whether `prepare` is enumerating the processes already.
+static PREPARING: AtomicBool = AtomicBool::new(false);
+
+thread_local! {
+    static BUILTIN: Vec<Quirk> = builtin();
+}
+
+/// the built-in quirk table, quirks from config take precedence over these.
+fn builtin() -> Vec<Quirk> {
+    vec![
+        Quirk {
+            name: "Sogou Pinyin".into(),
+            process: Some("SogouCloud.exe".into()),
+            delay_ms: 20,
+            ..Default::default()
+        },
+        Quirk {
+            name: "Microsoft Office IME 2010".into(),
+            hkl: Some(0xE020_0411),
+            delay_ms: 30,
+            backend: Some(inject::Backend::SendInput),
+            ..Default::default()
+        },
+    ]
+}
+
+/// enumerates the running processes in the background ahead of the next hotkey, e.g. after the
+/// foreground window changed.
+pub fn prepare() {
+    if PREPARING.swap(true, Ordering::Relaxed) {
+        return;
+    }
+    thread::spawn(|| {
+        let running = procs::running();
+        *RUNNING.locked() = Some(running);
+        PREPARING.store(false, Ordering::Relaxed);
+    });
+}
+
+pub fn resolve(config: &Config, hwnd: HWND) -> Injection {
+    let mut injection = Injection {
+        backends: config.backends.clone(),
+        delay: Duration::ZERO,
+    };
+    if let Some(profile) = remote::profile(config) {
+        debug!("applying the remote session profile {profile:?}");
+        injection.backends = profile
+            .backend
+            .into_iter()
+            .chain(injection.backends)
+            .collect();
+    }
+
+    let hkl = unsafe { GetKeyboardLayout(GetWindowThreadProcessId(hwnd, None)) }.0 as u32;
+    // note: the processes are only enumerated here if `prepare` hasn't finished yet.
+    let mut running = RUNNING.locked();
+    let mut profile = None;
+    let mut matches = |quirk: &Quirk| {
+        (quirk.hkl.is_some() || quirk.profile.is_some() || quirk.process.is_some())
+            && quirk.hkl.is_none_or(|quirk_hkl| quirk_hkl == hkl)
+            && quirk.profile.as_ref().is_none_or(|quirk_profile| {
+                profile
+                    .get_or_insert_with(|| {
+                        ime::active_profile()
+                            .warn()
+                            .flatten()
+                            .map(|guid| format!("{guid:?}"))
+                    })
+                    .as_ref()
+                    .is_some_and(|guid| {
+                        guid.eq_ignore_ascii_case(quirk_profile.trim_matches(['{', '}']))
+                    })
+            })
+            && quirk.process.as_ref().is_none_or(|quirk_process| {
+                running
+                    .get_or_insert_with(procs::running)
+                    .iter()
+                    .any(|name| name.eq_ignore_ascii_case(quirk_process))
+            })
+    };
+
+    BUILTIN.with(|builtin| {
+        let Some(quirk) = config
+            .ime_quirks
+            .iter()
+            .chain(builtin)
+            .find(|quirk| matches(quirk))
+        else {
+            return injection;
+        };
+        debug!("applying IME quirk {:?} for layout {hkl:#010x}", quirk.name);
+        Injection {
+            backends: quirk
+                .backend
+                .into_iter()
+                .chain(injection.backends)
+                .collect(),
+            delay: Duration::from_millis(quirk.delay_ms),
+        }
+    })
+}
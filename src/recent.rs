@@ -0,0 +1,69 @@
+use std::{collections::VecDeque, io, sync::Mutex};
+
+#[allow(unused_imports)]
+use tracing::{debug, error, info, trace, warn};
+use tracing_subscriber::fmt::MakeWriter;
+
+#[cfg(feature = "settings-ui")]
+use crate::viewer;
+use crate::LockExt;
+
+/// how many log events are kept in memory.
+const CAPACITY: usize = 500;
+
+/// the latest log events, oldest first.
+static EVENTS: Mutex<VecDeque<String>> = Mutex::new(VecDeque::new());
+
+#[cfg(feature = "settings-ui")]
+static WINDOW: viewer::Window = viewer::Window::new();
+
+/// keeps the formatted log events in memory, next to the log file.
+pub struct Recent;
+
+impl<'a> MakeWriter<'a> for Recent {
+    type Writer = Event;
+
+    fn make_writer(&'a self) -> Self::Writer {
+        Event(Vec::new())
+    }
+}
+
+/// a log event being formatted, kept once complete.
+pub struct Event(Vec<u8>);
+
+impl io::Write for Event {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.0.extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+impl Drop for Event {
+    fn drop(&mut self) {
+        let text = String::from_utf8_lossy(&self.0);
+        let mut events = EVENTS.locked();
+        for line in text.lines().filter(|line| !line.is_empty()) {
+            if events.len() == CAPACITY {
+                events.pop_front();
+            }
+            events.push_back(line.to_owned());
+        }
+    }
+}
+
+/// the recent log events, oldest first.
+pub fn lines() -> Vec<String> {
+    EVENTS.locked().iter().cloned().collect()
+}
+
+/// shows the recent log events in a window of their own, without blocking the caller.
+///
+/// note: the events are those of the moment it's opened, it's opened again for newer ones.
+#[cfg(feature = "settings-ui")]
+pub fn show() {
+    viewer::show(&WINDOW, "Recent Events", lines);
+}
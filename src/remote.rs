@@ -0,0 +1,45 @@
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+use windows::Win32::UI::WindowsAndMessaging::{GetSystemMetrics, SM_REMOTESESSION};
+
+use crate::{config::Config, inject::Backend};
+
+/// how the toggle is injected in a remote desktop session, where the posted key messages race with
+/// the translation of the remote input.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(default)]
+pub struct Profile {
+    pub enabled: bool,
+    /// tried before the configured backends.
+    pub backend: Option<Backend>,
+    /// the pause between the posted key messages.
+    pub key_delay_ms: u64,
+}
+
+impl Default for Profile {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            backend: Some(Backend::SendInput),
+            key_delay_ms: 15,
+        }
+    }
+}
+
+/// whether we run in a remote desktop session, e.g. RDP.
+pub fn is_remote_session() -> bool {
+    unsafe { GetSystemMetrics(SM_REMOTESESSION) != 0 }
+}
+
+/// the profile if it applies, i.e. it's enabled and we run in a remote desktop session.
+pub fn profile(config: &Config) -> Option<&Profile> {
+    Some(&config.remote_session).filter(|profile| profile.enabled && is_remote_session())
+}
+
+/// the pause between the posted key messages, none outside a remote desktop session.
+pub fn key_delay(config: &Config) -> Duration {
+    profile(config).map_or(Duration::ZERO, |profile| {
+        Duration::from_millis(profile.key_delay_ms)
+    })
+}
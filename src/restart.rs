@@ -0,0 +1,82 @@
+use std::{env, path::Path, process::Command};
+
+use anyhow::{ensure, Context, Result};
+use windows::{
+    core::{w, HSTRING},
+    Win32::{
+        Foundation::HWND,
+        System::Recovery::{RegisterApplicationRestart, RESTART_NO_CRASH, RESTART_NO_HANG},
+        UI::{Shell::ShellExecuteW, WindowsAndMessaging::SW_SHOWNORMAL},
+    },
+};
+
+use crate::LogExt;
+
+/// the `wParam` of `WM_QUIT` asking to start again once everything is cleaned up.
+pub const RELAUNCH: usize = 1;
+/// like `RELAUNCH`, as administrator, e.g. to reach a VSCode running elevated.
+pub const RELAUNCH_ELEVATED: usize = 2;
+
+/// asks Windows to relaunch us after an update restart or an installer closing us, keeping the
+/// paused state via `--paused`.
+///
+/// note: crashes and hangs are left out, relaunching would just repeat them.
+pub fn register(paused: bool) -> Result<()> {
+    let command_line = if paused { "--paused" } else { "" };
+    unsafe {
+        RegisterApplicationRestart(
+            &HSTRING::from(command_line),
+            RESTART_NO_CRASH | RESTART_NO_HANG,
+        )?
+    };
+    Ok(())
+}
+
+/// starts the executable again with our arguments, as administrator if `elevated`.
+///
+/// note: the new instance registers the same hotkeys and checks the single instance lock, so ours
+/// must be released first. if the user declines elevating it, it's started as before, we're gone
+/// already.
+pub fn relaunch(app_path: &Path, elevated: bool) -> Result<()> {
+    if elevated && relaunch_elevated(app_path).warn().is_some() {
+        return Ok(());
+    }
+    Command::new(app_path)
+        .args(env::args_os().skip(1))
+        .spawn()
+        .with_context(|| format!("failed to relaunch {app_path:?}"))?;
+    Ok(())
+}
+
+fn relaunch_elevated(app_path: &Path) -> Result<()> {
+    let arguments: Vec<_> = env::args_os()
+        .skip(1)
+        .map(|arg| quote(&arg.to_string_lossy()))
+        .collect();
+    let instance = unsafe {
+        ShellExecuteW(
+            HWND(0),
+            w!("runas"),
+            &HSTRING::from(app_path.to_string_lossy().as_ref()),
+            &HSTRING::from(arguments.join(" ")),
+            None,
+            SW_SHOWNORMAL,
+        )
+    };
+    // note: values up to 32 are errors.
+    ensure!(
+        instance.0 > 32,
+        "failed to relaunch {app_path:?} as administrator: {}",
+        windows::core::Error::from_win32()
+    );
+    Ok(())
+}
+
+/// quotes an argument with spaces for the command line, ours never contain quotes.
+fn quote(arg: &str) -> String {
+    if arg.is_empty() || arg.contains([' ', '\t']) {
+        format!("\"{arg}\"")
+    } else {
+        arg.to_owned()
+    }
+}
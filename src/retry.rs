@@ -0,0 +1,102 @@
+use std::{
+    cell::{Cell, RefCell},
+    thread,
+    time::{Duration, Instant},
+};
+
+#[allow(unused_imports)]
+use tracing::{debug, error, info, trace, warn};
+use windows::Win32::{
+    Foundation::{HWND, LPARAM, WPARAM},
+    System::Threading::GetCurrentThreadId,
+    UI::WindowsAndMessaging::{PostThreadMessageW, WM_APP},
+};
+
+use crate::{hotkey::Hotkey, summary, window, LogExt};
+
+/// posted to the message pump with the generation of the press in `wParam` once the busy window
+/// responds again, `lParam` is 0 if it didn't in time.
+pub const WM_RETRY: u32 = WM_APP + 11;
+
+/// how often the busy window is asked whether it responds again.
+const POLL_INTERVAL: Duration = Duration::from_millis(50);
+
+struct Pending {
+    generation: usize,
+    hwnd: HWND,
+    hotkey: Hotkey,
+}
+
+thread_local! {
+    static PENDING: RefCell<Option<Pending>> = const { RefCell::new(None) };
+    static GENERATION: Cell<usize> = const { Cell::new(0) };
+}
+
+/// retries the hotkey on the busy window as soon as it responds again within `timeout`.
+///
+/// note: only the latest press is kept, replaying every press made while waiting would toggle the
+/// terminal back and forth.
+pub fn schedule(hwnd: HWND, hotkey: Hotkey, timeout: Duration) {
+    let generation = GENERATION.with(|generation| {
+        generation.set(generation.get().wrapping_add(1));
+        generation.get()
+    });
+    let replaced = PENDING.with(|pending| {
+        pending.borrow_mut().replace(Pending {
+            generation,
+            hwnd,
+            hotkey,
+        })
+    });
+    if let Some(replaced) = replaced {
+        debug!("replaced the pending {:?}", replaced.hotkey);
+    }
+
+    let tid = unsafe { GetCurrentThreadId() };
+    thread::spawn(move || {
+        let deadline = Instant::now() + timeout;
+        let responsive = loop {
+            thread::sleep(POLL_INTERVAL);
+            if window::is_responsive(hwnd) {
+                break true;
+            }
+            if Instant::now() >= deadline {
+                break false;
+            }
+        };
+        unsafe {
+            PostThreadMessageW(
+                tid,
+                WM_RETRY,
+                WPARAM(generation),
+                LPARAM(responsive as isize),
+            )
+        }
+        .warn();
+    });
+}
+
+/// the window and the hotkey to retry, `None` if a later press replaced it or the window didn't
+/// respond in time.
+pub fn finish(generation: usize, responsive: bool) -> Option<(HWND, Hotkey)> {
+    let pending = PENDING.with(|pending| {
+        let mut pending = pending.borrow_mut();
+        match &*pending {
+            Some(current) if current.generation == generation => pending.take(),
+            _ => None,
+        }
+    })?;
+    if !responsive {
+        warn!(
+            "dropped {:?}, {:?} didn't respond in time",
+            pending.hotkey, pending.hwnd
+        );
+        summary::record_failure(summary::NOT_RESPONDING);
+        return None;
+    }
+    info!(
+        "retrying {:?} now that {:?} responds again",
+        pending.hotkey, pending.hwnd
+    );
+    Some((pending.hwnd, pending.hotkey))
+}
@@ -0,0 +1,76 @@
+use std::path::Path;
+
+use anyhow::{Context, Result};
+
+use crate::{config::Config, gesture, hotkey, tray::Menu};
+
+/// a rule of the config file by its place, e.g. the second of `chords`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Rule {
+    Chord(usize),
+    Gesture(usize),
+}
+
+/// a rule is enabled unless it was switched off.
+pub fn enabled() -> bool {
+    true
+}
+
+pub fn is_enabled(enabled: &bool) -> bool {
+    *enabled
+}
+
+/// whether there's any rule to list.
+pub fn any(config: &Config) -> bool {
+    !config.chords.is_empty() || !config.gestures.is_empty()
+}
+
+/// the "Rules" submenu, the chords and then the gestures, each checked while it's enabled.
+///
+/// note: the keys are the shortcut text, aligned to the right like an accelerator.
+pub fn menu<E>(config: &Config, event: impl Fn(Rule) -> E) -> Menu<E> {
+    let menu = config
+        .chords
+        .iter()
+        .enumerate()
+        .fold(Menu::default(), |menu, (i, rule)| {
+            let keys: Vec<_> = rule.keys.iter().map(ToString::to_string).collect();
+            let label = format!("{}\t{}", rule.action.command(), keys.join(" "));
+            menu.checkable(&label, rule.enabled, event(Rule::Chord(i)))
+        });
+    let menu = if config.chords.is_empty() || config.gestures.is_empty() {
+        menu
+    } else {
+        menu.separator()
+    };
+    config
+        .gestures
+        .iter()
+        .enumerate()
+        .fold(menu, |menu, (i, rule)| {
+            let label = format!(
+                "{}\tDouble-Tap {:?}",
+                rule.action.command(),
+                rule.double_tap
+            );
+            menu.checkable(&label, rule.enabled, event(Rule::Gesture(i)))
+        })
+}
+
+/// switches the rule on or off in the config file, keeping the user's other keys and comments.
+pub fn set(path: &Path, rule: Rule, enabled: bool) -> Result<()> {
+    let config = Config::load(path)?;
+    let values = match rule {
+        Rule::Chord(i) => {
+            let mut chords = config.chords;
+            chords.get_mut(i).context("the rule was removed")?.enabled = enabled;
+            hotkey::chords_value(&chords)
+        }
+        Rule::Gesture(i) => {
+            let mut gestures = config.gestures;
+            gestures.get_mut(i).context("the rule was removed")?.enabled = enabled;
+            gesture::gestures_value(&gestures)
+        }
+    };
+    Config::update(path, &values).with_context(|| format!("failed to switch {rule:?}"))
+}
@@ -0,0 +1,120 @@
+use std::cell::RefCell;
+
+use anyhow::Result;
+use windows::Win32::{
+    Foundation::{HINSTANCE, LPARAM, LRESULT, WPARAM},
+    System::Threading::GetCurrentThreadId,
+    UI::WindowsAndMessaging::{
+        CallNextHookEx, PostThreadMessageW, SetWindowsHookExW, UnhookWindowsHookEx, HHOOK,
+        KBDLLHOOKSTRUCT, LLKHF_EXTENDED, LLKHF_INJECTED, WH_KEYBOARD_LL, WM_HOTKEY, WM_KEYDOWN,
+        WM_SYSKEYDOWN,
+    },
+};
+
+use crate::{
+    chord,
+    hotkey::{Hotkey, HotkeyBackend},
+    inject::SCANCODE_OEM_3,
+    LogExt,
+};
+
+/// recognizes the hotkeys on the physical key left of 「1」 by its scan code, whatever virtual key
+/// the keyboard layout maps it to, via a keyboard hook posting `WM_HOTKEY` like a registered
+/// hotkey.
+pub struct Hook;
+
+struct Installed {
+    hook: HHOOK,
+    hotkeys: Vec<Hotkey>,
+    /// whether the key presses go through to the focused window untouched.
+    passthrough: bool,
+}
+
+thread_local! {
+    static HOOK: RefCell<Option<Installed>> = const { RefCell::new(None) };
+}
+
+impl HotkeyBackend for Hook {
+    /// note: no hook is installed without hotkeys, it costs every key press of the session.
+    fn register(&self, hotkeys: Vec<Hotkey>) -> Result<()> {
+        if hotkeys.is_empty() {
+            return Ok(());
+        }
+        let installed = HOOK.with(|cell| {
+            let mut cell = cell.borrow_mut();
+            let installed = cell.as_mut()?;
+            installed.hotkeys.extend(&hotkeys);
+            Some(())
+        });
+        if installed.is_none() {
+            let hook = unsafe { SetWindowsHookExW(WH_KEYBOARD_LL, Some(on_key), HINSTANCE(0), 0)? };
+            HOOK.with(|cell| {
+                *cell.borrow_mut() = Some(Installed {
+                    hook,
+                    hotkeys,
+                    passthrough: false,
+                })
+            });
+        }
+        Ok(())
+    }
+
+    fn unregister(&self) {
+        if let Some(installed) = HOOK.with(|cell| cell.borrow_mut().take()) {
+            unsafe { UnhookWindowsHookEx(installed.hook) }.warn();
+        }
+    }
+
+    fn set_passthrough(&self, passthrough: bool) {
+        HOOK.with(|cell| {
+            if let Some(installed) = cell.borrow_mut().as_mut() {
+                installed.passthrough = passthrough;
+            }
+        });
+    }
+
+    fn find(&self, id: usize) -> Option<Hotkey> {
+        HOOK.with(|cell| {
+            cell.borrow()
+                .as_ref()?
+                .hotkeys
+                .iter()
+                .find(|hotkey| hotkey.id == id)
+                .copied()
+        })
+    }
+
+    fn count(&self) -> usize {
+        HOOK.with(|cell| {
+            cell.borrow()
+                .as_ref()
+                .map_or(0, |installed| installed.hotkeys.len())
+        })
+    }
+}
+
+unsafe extern "system" fn on_key(code: i32, wparam: WPARAM, lparam: LPARAM) -> LRESULT {
+    if code >= 0 && matches!(wparam.0 as u32, WM_KEYDOWN | WM_SYSKEYDOWN) {
+        let info = &*(lparam.0 as *const KBDLLHOOKSTRUCT);
+        // note: our own injected keys carry the same scan code, they mustn't trigger again.
+        if info.scanCode == SCANCODE_OEM_3 as u32
+            && (info.flags.0 & (LLKHF_INJECTED.0 | LLKHF_EXTENDED.0)) == 0
+        {
+            let modifiers = chord::held_modifiers();
+            let id = HOOK.with(|cell| {
+                let cell = cell.borrow();
+                let installed = cell.as_ref().filter(|installed| !installed.passthrough)?;
+                installed
+                    .hotkeys
+                    .iter()
+                    .find(|hotkey| hotkey.chord.modifiers == modifiers)
+                    .map(|hotkey| hotkey.id)
+            });
+            if let Some(id) = id {
+                PostThreadMessageW(GetCurrentThreadId(), WM_HOTKEY, WPARAM(id), LPARAM(0)).warn();
+                return LRESULT(1);
+            }
+        }
+    }
+    CallNextHookEx(HHOOK(0), code, wparam, lparam)
+}
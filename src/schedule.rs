@@ -0,0 +1,252 @@
+use std::{
+    fmt,
+    str::FromStr,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Mutex,
+    },
+    time::Duration,
+};
+
+use anyhow::{ensure, Context, Result};
+use serde::{Deserialize, Serialize};
+#[allow(unused_imports)]
+use tracing::{debug, error, info, trace, warn};
+use windows::Win32::{
+    Foundation::WPARAM, System::SystemInformation::GetLocalTime, UI::WindowsAndMessaging::WM_APP,
+};
+
+use crate::{timer, LockExt};
+
+/// sent to the message pump by the timer to check whether the active hours began or ended.
+pub const WM_SCHEDULE: u32 = WM_APP + 16;
+
+/// how often the timer checks the clock, the hotkeys turn on or off within this long.
+const CHECK_INTERVAL: Duration = Duration::from_secs(20);
+
+/// when the hotkeys are active, e.g. `{ from = "09:00", to = "19:00", days = ["mon", "tue"] }`.
+///
+/// note: hours ending before they begin span midnight, e.g. 22:00 to 02:00, and equal ones the
+/// whole day, the days are those the current time falls on.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+pub struct ActiveHours {
+    pub from: TimeOfDay,
+    pub to: TimeOfDay,
+    #[serde(default = "every_day")]
+    pub days: Vec<Weekday>,
+}
+
+fn every_day() -> Vec<Weekday> {
+    WEEKDAYS.to_vec()
+}
+
+/// minutes since midnight, written as `HH:MM`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Deserialize, Serialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct TimeOfDay(u16);
+
+impl FromStr for TimeOfDay {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let (hours, minutes) = s
+            .split_once(':')
+            .with_context(|| format!("{s:?} isn't HH:MM"))?;
+        let hours: u16 = hours
+            .parse()
+            .with_context(|| format!("{s:?} isn't HH:MM"))?;
+        let minutes: u16 = minutes
+            .parse()
+            .with_context(|| format!("{s:?} isn't HH:MM"))?;
+        // note: 24:00 ends the day.
+        ensure!(
+            minutes < 60 && (hours < 24 || (hours, minutes) == (24, 0)),
+            "{s:?} isn't a time of day"
+        );
+        Ok(Self(hours * 60 + minutes))
+    }
+}
+
+impl TryFrom<String> for TimeOfDay {
+    type Error = anyhow::Error;
+
+    fn try_from(s: String) -> Result<Self> {
+        s.parse()
+    }
+}
+
+impl fmt::Display for TimeOfDay {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:02}:{:02}", self.0 / 60, self.0 % 60)
+    }
+}
+
+impl From<TimeOfDay> for String {
+    fn from(time: TimeOfDay) -> Self {
+        time.to_string()
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Weekday {
+    Mon,
+    Tue,
+    Wed,
+    Thu,
+    Fri,
+    Sat,
+    Sun,
+}
+
+/// in the order of `SYSTEMTIME::wDayOfWeek`, which starts on Sunday.
+const WEEKDAYS: [Weekday; 7] = [
+    Weekday::Sun,
+    Weekday::Mon,
+    Weekday::Tue,
+    Weekday::Wed,
+    Weekday::Thu,
+    Weekday::Fri,
+    Weekday::Sat,
+];
+
+impl ActiveHours {
+    fn contains(&self, weekday: Weekday, time: TimeOfDay) -> bool {
+        self.days.contains(&weekday)
+            && match self.from.cmp(&self.to) {
+                std::cmp::Ordering::Less => self.from <= time && time < self.to,
+                std::cmp::Ordering::Equal => true,
+                std::cmp::Ordering::Greater => time >= self.from || time < self.to,
+            }
+    }
+}
+
+/// the configured active hours, checked by the timer.
+///
+/// note: the status is reported from other threads, so it's shared although only the message pump
+/// changes it.
+static HOURS: Mutex<Option<ActiveHours>> = Mutex::new(None);
+
+/// whether the hotkeys are dormant outside the active hours right now.
+static OFF: AtomicBool = AtomicBool::new(false);
+
+/// applies the active hours right away, e.g. after the config was reloaded, the caller registers
+/// the hotkeys accordingly.
+pub fn configure(hours: Option<ActiveHours>) {
+    let mut configured = HOURS.locked();
+    OFF.store(is_outside(hours.as_ref()), Ordering::Relaxed);
+    *configured = hours;
+}
+
+/// whether the hotkeys are dormant outside the active hours.
+pub fn is_off() -> bool {
+    OFF.load(Ordering::Relaxed)
+}
+
+fn is_outside(hours: Option<&ActiveHours>) -> bool {
+    let Some(hours) = hours else {
+        return false;
+    };
+    let now = unsafe { GetLocalTime() };
+    let weekday = WEEKDAYS[now.wDayOfWeek as usize % WEEKDAYS.len()];
+    !hours.contains(weekday, TimeOfDay(now.wHour * 60 + now.wMinute))
+}
+
+/// starts the timer of the calling thread, which gets `WM_SCHEDULE` to `check` the clock.
+pub fn watch() -> Result<timer::Handle> {
+    timer::every(CHECK_INTERVAL, WM_SCHEDULE, WPARAM(0))
+}
+
+/// whether the active hours began or ended since the last check.
+pub fn check() -> bool {
+    let hours = HOURS.locked();
+    let off = is_outside(hours.as_ref());
+    OFF.swap(off, Ordering::Relaxed) != off
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn hours(from: &str, to: &str, days: &[Weekday]) -> ActiveHours {
+        ActiveHours {
+            from: from.parse().unwrap(),
+            to: to.parse().unwrap(),
+            days: days.to_vec(),
+        }
+    }
+
+    fn at(time: &str) -> TimeOfDay {
+        time.parse().unwrap()
+    }
+
+    #[test]
+    fn time_of_day_parses_hh_mm() {
+        assert_eq!(at("00:00"), TimeOfDay(0));
+        assert_eq!(at("09:30"), TimeOfDay(570));
+        assert_eq!(at("23:59").to_string(), "23:59");
+        assert_eq!(at("24:00"), TimeOfDay(24 * 60));
+        for invalid in [
+            "", "9", "9:", ":30", "24:01", "25:00", "12:60", "-1:00", "a:b", "12:30:00",
+        ] {
+            assert!(invalid.parse::<TimeOfDay>().is_err(), "{invalid:?}");
+        }
+    }
+
+    #[test]
+    fn hours_within_a_day() {
+        let hours = hours("09:00", "19:00", &WEEKDAYS);
+        assert!(!hours.contains(Weekday::Mon, at("08:59")));
+        assert!(hours.contains(Weekday::Mon, at("09:00")));
+        assert!(hours.contains(Weekday::Mon, at("18:59")));
+        assert!(!hours.contains(Weekday::Mon, at("19:00")));
+    }
+
+    #[test]
+    fn hours_span_midnight() {
+        let hours = hours("22:00", "02:00", &WEEKDAYS);
+        assert!(hours.contains(Weekday::Mon, at("22:00")));
+        assert!(hours.contains(Weekday::Mon, at("23:59")));
+        assert!(hours.contains(Weekday::Tue, at("00:00")));
+        assert!(hours.contains(Weekday::Tue, at("01:59")));
+        assert!(!hours.contains(Weekday::Tue, at("02:00")));
+        assert!(!hours.contains(Weekday::Tue, at("21:59")));
+    }
+
+    #[test]
+    fn hours_until_24_00_end_at_midnight() {
+        let hours = hours("18:00", "24:00", &WEEKDAYS);
+        assert!(hours.contains(Weekday::Mon, at("23:59")));
+        assert!(!hours.contains(Weekday::Mon, at("00:00")));
+        assert!(!hours.contains(Weekday::Mon, at("17:59")));
+    }
+
+    #[test]
+    fn equal_hours_are_the_whole_day() {
+        let hours = hours("08:00", "08:00", &[Weekday::Sat]);
+        assert!(hours.contains(Weekday::Sat, at("00:00")));
+        assert!(hours.contains(Weekday::Sat, at("07:59")));
+        assert!(hours.contains(Weekday::Sat, at("23:59")));
+        assert!(!hours.contains(Weekday::Sun, at("08:00")));
+    }
+
+    #[test]
+    fn days_are_those_the_time_falls_on() {
+        // note: the early hours of Saturday belong to Saturday, not to Friday's span.
+        let hours = hours("22:00", "02:00", &[Weekday::Fri]);
+        assert!(hours.contains(Weekday::Fri, at("23:00")));
+        assert!(hours.contains(Weekday::Fri, at("01:00")));
+        assert!(!hours.contains(Weekday::Sat, at("01:00")));
+        assert!(!hours.contains(Weekday::Thu, at("23:00")));
+    }
+
+    #[test]
+    fn days_default_to_every_day() {
+        let parse = toml::from_str::<ActiveHours>;
+        let hours = parse("from = '09:00'\nto = '17:00'").unwrap();
+        assert_eq!(hours.days, WEEKDAYS);
+        let hours = parse("from = '09:00'\nto = '17:00'\ndays = ['mon', 'sun']").unwrap();
+        assert_eq!(hours.days, [Weekday::Mon, Weekday::Sun]);
+        assert!(parse("from = '9'\nto = '17:00'").is_err());
+    }
+}
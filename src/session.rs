@@ -0,0 +1,121 @@
+use std::cell::Cell;
+
+use anyhow::{ensure, Result};
+#[allow(unused_imports)]
+use tracing::{debug, error, info, trace, warn};
+use windows::{
+    core::{w, PCWSTR},
+    Win32::{
+        Foundation::{HANDLE, HWND, LPARAM, LRESULT, WPARAM},
+        System::{
+            LibraryLoader::GetModuleHandleW,
+            Power::{
+                RegisterSuspendResumeNotification, UnregisterSuspendResumeNotification,
+                HPOWERNOTIFY,
+            },
+            RemoteDesktop::{
+                WTSRegisterSessionNotification, WTSUnRegisterSessionNotification,
+                NOTIFY_FOR_THIS_SESSION,
+            },
+            Threading::GetCurrentThreadId,
+        },
+        UI::WindowsAndMessaging::{
+            CreateWindowExW, DefWindowProcW, DestroyWindow, PostThreadMessageW, RegisterClassW,
+            DEVICE_NOTIFY_WINDOW_HANDLE, HMENU, HWND_MESSAGE, PBT_APMRESUMEAUTOMATIC,
+            PBT_APMSUSPEND, WINDOW_EX_STYLE, WINDOW_STYLE, WM_APP, WM_POWERBROADCAST,
+            WM_WTSSESSION_CHANGE, WNDCLASSW,
+        },
+    },
+};
+
+use crate::LogExt;
+
+/// posted to the message pump with the `WTS_*` session change in `wParam`.
+pub const WM_SESSION: u32 = WM_APP + 5;
+
+/// posted to the message pump with `PBT_APMSUSPEND` or `PBT_APMRESUMEAUTOMATIC` in `wParam`.
+pub const WM_POWER: u32 = WM_APP + 21;
+
+thread_local! {
+    static POWER: Cell<HPOWERNOTIFY> = const { Cell::new(HPOWERNOTIFY(0)) };
+}
+
+/// receives the session change notifications of the current session, e.g. lock and unlock, and
+/// the computer going to sleep and waking up.
+///
+/// note: the notifications need a window, so a message-only one is created on the calling thread.
+pub fn watch() -> Result<HWND> {
+    unsafe {
+        let instance = GetModuleHandleW(PCWSTR::null())?;
+        let class = WNDCLASSW {
+            lpfnWndProc: Some(on_message),
+            hInstance: instance.into(),
+            lpszClassName: w!("vscode-cjk-toggle-terminal-fixer-session"),
+            ..Default::default()
+        };
+        ensure!(
+            RegisterClassW(&class) != 0,
+            "failed to register the window class: {}",
+            windows::core::Error::from_win32()
+        );
+        let hwnd = CreateWindowExW(
+            WINDOW_EX_STYLE(0),
+            class.lpszClassName,
+            PCWSTR::null(),
+            WINDOW_STYLE(0),
+            0,
+            0,
+            0,
+            0,
+            HWND_MESSAGE,
+            HMENU(0),
+            instance,
+            None,
+        );
+        ensure!(
+            hwnd != HWND(0),
+            "failed to create the message window: {}",
+            windows::core::Error::from_win32()
+        );
+        WTSRegisterSessionNotification(hwnd, NOTIFY_FOR_THIS_SESSION)?;
+        if let Some(power) =
+            RegisterSuspendResumeNotification(HANDLE(hwnd.0), DEVICE_NOTIFY_WINDOW_HANDLE).warn()
+        {
+            POWER.set(power);
+        }
+        Ok(hwnd)
+    }
+}
+
+pub fn unwatch(hwnd: HWND) {
+    unsafe {
+        WTSUnRegisterSessionNotification(hwnd).warn();
+        let power = POWER.replace(HPOWERNOTIFY(0));
+        if power.0 != 0 {
+            UnregisterSuspendResumeNotification(power).warn();
+        }
+        DestroyWindow(hwnd).warn();
+    }
+}
+
+unsafe extern "system" fn on_message(
+    hwnd: HWND,
+    msg: u32,
+    wparam: WPARAM,
+    lparam: LPARAM,
+) -> LRESULT {
+    if msg == WM_WTSSESSION_CHANGE {
+        debug!("session change: {}", wparam.0);
+        // note: the message pump owns the config and the hotkeys, so it handles the change.
+        PostThreadMessageW(GetCurrentThreadId(), WM_SESSION, wparam, LPARAM(0)).warn();
+        return LRESULT(0);
+    }
+    if msg == WM_POWERBROADCAST
+        && matches!(wparam.0 as u32, PBT_APMSUSPEND | PBT_APMRESUMEAUTOMATIC)
+    {
+        debug!("power change: {}", wparam.0);
+        PostThreadMessageW(GetCurrentThreadId(), WM_POWER, wparam, LPARAM(0)).warn();
+        return LRESULT(1);
+    }
+    DefWindowProcW(hwnd, msg, wparam, lparam)
+}
@@ -0,0 +1,126 @@
+use std::{
+    collections::VecDeque,
+    fs,
+    path::PathBuf,
+    sync::Mutex,
+    thread,
+    time::{Duration, Instant},
+};
+
+use anyhow::{Context, Result};
+#[allow(unused_imports)]
+use tracing::{debug, error, info, trace, warn};
+use windows::{
+    core::HSTRING,
+    Win32::{
+        Foundation::HWND,
+        System::SystemInformation::GetLocalTime,
+        UI::WindowsAndMessaging::{MessageBoxW, MB_ICONWARNING, MB_OK},
+    },
+};
+
+use crate::{archive, diagnostics, errors, recent, status, LockExt, PACKAGE_NAME};
+
+/// the failures have to happen within this long to trigger a snapshot.
+const FAILURE_WINDOW: Duration = Duration::from_secs(60);
+
+struct Snapshots {
+    /// how many failures within `FAILURE_WINDOW` trigger a snapshot, 0 never does.
+    threshold: usize,
+    config_path: PathBuf,
+    log_path: PathBuf,
+    /// the times of the latest failures, oldest first.
+    failures: VecDeque<Instant>,
+}
+
+static SNAPSHOTS: Mutex<Option<Snapshots>> = Mutex::new(None);
+
+/// where the snapshots go and how many failures trigger one, e.g. after the config was reloaded.
+pub fn configure(threshold: usize, config_path: PathBuf, log_path: PathBuf) {
+    let mut snapshots = SNAPSHOTS.locked();
+    let failures = snapshots
+        .take()
+        .map(|snapshots| snapshots.failures)
+        .unwrap_or_default();
+    *snapshots = Some(Snapshots {
+        threshold,
+        config_path,
+        log_path,
+        failures,
+    });
+}
+
+/// counts a failed hotkey and writes a snapshot in the background once too many failed within a
+/// minute.
+///
+/// note: the count starts over after a snapshot, so a persistent failure writes one a minute at most.
+pub fn record_failure() {
+    let mut snapshots = SNAPSHOTS.locked();
+    let Some(snapshots) = snapshots
+        .as_mut()
+        .filter(|snapshots| snapshots.threshold > 0)
+    else {
+        return;
+    };
+    let now = Instant::now();
+    snapshots.failures.push_back(now);
+    while snapshots
+        .failures
+        .front()
+        .is_some_and(|&failure| now.duration_since(failure) > FAILURE_WINDOW)
+    {
+        snapshots.failures.pop_front();
+    }
+    if snapshots.failures.len() < snapshots.threshold {
+        return;
+    }
+    snapshots.failures.clear();
+
+    let (config_path, log_path) = (snapshots.config_path.clone(), snapshots.log_path.clone());
+    thread::spawn(move || {
+        let text = match write(config_path, log_path) {
+            Ok(path) => format!(
+                "Several hotkeys failed within a minute, the diagnostics were saved to {}. Attach it when reporting the problem.",
+                path.display()
+            ),
+            Err(err) => errors::describe(&err),
+        };
+        unsafe {
+            MessageBoxW(
+                HWND(0),
+                &HSTRING::from(text),
+                &HSTRING::from(PACKAGE_NAME),
+                MB_OK | MB_ICONWARNING,
+            )
+        };
+    });
+}
+
+/// zips the report, the status, the recent events and the whole log next to the log, e.g.
+/// `…-diagnostics-20240501-173000.zip`.
+fn write(config_path: PathBuf, log_path: PathBuf) -> Result<PathBuf> {
+    let time = unsafe { GetLocalTime() };
+    let path = log_path.with_file_name(format!(
+        "{PACKAGE_NAME}-diagnostics-{:04}{:02}{:02}-{:02}{:02}{:02}.zip",
+        time.wYear, time.wMonth, time.wDay, time.wHour, time.wMinute, time.wSecond
+    ));
+    let status = status::LINES
+        .into_iter()
+        .map(|line| line.label(false, Duration::ZERO))
+        .collect::<Vec<_>>()
+        .join("\n");
+    let log = fs::read(&log_path).unwrap_or_default();
+    let entries = [
+        (
+            "report.md",
+            diagnostics::report(&config_path, &log_path).into_bytes(),
+        ),
+        ("status.txt", status.into_bytes()),
+        ("recent.log", recent::lines().join("\n").into_bytes()),
+        ("full.log", log),
+    ];
+    fs::write(&path, archive::zip(&entries, &time))
+        .with_context(|| format!("failed to write diagnostics: {path:?}"))?;
+    info!("saved diagnostics to {path:?}");
+    Ok(path)
+}
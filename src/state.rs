@@ -0,0 +1,48 @@
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+
+use crate::{inject::Action, summary::Week, telemetry::Usage};
+
+/// what we remember across restarts, unlike the config it's written by us only.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct State {
+    pub paused: bool,
+    /// how often the hotkey of each action was pressed.
+    pub triggers: BTreeMap<Action, u64>,
+    /// the counts for the next usage report.
+    pub usage: Usage,
+    /// the counts for the next weekly summary.
+    pub summary: Week,
+    /// the conflicting tools the user was told about already, each is shown once.
+    pub warned_conflicts: BTreeSet<String>,
+    /// the VSCode version installed when last checked, to notice an update.
+    pub vscode_version: Option<String>,
+}
+
+impl State {
+    /// the state file lives next to the executable, e.g. `vscode-cjk-toggle-terminal-fixer.state.toml`.
+    pub fn path(app_path: &Path) -> PathBuf {
+        app_path.with_extension("state.toml")
+    }
+
+    /// a missing state file is not an error, e.g. on the first run.
+    pub fn load(path: &Path) -> Result<Self> {
+        match fs::read_to_string(path) {
+            Ok(text) => toml::from_str(&text).with_context(|| format!("invalid state: {path:?}")),
+            Err(err) if err.kind() == io::ErrorKind::NotFound => Ok(Self::default()),
+            Err(err) => Err(err).with_context(|| format!("failed to read state: {path:?}")),
+        }
+    }
+
+    pub fn save(&self, path: &Path) -> Result<()> {
+        let text = toml::to_string(self)?;
+        fs::write(path, text).with_context(|| format!("failed to write state: {path:?}"))
+    }
+}
@@ -0,0 +1,249 @@
+#[cfg(feature = "ipc")]
+use std::io::{self, Write};
+use std::{
+    fmt,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Mutex,
+    },
+    time::Duration,
+};
+
+#[cfg(feature = "ipc")]
+use anyhow::{ensure, Context, Result};
+use serde::{Deserialize, Serialize};
+use windows::Win32::UI::Input::Ime::ImmGetDescriptionW;
+
+#[cfg(feature = "ipc")]
+use crate::ipc;
+use crate::{
+    hotkey,
+    inject::{Action, Backend},
+    LockExt, PACKAGE_VERSION,
+};
+
+/// what the message pump last did, shown read-only in the "Status" submenu of the tray.
+#[derive(Debug, Clone)]
+pub struct Status {
+    pub hotkeys: usize,
+    pub backend: Option<Backend>,
+    /// the executable of the window the last hotkey was sent to, if it had a target.
+    pub target: Option<String>,
+    pub paused: bool,
+    /// the configured `target_processes`.
+    pub targets: Vec<String>,
+    pub last_trigger: Option<LastTrigger>,
+    /// `None` if we can't be launched on logon, e.g. a build without the "autolaunch" feature.
+    pub autostart: Option<Autostart>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LastTrigger {
+    pub action: Action,
+    /// in ISO 8601 and UTC, e.g. `2024-05-01T08:30:00.123Z`.
+    pub at: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Autostart {
+    /// "run_key" or "startup_task".
+    pub backend: String,
+    pub enabled: bool,
+}
+
+static STATUS: Mutex<Status> = Mutex::new(Status {
+    hotkeys: 0,
+    backend: None,
+    target: None,
+    paused: false,
+    targets: Vec::new(),
+    last_trigger: None,
+    autostart: None,
+});
+
+/// whether the status changed since the tray was last told.
+static CHANGED: AtomicBool = AtomicBool::new(false);
+
+pub fn update(f: impl FnOnce(&mut Status)) {
+    f(&mut STATUS.locked());
+    CHANGED.store(true, Ordering::Relaxed);
+}
+
+pub fn get() -> Status {
+    STATUS.locked().clone()
+}
+
+/// whether the status changed since the last call, so the tray should refresh the submenu.
+pub fn take_changed() -> bool {
+    CHANGED.swap(false, Ordering::Relaxed)
+}
+
+/// a line of the "Status" submenu.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Line {
+    Hotkeys,
+    Backend,
+    Target,
+    Layout,
+    Paused,
+    Uptime,
+}
+
+pub const LINES: [Line; 6] = [
+    Line::Hotkeys,
+    Line::Backend,
+    Line::Target,
+    Line::Layout,
+    Line::Paused,
+    Line::Uptime,
+];
+
+/// how often the lines are refreshed while nothing happens, for the uptime.
+pub const REFRESH_INTERVAL: Duration = Duration::from_secs(60);
+
+impl Line {
+    pub fn label(self, paused: bool, uptime: Duration) -> String {
+        let status = get();
+        let yes_no = |yes| if yes { "yes" } else { "no" };
+        match self {
+            Self::Hotkeys => format!(
+                "Hotkeys Registered: {} ({})",
+                yes_no(status.hotkeys > 0),
+                status.hotkeys
+            ),
+            Self::Backend => match status.backend {
+                Some(backend) => format!("Last Backend: {backend:?}"),
+                None => "Last Backend: none".to_owned(),
+            },
+            Self::Target => format!(
+                "Last Target: {}",
+                status.target.as_deref().unwrap_or("none")
+            ),
+            Self::Layout => format!("Keyboard Layout: {}", layout()),
+            Self::Paused => format!("Paused: {}", yes_no(paused)),
+            Self::Uptime => {
+                let minutes = uptime.as_secs() / 60;
+                format!("Uptime: {}h {}m", minutes / 60, minutes % 60)
+            }
+        }
+    }
+}
+
+/// the keyboard layout of the foreground window, with the name of its IME if it's an IMM one.
+///
+/// note: TSF based IMEs, e.g. Microsoft Pinyin, have no IMM description.
+fn layout() -> String {
+    let hkl = hotkey::foreground_layout();
+    let mut description = [0u16; 128];
+    let len = unsafe { ImmGetDescriptionW(hkl, Some(&mut description)) } as usize;
+    match String::from_utf16_lossy(&description[..len.min(description.len())]) {
+        name if name.is_empty() => format!("{:#010x}", hkl.0 as u32),
+        name => format!("{:#010x} ({name})", hkl.0 as u32),
+    }
+}
+
+/// the document `--status --json` prints, keys are only ever added so scripts keep working.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Report {
+    pub version: String,
+    pub running: bool,
+    pub paused: bool,
+    pub registered_hotkeys: usize,
+    /// executables whose windows receive the hotkeys besides VSCode's.
+    pub targets: Vec<String>,
+    pub last_target: Option<String>,
+    pub last_backend: Option<Backend>,
+    pub last_trigger: Option<LastTrigger>,
+    pub autostart: Option<Autostart>,
+}
+
+#[cfg(feature = "ipc")]
+impl Report {
+    fn not_running() -> Self {
+        Self {
+            version: PACKAGE_VERSION.to_owned(),
+            ..Default::default()
+        }
+    }
+}
+
+impl fmt::Display for Report {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let yes_no = |yes| if yes { "yes" } else { "no" };
+        writeln!(f, "Version: {}", self.version)?;
+        writeln!(f, "Running: {}", yes_no(self.running))?;
+        if !self.running {
+            return Ok(());
+        }
+        writeln!(f, "Paused: {}", yes_no(self.paused))?;
+        writeln!(f, "Hotkeys Registered: {}", self.registered_hotkeys)?;
+        let mut targets = vec!["VSCode"];
+        targets.extend(self.targets.iter().map(String::as_str));
+        writeln!(f, "Targets: {}", targets.join(", "))?;
+        writeln!(
+            f,
+            "Last Target: {}",
+            self.last_target.as_deref().unwrap_or("none")
+        )?;
+        match self.last_backend {
+            Some(backend) => writeln!(f, "Last Backend: {backend:?}")?,
+            None => writeln!(f, "Last Backend: none")?,
+        }
+        match &self.last_trigger {
+            Some(trigger) => writeln!(f, "Last Trigger: {:?} at {}", trigger.action, trigger.at)?,
+            None => writeln!(f, "Last Trigger: none")?,
+        }
+        match &self.autostart {
+            Some(autostart) => writeln!(
+                f,
+                "Autostart: {} via {}",
+                yes_no(autostart.enabled),
+                autostart.backend
+            ),
+            None => writeln!(f, "Autostart: unavailable"),
+        }
+    }
+}
+
+/// the running instance's answer to the "status" command.
+#[cfg(feature = "ipc")]
+pub fn json() -> Result<String> {
+    Ok(serde_json::to_string_pretty(&report())? + "\n")
+}
+
+/// the status of the running instance, as answered to `--status`.
+pub fn report() -> Report {
+    let status = get();
+    Report {
+        version: PACKAGE_VERSION.to_owned(),
+        running: true,
+        paused: status.paused,
+        registered_hotkeys: status.hotkeys,
+        targets: status.targets,
+        last_target: status.target,
+        last_backend: status.backend,
+        last_trigger: status.last_trigger,
+        autostart: status.autostart,
+    }
+}
+
+/// prints the status of the running instance for `--status`, failing if none runs so scripts can
+/// tell by the exit code.
+///
+/// note: a release build has no console, so its standard output has to be redirected.
+#[cfg(feature = "ipc")]
+pub fn print(json: bool) -> Result<()> {
+    let report = match ipc::request("status")? {
+        Some(response) => serde_json::from_str(&response).context("invalid status")?,
+        None => Report::not_running(),
+    };
+    let text = match json {
+        true => serde_json::to_string_pretty(&report)? + "\n",
+        false => report.to_string(),
+    };
+    io::stdout()
+        .write_all(text.as_bytes())
+        .context("failed to write the standard output")?;
+    ensure!(report.running, "not running");
+    Ok(())
+}
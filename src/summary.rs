@@ -0,0 +1,126 @@
+use std::{
+    collections::BTreeMap,
+    sync::Mutex,
+    thread,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use serde::{Deserialize, Serialize};
+#[allow(unused_imports)]
+use tracing::{debug, error, info, trace, warn};
+use windows::{
+    core::HSTRING,
+    Win32::{
+        Foundation::HWND,
+        UI::WindowsAndMessaging::{MessageBoxW, MB_ICONINFORMATION, MB_OK},
+    },
+};
+
+use crate::{config::Config, quiet, snapshot, LockExt, PACKAGE_NAME};
+
+/// how often the summary is shown when enabled.
+const SUMMARY_INTERVAL: Duration = Duration::from_secs(7 * 24 * 60 * 60);
+
+/// the failure reason of a busy target window.
+pub const NOT_RESPONDING: &str = "the window didn't respond";
+
+/// what happened to the hotkeys since the last summary.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Week {
+    /// seconds since the unix epoch when counting started.
+    pub since: u64,
+    pub triggers: u64,
+    /// how often each reason made a hotkey fail.
+    pub failures: BTreeMap<String, u64>,
+}
+
+static WEEK: Mutex<Week> = Mutex::new(Week {
+    since: 0,
+    triggers: 0,
+    failures: BTreeMap::new(),
+});
+
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs()
+}
+
+/// continues counting from the persisted week.
+pub fn restore(mut week: Week) {
+    if week.since == 0 {
+        week.since = now();
+    }
+    *WEEK.locked() = week;
+}
+
+pub fn week() -> Week {
+    WEEK.locked().clone()
+}
+
+pub fn record_trigger() {
+    WEEK.locked().triggers += 1;
+}
+
+/// counts a hotkey whose action didn't happen, towards the summary and the diagnostics snapshot,
+/// `reason` is a short phrase, e.g. "the window didn't respond".
+pub fn record_failure(reason: &str) {
+    let mut week = WEEK.locked();
+    *week.failures.entry(reason.to_owned()).or_default() += 1;
+    drop(week);
+    snapshot::record_failure();
+}
+
+/// the text of the summary, e.g. "42 toggles, 3 failed, most often because …".
+pub fn text(week: &Week) -> String {
+    let failed: u64 = week.failures.values().sum();
+    let mut text = format!(
+        "This week: {} hotkeys handled, {failed} failed.",
+        week.triggers
+    );
+    if let Some((reason, count)) = week.failures.iter().max_by_key(|(_, count)| **count) {
+        text.push_str(&format!("\nMost often {reason} ({count}×)."));
+    }
+    if failed > 0 {
+        text.push_str(
+            "\n\nIf this started after a VSCode update, see \"Why Isn't It Working?\" in the tray.",
+        );
+    }
+    text
+}
+
+/// shows the summary in the background and starts counting anew once a week passed, only if enabled.
+///
+/// note: a quiet week isn't worth interrupting the user for, it's counted into the next one.
+pub fn show_if_due(config: &Config) {
+    if !config.weekly_summary || quiet::is_on() {
+        return;
+    }
+    let week = {
+        let mut week = WEEK.locked();
+        if now().saturating_sub(week.since) < SUMMARY_INTERVAL.as_secs() || week.triggers == 0 {
+            return;
+        }
+        std::mem::replace(
+            &mut *week,
+            Week {
+                since: now(),
+                ..Default::default()
+            },
+        )
+    };
+    info!("weekly summary: {week:?}");
+
+    thread::spawn(move || {
+        unsafe {
+            MessageBoxW(
+                HWND(0),
+                &HSTRING::from(text(&week)),
+                &HSTRING::from(PACKAGE_NAME),
+                MB_OK | MB_ICONINFORMATION,
+            )
+        };
+    });
+}
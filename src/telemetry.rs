@@ -0,0 +1,155 @@
+use std::{
+    collections::BTreeMap,
+    fmt::Write,
+    sync::Mutex,
+    thread,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use anyhow::{ensure, Result};
+use serde::{Deserialize, Serialize};
+#[allow(unused_imports)]
+use tracing::{debug, error, info, trace, warn};
+use windows::{
+    core::HSTRING,
+    Win32::{
+        Foundation::HWND,
+        UI::WindowsAndMessaging::{MessageBoxW, IDYES, MB_ICONQUESTION, MB_YESNO},
+    },
+};
+
+use crate::{
+    config::Config, diagnostics, http, inject::Backend, LockExt, LogExt, PACKAGE_NAME,
+    PACKAGE_VERSION,
+};
+
+/// how often the statistics are sent when opted in.
+const REPORT_INTERVAL: Duration = Duration::from_secs(24 * 60 * 60);
+
+/// aggregate counts since the last report, nothing about what the user types or where.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Usage {
+    /// seconds since the unix epoch when counting started.
+    pub since: u64,
+    pub triggers: u64,
+    pub backends: BTreeMap<Backend, Outcomes>,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Outcomes {
+    pub succeeded: u64,
+    pub failed: u64,
+}
+
+/// note: counted even when not opted in, so the consent dialog can show real numbers.
+static USAGE: Mutex<Usage> = Mutex::new(Usage {
+    since: 0,
+    triggers: 0,
+    backends: BTreeMap::new(),
+});
+
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs()
+}
+
+/// continues counting from the persisted usage.
+pub fn restore(mut usage: Usage) {
+    if usage.since == 0 {
+        usage.since = now();
+    }
+    *USAGE.locked() = usage;
+}
+
+pub fn usage() -> Usage {
+    USAGE.locked().clone()
+}
+
+pub fn record_trigger() {
+    USAGE.locked().triggers += 1;
+}
+
+pub fn record_injection(backend: Backend, succeeded: bool) {
+    let mut usage = USAGE.locked();
+    let outcomes = usage.backends.entry(backend).or_default();
+    if succeeded {
+        outcomes.succeeded += 1;
+    } else {
+        outcomes.failed += 1;
+    }
+}
+
+/// the exact JSON document sent, also shown to the user before opting in.
+pub fn payload(usage: &Usage) -> String {
+    let days = (now().saturating_sub(usage.since) as f64 / REPORT_INTERVAL.as_secs_f64()).max(1.0);
+    let mut backends = String::new();
+    for (i, (backend, outcomes)) in usage.backends.iter().enumerate() {
+        let separator = if i == 0 { "" } else { ", " };
+        // note: the debug names are plain identifiers, no escaping needed.
+        let _ = write!(
+            backends,
+            r#"{separator}"{backend:?}": {{ "succeeded": {}, "failed": {} }}"#,
+            outcomes.succeeded, outcomes.failed
+        );
+    }
+    format!(
+        r#"{{ "version": "{PACKAGE_VERSION}", "os": "{}", "triggers_per_day": {:.1}, "backends": {{ {backends} }} }}"#,
+        diagnostics::os_bucket(),
+        usage.triggers as f64 / days,
+    )
+}
+
+/// sends the statistics in the background and starts counting anew once a report is due, only if
+/// opted in.
+pub fn report_if_due(config: &Config) {
+    let Some(url) = config.telemetry_url.clone().filter(|_| config.telemetry) else {
+        return;
+    };
+    let usage = {
+        let mut usage = USAGE.locked();
+        if now().saturating_sub(usage.since) < REPORT_INTERVAL.as_secs() {
+            return;
+        }
+        std::mem::replace(
+            &mut *usage,
+            Usage {
+                since: now(),
+                ..Default::default()
+            },
+        )
+    };
+
+    // note: a lost report isn't worth retrying, the counts are only used for prioritizing.
+    thread::spawn(move || send(&url, &payload(&usage)).warn());
+}
+
+fn send(url: &str, payload: &str) -> Result<()> {
+    let status = http::post(url, "application/json", payload.as_bytes())?;
+    ensure!(
+        (200..300).contains(&status),
+        "the report was rejected: {status}"
+    );
+    debug!("sent usage statistics: {payload}");
+    Ok(())
+}
+
+/// shows the exact payload and asks whether to send it, returns the answer.
+pub fn ask_consent() -> bool {
+    let text = format!(
+        "When enabled, the following is sent once a day and nothing else:\n\n{}\n\nSend anonymous usage statistics?",
+        payload(&usage())
+    );
+    let answer = unsafe {
+        MessageBoxW(
+            HWND(0),
+            &HSTRING::from(text),
+            &HSTRING::from(PACKAGE_NAME),
+            MB_YESNO | MB_ICONQUESTION,
+        )
+    };
+    answer == IDYES
+}
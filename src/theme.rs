@@ -0,0 +1,143 @@
+use std::{
+    mem,
+    sync::atomic::{AtomicBool, Ordering},
+};
+
+use anyhow::{ensure, Context, Result};
+use windows::{
+    core::{w, PCSTR, PCWSTR},
+    Foundation::TypedEventHandler,
+    Win32::{
+        Foundation::{LPARAM, WPARAM},
+        System::{
+            LibraryLoader::{GetProcAddress, LoadLibraryW},
+            Registry::{RegGetValueW, HKEY_CURRENT_USER, RRF_RT_REG_DWORD},
+        },
+        UI::WindowsAndMessaging::{PostThreadMessageW, WM_APP},
+    },
+    UI::ViewManagement::UISettings,
+};
+
+use crate::{
+    compat::{self, Feature},
+    tray::Icon,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Theme {
+    Light,
+    Dark,
+}
+
+/// posted to the message pump when the personalized colors changed, e.g. the taskbar turned dark.
+pub const WM_THEME: u32 = WM_APP + 10;
+
+/// posts `WM_THEME` to the thread whenever the colors of the personalization settings change, e.g.
+/// the accent color or the mode, which Windows 11 doesn't always broadcast, the settings have to be
+/// kept until the changes aren't needed anymore.
+///
+/// note: the event is raised on a thread pool thread.
+pub fn watch_colors(tid: u32) -> Result<UISettings> {
+    let settings = UISettings::new()?;
+    settings.ColorValuesChanged(&TypedEventHandler::new(move |_, _| unsafe {
+        PostThreadMessageW(tid, WM_THEME, WPARAM(0), LPARAM(0))
+    }))?;
+    Ok(settings)
+}
+
+/// whether our popup menus follow the theme of applications, they are always light otherwise.
+static DARK_MENUS: AtomicBool = AtomicBool::new(false);
+
+/// lets the popup menus of our process, e.g. the tray menu, turn dark with the theme of
+/// applications.
+///
+/// note: uxtheme exports `SetPreferredAppMode` by ordinal only, it's what Explorer's own menus use.
+pub fn allow_dark_menus() -> Result<()> {
+    const SET_PREFERRED_APP_MODE: usize = 135;
+    const ALLOW_DARK: i32 = 1;
+
+    // note: the menus stay light in builds without the dark glyphs.
+    if !cfg!(feature = "dark-menus") {
+        return Ok(());
+    }
+
+    ensure!(
+        compat::has(Feature::PreferredAppMode),
+        "dark menus need Windows 10 1903"
+    );
+    unsafe {
+        let uxtheme = LoadLibraryW(w!("uxtheme.dll"))?;
+        let set_preferred_app_mode = GetProcAddress(uxtheme, PCSTR(SET_PREFERRED_APP_MODE as _))
+            .context("uxtheme has no SetPreferredAppMode")?;
+        let set_preferred_app_mode: unsafe extern "system" fn(i32) -> i32 =
+            mem::transmute(set_preferred_app_mode);
+        set_preferred_app_mode(ALLOW_DARK);
+    }
+    DARK_MENUS.store(true, Ordering::Relaxed);
+    Ok(())
+}
+
+/// the theme of our popup menus, following the "app mode" of the personalization settings.
+pub fn menus() -> Theme {
+    if !DARK_MENUS.load(Ordering::Relaxed) {
+        return Theme::Light;
+    }
+    personalized(w!("AppsUseLightTheme"))
+}
+
+/// the theme of the taskbar and the notification area, following the "Windows mode" of the
+/// personalization settings.
+pub fn taskbar() -> Theme {
+    if !compat::has(Feature::LightTaskbar) {
+        return Theme::Dark;
+    }
+    personalized(w!("SystemUsesLightTheme"))
+}
+
+fn personalized(value: PCWSTR) -> Theme {
+    let mut light = 1u32;
+    let mut size = mem::size_of::<u32>() as u32;
+    // note: the value is missing before Windows 10 1809, which is light only.
+    let _ = unsafe {
+        RegGetValueW(
+            HKEY_CURRENT_USER,
+            w!(r"Software\Microsoft\Windows\CurrentVersion\Themes\Personalize"),
+            value,
+            RRF_RT_REG_DWORD,
+            None,
+            Some(&mut light as *mut u32 as *mut _),
+            Some(&mut size),
+        )
+    };
+    if light == 0 {
+        Theme::Dark
+    } else {
+        Theme::Light
+    }
+}
+
+/// the glyph next to a menu item.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MenuIcon {
+    Pause,
+    Autostart,
+    Presets,
+}
+
+impl MenuIcon {
+    /// the glyph drawn for the theme, dark on light menus and vice versa.
+    pub fn icon(self, theme: Theme) -> Icon {
+        let buffer: &'static [u8] = match (self, theme) {
+            #[cfg(feature = "dark-menus")]
+            (Self::Pause, Theme::Dark) => include_bytes!("../assets/menu/pause-dark.ico"),
+            #[cfg(feature = "dark-menus")]
+            (Self::Autostart, Theme::Dark) => include_bytes!("../assets/menu/autostart-dark.ico"),
+            #[cfg(feature = "dark-menus")]
+            (Self::Presets, Theme::Dark) => include_bytes!("../assets/menu/presets-dark.ico"),
+            (Self::Pause, _) => include_bytes!("../assets/menu/pause-light.ico"),
+            (Self::Autostart, _) => include_bytes!("../assets/menu/autostart-light.ico"),
+            (Self::Presets, _) => include_bytes!("../assets/menu/presets-light.ico"),
+        };
+        Icon::from_buffer(buffer, None, None).expect("the bundled menu icons are valid")
+    }
+}
@@ -0,0 +1,90 @@
+use std::{cell::RefCell, collections::BTreeMap, time::Duration};
+
+use anyhow::{ensure, Result};
+#[allow(unused_imports)]
+use tracing::{debug, error, info, trace, warn};
+use windows::Win32::{
+    Foundation::{HWND, WPARAM},
+    UI::WindowsAndMessaging::{KillTimer, SetTimer, USER_TIMER_MAXIMUM, USER_TIMER_MINIMUM},
+};
+
+use crate::LogExt;
+
+/// a started timer of the calling thread, it runs until it elapses once or is cancelled.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Handle(usize);
+
+/// what a timer stands in for once it elapses.
+struct Timer {
+    message: u32,
+    wparam: WPARAM,
+    repeats: bool,
+}
+
+thread_local! {
+    /// the timers of the thread by their id, which Windows picks.
+    static TIMERS: RefCell<BTreeMap<usize, Timer>> = const { RefCell::new(BTreeMap::new()) };
+}
+
+/// has the message pump get `message` once after `delay`, like a message posted then.
+///
+/// note: the timer is the thread's, not a window's, so a modal loop like the tray menu's drops it
+/// as it does posted thread messages.
+pub fn once(delay: Duration, message: u32, wparam: WPARAM) -> Result<Handle> {
+    start(delay, message, wparam, false)
+}
+
+/// has the message pump get `message` every `interval` until cancelled.
+pub fn every(interval: Duration, message: u32, wparam: WPARAM) -> Result<Handle> {
+    start(interval, message, wparam, true)
+}
+
+fn start(delay: Duration, message: u32, wparam: WPARAM, repeats: bool) -> Result<Handle> {
+    let elapse = delay
+        .as_millis()
+        .clamp(USER_TIMER_MINIMUM as u128, USER_TIMER_MAXIMUM as u128) as u32;
+    let id = unsafe { SetTimer(HWND(0), 0, elapse, None) };
+    ensure!(
+        id != 0,
+        "failed to start a timer: {}",
+        windows::core::Error::from_win32()
+    );
+    TIMERS.with(|timers| {
+        timers.borrow_mut().insert(
+            id,
+            Timer {
+                message,
+                wparam,
+                repeats,
+            },
+        )
+    });
+    Ok(Handle(id))
+}
+
+impl Handle {
+    /// stops the timer unless it already elapsed, on the thread that started it.
+    pub fn cancel(self) {
+        if TIMERS
+            .with(|timers| timers.borrow_mut().remove(&self.0))
+            .is_some()
+        {
+            unsafe { KillTimer(HWND(0), self.0) }.warn();
+        }
+    }
+}
+
+/// the message and `wParam` the elapsed timer of a `WM_TIMER` stands in for, `None` if it isn't
+/// ours or was cancelled meanwhile.
+pub fn elapsed(id: usize) -> Option<(u32, WPARAM)> {
+    TIMERS.with(|timers| {
+        let mut timers = timers.borrow_mut();
+        let timer = timers.get(&id)?;
+        let elapsed = (timer.message, timer.wparam);
+        if !timer.repeats {
+            timers.remove(&id);
+            unsafe { KillTimer(HWND(0), id) }.warn();
+        }
+        Some(elapsed)
+    })
+}
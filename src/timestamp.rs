@@ -0,0 +1,80 @@
+use std::{cmp::Ordering, fmt};
+
+use serde::{Deserialize, Serialize};
+use tracing_subscriber::fmt::{format::Writer, time::FormatTime};
+use windows::Win32::{
+    Foundation::SYSTEMTIME,
+    System::{SystemInformation::GetSystemTime, Time::SystemTimeToTzSpecificLocalTime},
+};
+
+/// the clock the log timestamps are taken from.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Timezone {
+    /// e.g. `2024-05-01T08:30:00.123Z`.
+    #[default]
+    Utc,
+    /// with the offset of the time zone of Windows, e.g. `2024-05-01T17:30:00.123+09:00`.
+    Local,
+}
+
+/// formats the timestamps of the log lines in ISO 8601, with milliseconds.
+pub struct Timestamp(pub Timezone);
+
+impl FormatTime for Timestamp {
+    fn format_time(&self, w: &mut Writer<'_>) -> fmt::Result {
+        write_now(w, self.0)
+    }
+}
+
+/// the current time in ISO 8601, e.g. for `--status`.
+pub fn now(timezone: Timezone) -> String {
+    let mut text = String::new();
+    // note: writing to a string doesn't fail.
+    let _ = write_now(&mut text, timezone);
+    text
+}
+
+fn write_now(w: &mut impl fmt::Write, timezone: Timezone) -> fmt::Result {
+    let utc = unsafe { GetSystemTime() };
+    let mut local = SYSTEMTIME::default();
+    let local = match timezone {
+        Timezone::Local
+            if unsafe { SystemTimeToTzSpecificLocalTime(None, &utc, &mut local) }.is_ok() =>
+        {
+            local
+        }
+        // note: the time zone can't be lost really, UTC is a fine fallback anyway.
+        _ => return write_time(w, &utc, "Z"),
+    };
+    let offset = offset_minutes(&utc, &local);
+    let sign = if offset < 0 { '-' } else { '+' };
+    let offset = format!("{sign}{:02}:{:02}", offset.abs() / 60, offset.abs() % 60);
+    write_time(w, &local, &offset)
+}
+
+fn write_time(w: &mut impl fmt::Write, time: &SYSTEMTIME, offset: &str) -> fmt::Result {
+    write!(
+        w,
+        "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}.{:03}{offset}",
+        time.wYear,
+        time.wMonth,
+        time.wDay,
+        time.wHour,
+        time.wMinute,
+        time.wSecond,
+        time.wMilliseconds
+    )
+}
+
+/// how far the local time is ahead of UTC, they're at most a day apart.
+fn offset_minutes(utc: &SYSTEMTIME, local: &SYSTEMTIME) -> i32 {
+    let minutes = |time: &SYSTEMTIME| time.wHour as i32 * 60 + time.wMinute as i32;
+    let date = |time: &SYSTEMTIME| (time.wYear, time.wMonth, time.wDay);
+    let days = match date(local).cmp(&date(utc)) {
+        Ordering::Less => -1,
+        Ordering::Equal => 0,
+        Ordering::Greater => 1,
+    };
+    days * 24 * 60 + minutes(local) - minutes(utc)
+}
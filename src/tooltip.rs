@@ -0,0 +1,93 @@
+/// the most UTF-16 units a tray tooltip holds, `szTip` has room for 128 including the terminating
+/// null.
+const MAX_LEN: usize = 127;
+
+/// builds the tray tooltip from status lines and a description, fitting it into `MAX_LEN`.
+///
+/// note: the status lines take precedence, the description only gets the room they leave.
+#[derive(Debug, Clone, Default)]
+pub struct Tooltip {
+    status: Vec<String>,
+    description: String,
+}
+
+impl Tooltip {
+    pub fn new(description: &str) -> Self {
+        Self {
+            status: Vec::new(),
+            description: description.trim().to_owned(),
+        }
+    }
+
+    pub fn status(mut self, line: impl Into<String>) -> Self {
+        self.status.push(line.into());
+        self
+    }
+
+    pub fn build(&self) -> String {
+        let status = truncate(&self.status.join("\n"), MAX_LEN);
+        if status.is_empty() {
+            return truncate(&self.description, MAX_LEN);
+        }
+        // note: the description goes on its own line.
+        let room = (MAX_LEN - utf16_len(&status)).saturating_sub(1);
+        match truncate(&self.description, room) {
+            description if description.is_empty() => status,
+            description => format!("{status}\n{description}"),
+        }
+    }
+}
+
+fn utf16_len(text: &str) -> usize {
+    text.chars().map(char::len_utf16).sum()
+}
+
+/// cuts the text to at most `max_len` UTF-16 units, ending with 「…」 if anything was cut, without
+/// tearing a character from its combining marks.
+fn truncate(text: &str, max_len: usize) -> String {
+    if utf16_len(text) <= max_len {
+        return text.to_owned();
+    }
+    let Some(max_len) = max_len.checked_sub('…'.len_utf16()) else {
+        return String::new();
+    };
+
+    let mut end = 0;
+    let mut len = 0;
+    let mut previous = None;
+    for (i, c) in text.char_indices() {
+        if !extends(previous, c) {
+            end = i;
+        }
+        len += c.len_utf16();
+        if len > max_len {
+            break;
+        }
+        previous = Some(c);
+    }
+    format!("{}…", text[..end].trim_end())
+}
+
+/// whether the character belongs to the same grapheme as the one before, e.g. a combining mark, a
+/// variation selector or the parts of an emoji sequence.
+///
+/// note: a conservative subset of the Unicode rules, enough not to tear the usual CJK text apart.
+fn extends(previous: Option<char>, c: char) -> bool {
+    previous == Some('\u{200D}')
+        || matches!(
+            c,
+            '\u{0300}'..='\u{036F}'
+                | '\u{1AB0}'..='\u{1AFF}'
+                | '\u{1DC0}'..='\u{1DFF}'
+                | '\u{20D0}'..='\u{20FF}'
+                | '\u{FE20}'..='\u{FE2F}'
+                // note: the dakuten and handakuten of kana.
+                | '\u{3099}'..='\u{309A}'
+                // note: the medial vowels and final consonants of Hangul syllables spelled in jamo.
+                | '\u{1160}'..='\u{11FF}'
+                | '\u{FE00}'..='\u{FE0F}'
+                | '\u{E0100}'..='\u{E01EF}'
+                | '\u{200D}'
+                | '\u{1F3FB}'..='\u{1F3FF}'
+        )
+}
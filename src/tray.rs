@@ -0,0 +1,898 @@
+use std::{
+    collections::BTreeMap,
+    fmt, mem,
+    sync::{mpsc::Sender, Arc, Mutex},
+};
+
+use anyhow::{bail, ensure, Context, Result};
+#[allow(unused_imports)]
+use tracing::{debug, error, info, trace, warn};
+use windows::{
+    core::{w, PCWSTR, PWSTR},
+    Win32::{
+        Foundation::{HWND, LPARAM, LRESULT, POINT, WPARAM},
+        Graphics::Gdi::HBRUSH,
+        System::{LibraryLoader::GetModuleHandleW, Threading::GetCurrentThreadId},
+        UI::{
+            Controls::{DRAWITEMSTRUCT, MEASUREITEMSTRUCT, ODT_MENU},
+            Shell::{
+                Shell_NotifyIconW, NIF_ICON, NIF_INFO, NIF_MESSAGE, NIF_TIP, NIIF_NOSOUND,
+                NIIF_WARNING, NIM_ADD, NIM_DELETE, NIM_MODIFY, NIN_BALLOONHIDE, NIN_BALLOONTIMEOUT,
+                NIN_BALLOONUSERCLICK, NOTIFYICONDATAW, NOTIFY_ICON_INFOTIP_FLAGS,
+            },
+            WindowsAndMessaging::{
+                CheckMenuItem, CopyIcon, CreateIconFromResourceEx, CreatePopupMenu,
+                CreateWindowExW, DefWindowProcW, DestroyIcon, DestroyMenu, DestroyWindow,
+                DrawIconEx, GetCursorPos, GetMenuState, GetWindowLongPtrW, InsertMenuItemW,
+                LookupIconIdFromDirectoryEx, PostMessageW, PostThreadMessageW, RegisterClassW,
+                RegisterWindowMessageW, SetForegroundWindow, SetMenuItemInfoW, SetWindowLongPtrW,
+                TrackPopupMenuEx, DI_NORMAL, GWLP_USERDATA, HBMMENU_CALLBACK, HICON, HMENU,
+                LR_DEFAULTCOLOR, MENUITEMINFOW, MFS_CHECKED, MFS_DISABLED, MFT_SEPARATOR,
+                MF_BYCOMMAND, MF_CHECKED, MF_UNCHECKED, MIIM_BITMAP, MIIM_DATA, MIIM_FTYPE,
+                MIIM_ID, MIIM_STATE, MIIM_STRING, MIIM_SUBMENU, TPM_BOTTOMALIGN, TPM_NOANIMATION,
+                TPM_RIGHTBUTTON, WM_APP, WM_COMMAND, WM_DRAWITEM, WM_MEASUREITEM, WM_NCDESTROY,
+                WM_NULL, WM_RBUTTONUP, WNDCLASSW, WS_EX_TOOLWINDOW, WS_POPUP,
+            },
+        },
+    },
+};
+
+use crate::{dpi, quiet, LockExt, LogExt};
+
+/// sent to the window by the tray icon, e.g. once it was right-clicked.
+const WM_NOTIFY_ICON: u32 = WM_APP + 19;
+
+/// posted to the window to show the menu, only the thread owning the window may.
+const WM_SHOW_MENU: u32 = WM_APP + 20;
+
+/// posted to the message pump once the icon was added back after Explorer restarted.
+pub const WM_TASKBAR_CREATED: u32 = WM_APP + 22;
+
+/// the class of the window receiving the tray icon's notifications and the menu's commands.
+const CLASS: PCWSTR = w!("vscode-cjk-toggle-terminal-fixer-tray");
+
+/// an icon of the tray or of a menu item, destroyed once dropped.
+#[derive(Debug)]
+pub struct Icon(HICON);
+
+impl Icon {
+    /// creates the icon from the image of an ICO file closest to the size, or to the default icon
+    /// size without one.
+    ///
+    /// note: `LookupIconIdFromDirectoryEx` takes the ICO file for an icon group resource, whose
+    /// ids are then the offsets of the images.
+    pub fn from_buffer(buffer: &[u8], width: Option<u32>, height: Option<u32>) -> Result<Self> {
+        const HEADER_LEN: usize = 6;
+
+        ensure!(buffer.len() > HEADER_LEN, "not an ICO file");
+        let (width, height) = (width.unwrap_or(0) as i32, height.unwrap_or(0) as i32);
+        let offset = unsafe {
+            LookupIconIdFromDirectoryEx(buffer.as_ptr(), true, width, height, LR_DEFAULTCOLOR)
+        };
+        let image = usize::try_from(offset)
+            .ok()
+            .filter(|&offset| offset > 0)
+            .and_then(|offset| buffer.get(offset..))
+            .context("found no image in the ICO file")?;
+        let icon = unsafe {
+            CreateIconFromResourceEx(image, true, 0x00030000, width, height, LR_DEFAULTCOLOR)?
+        };
+        Ok(Self(icon))
+    }
+
+    fn copy(&self) -> Result<Self> {
+        Ok(Self(unsafe { CopyIcon(self.0)? }))
+    }
+}
+
+impl Drop for Icon {
+    fn drop(&mut self) {
+        unsafe { DestroyIcon(self.0) }.warn();
+    }
+}
+
+/// an item of the tray menu.
+pub enum Item<E> {
+    /// sends its event once chosen, with a check mark unless `checked` is `None`.
+    Command {
+        id: E,
+        label: String,
+        checked: Option<bool>,
+        disabled: bool,
+        icon: Option<Icon>,
+    },
+    Submenu {
+        label: String,
+        icon: Option<Icon>,
+        menu: Menu<E>,
+    },
+    Separator,
+}
+
+/// the items of the tray menu or of a submenu, top to bottom.
+pub struct Menu<E>(Vec<Item<E>>);
+
+impl<E> Default for Menu<E> {
+    fn default() -> Self {
+        Self(Vec::new())
+    }
+}
+
+impl<E> Menu<E> {
+    pub fn with(mut self, item: Item<E>) -> Self {
+        self.0.push(item);
+        self
+    }
+
+    /// continues building with `f`, e.g. to add items only if something's available.
+    pub fn when(self, f: impl FnOnce(Self) -> Self) -> Self {
+        f(self)
+    }
+
+    pub fn item(self, label: &str, id: E) -> Self {
+        self.with(Item::Command {
+            id,
+            label: label.to_owned(),
+            checked: None,
+            disabled: false,
+            icon: None,
+        })
+    }
+
+    pub fn checkable(self, label: &str, checked: bool, id: E) -> Self {
+        self.with(Item::Command {
+            id,
+            label: label.to_owned(),
+            checked: Some(checked),
+            disabled: false,
+            icon: None,
+        })
+    }
+
+    pub fn submenu(self, label: &str, menu: Menu<E>) -> Self {
+        self.with(Item::Submenu {
+            label: label.to_owned(),
+            icon: None,
+            menu,
+        })
+    }
+
+    pub fn separator(self) -> Self {
+        self.with(Item::Separator)
+    }
+}
+
+impl<E: fmt::Debug + PartialEq> Menu<E> {
+    /// the menu as text, an item per line with the submenus indented, e.g. to compare the menus of
+    /// two configs in the log.
+    pub fn outline(&self) -> String {
+        let mut outline = String::new();
+        self.write_outline(&mut outline, 0);
+        outline
+    }
+
+    fn write_outline(&self, outline: &mut String, depth: usize) {
+        let indent = "    ".repeat(depth);
+        for item in &self.0 {
+            match item {
+                Item::Command {
+                    id,
+                    label,
+                    checked,
+                    disabled,
+                    ..
+                } => {
+                    let check = match checked {
+                        Some(true) => "[x] ",
+                        Some(false) => "[ ] ",
+                        None => "",
+                    };
+                    let disabled = if *disabled { " (disabled)" } else { "" };
+                    let label = label.replace('\t', " | ");
+                    outline.push_str(&format!("{indent}{check}{label}{disabled} = {id:?}\n"));
+                }
+                Item::Submenu { label, menu, .. } => {
+                    outline.push_str(&format!("{indent}{label} >\n"));
+                    menu.write_outline(outline, depth + 1);
+                }
+                Item::Separator => outline.push_str(&format!("{indent}---\n")),
+            }
+        }
+    }
+
+    /// catches the mistakes of items added on conditions: a separator at either end or next to
+    /// another, an empty submenu, an event sent by two items, or an item without a label, which
+    /// Narrator would have nothing to read out for.
+    pub fn validate(&self) -> Result<()> {
+        let mut ids = Vec::new();
+        self.validate_in("the menu", &mut ids)
+    }
+
+    fn validate_in<'a>(&'a self, name: &str, ids: &mut Vec<&'a E>) -> Result<()> {
+        let separator = |item: Option<&Item<E>>| matches!(item, Some(Item::Separator));
+        ensure!(!self.0.is_empty(), "{name} is empty");
+        ensure!(
+            !separator(self.0.first()) && !separator(self.0.last()),
+            "{name} starts or ends with a separator"
+        );
+        for (i, item) in self.0.iter().enumerate() {
+            match item {
+                Item::Command { label, .. } | Item::Submenu { label, .. }
+                    if label.trim().is_empty() =>
+                {
+                    bail!("{name} has an item without a label");
+                }
+                Item::Command { id, label, .. } => {
+                    if ids.contains(&id) {
+                        bail!("{label:?} of {name} sends {id:?} like another item");
+                    }
+                    ids.push(id);
+                }
+                Item::Submenu { label, menu, .. } => {
+                    menu.validate_in(&format!("{label:?}"), ids)?;
+                }
+                Item::Separator => ensure!(
+                    !separator(self.0.get(i + 1)),
+                    "{name} has two separators in a row"
+                ),
+            }
+        }
+        Ok(())
+    }
+}
+
+/// the icon in the notification area with its menu, removed once dropped.
+///
+/// note: the thread creating it owns its window, which shows the menu, so it must pump messages
+/// and drop it as well, the other methods may be called from any thread.
+pub struct Tray<E> {
+    hwnd: HWND,
+    menus: Arc<Mutex<Menus<E>>>,
+    shown: Arc<Mutex<Shown>>,
+    /// sent once the balloon shown is clicked.
+    balloon_click: Arc<Mutex<Option<E>>>,
+}
+
+/// what the tray icon shows, added again once Explorer restarted.
+struct Shown {
+    data: NOTIFYICONDATAW,
+    /// our copy of the icon, the caller's may be dropped in the meantime.
+    icon: Icon,
+}
+
+/// the menu shown and the ones it replaced.
+struct Menus<E> {
+    current: Built<E>,
+    /// replaced while they may still be shown, they're destroyed once the menu is shown again.
+    retired: Vec<Built<E>>,
+    /// the id of the next command built, the commands of a replaced menu are never renumbered.
+    next_command: u32,
+}
+
+/// a menu created from its model, destroyed once dropped.
+struct Built<E> {
+    hmenu: HMENU,
+    /// the events of the commands by id.
+    commands: BTreeMap<u32, E>,
+    /// the glyphs of the menu items, drawn by the window.
+    glyphs: Vec<Icon>,
+}
+
+impl<E> Drop for Built<E> {
+    fn drop(&mut self) {
+        // note: destroys the submenus as well.
+        unsafe { DestroyMenu(self.hmenu) }.warn();
+    }
+}
+
+/// what the window needs to handle the notifications, kept in its user data.
+struct Handler<E> {
+    sender: Sender<E>,
+    /// sent once the tray icon was right-clicked, which should show the menu.
+    right_click: E,
+    menus: Arc<Mutex<Menus<E>>>,
+    shown: Arc<Mutex<Shown>>,
+    balloon_click: Arc<Mutex<Option<E>>>,
+    /// broadcast once the taskbar was created again, e.g. after Explorer crashed.
+    taskbar_created: u32,
+}
+
+impl<E: Copy + fmt::Debug + PartialEq + Send + 'static> Tray<E> {
+    /// adds the icon to the notification area, its events are sent to `sender`.
+    pub fn new(
+        sender: Sender<E>,
+        right_click: E,
+        icon: &Icon,
+        tooltip: &str,
+        menu: Menu<E>,
+    ) -> Result<Self> {
+        check(&menu);
+        let mut next_command = 1;
+        let current = Built::new(menu, &mut next_command)?;
+        let menus = Arc::new(Mutex::new(Menus {
+            current,
+            retired: Vec::new(),
+            next_command,
+        }));
+        let hwnd = create_window::<E>()?;
+        let mut data = NOTIFYICONDATAW {
+            cbSize: mem::size_of::<NOTIFYICONDATAW>() as u32,
+            hWnd: hwnd,
+            uFlags: NIF_ICON | NIF_MESSAGE | NIF_TIP,
+            uCallbackMessage: WM_NOTIFY_ICON,
+            ..Default::default()
+        };
+        let icon = icon.copy()?;
+        data.hIcon = icon.0;
+        set_tip(&mut data, tooltip);
+        let shown = Arc::new(Mutex::new(Shown { data, icon }));
+        let balloon_click = Arc::new(Mutex::new(None));
+        let handler = Box::new(Handler {
+            sender,
+            right_click,
+            menus: menus.clone(),
+            shown: shown.clone(),
+            balloon_click: balloon_click.clone(),
+            taskbar_created: unsafe { RegisterWindowMessageW(w!("TaskbarCreated")) },
+        });
+        unsafe { SetWindowLongPtrW(hwnd, GWLP_USERDATA, Box::into_raw(handler) as isize) };
+        let tray = Self {
+            hwnd,
+            menus,
+            shown,
+            balloon_click,
+        };
+        ensure!(
+            unsafe { Shell_NotifyIconW(NIM_ADD, &tray.shown.locked().data) }.as_bool(),
+            "failed to add the tray icon"
+        );
+        Ok(tray)
+    }
+
+    pub fn set_icon(&self, icon: &Icon) -> Result<()> {
+        let icon = icon.copy()?;
+        let mut shown = self.shown.locked();
+        shown.data.hIcon = icon.0;
+        shown.icon = icon;
+        modify(&shown.data)
+    }
+
+    pub fn set_tooltip(&self, tooltip: &str) -> Result<()> {
+        let mut shown = self.shown.locked();
+        set_tip(&mut shown.data, tooltip);
+        modify(&shown.data)
+    }
+
+    /// shows a warning balloon, a notification since Windows 10, sending `click` once it's clicked.
+    ///
+    /// note: the balloon replaces the one shown before, its click is gone with it.
+    pub fn show_balloon(&self, title: &str, text: &str, click: Option<E>) -> Result<()> {
+        *self.balloon_click.locked() = click;
+
+        // note: a copy, adding the icon back after Explorer restarted mustn't show it again.
+        let mut data = self.shown.locked().data;
+        data.uFlags |= NIF_INFO;
+        data.dwInfoFlags = match quiet::is_on() {
+            true => NOTIFY_ICON_INFOTIP_FLAGS(NIIF_WARNING.0 | NIIF_NOSOUND.0),
+            false => NIIF_WARNING,
+        };
+        set_text(&mut data.szInfoTitle, title);
+        set_text(&mut data.szInfo, text);
+        modify(&data)
+    }
+
+    /// replaces the menu, e.g. once the config changed, shown from the next time on.
+    ///
+    /// note: the checks and labels changed since are the new model's.
+    pub fn set_menu(&self, menu: Menu<E>) -> Result<()> {
+        check(&menu);
+        let mut menus = self.menus.locked();
+        let built = Built::new(menu, &mut menus.next_command)?;
+        let replaced = mem::replace(&mut menus.current, built);
+        menus.retired.push(replaced);
+        Ok(())
+    }
+
+    pub fn is_checked(&self, id: E) -> bool {
+        let menus = self.menus.locked();
+        let Some(command) = menus.current.command(id) else {
+            return false;
+        };
+        let state = unsafe { GetMenuState(menus.current.hmenu, command, MF_BYCOMMAND) };
+        state != u32::MAX && state & MF_CHECKED.0 != 0
+    }
+
+    pub fn set_checked(&self, id: E, checked: bool) -> Result<()> {
+        let menus = self.menus.locked();
+        let command = menus.current.command(id).context("no such menu item")?;
+        let check = if checked { MF_CHECKED } else { MF_UNCHECKED };
+        let previous =
+            unsafe { CheckMenuItem(menus.current.hmenu, command, (MF_BYCOMMAND | check).0) };
+        ensure!(previous != u32::MAX, "no such menu item");
+        Ok(())
+    }
+
+    pub fn set_label(&self, id: E, label: &str) -> Result<()> {
+        let menus = self.menus.locked();
+        let command = menus.current.command(id).context("no such menu item")?;
+        let mut label = wide(label);
+        let info = MENUITEMINFOW {
+            cbSize: mem::size_of::<MENUITEMINFOW>() as u32,
+            fMask: MIIM_STRING,
+            dwTypeData: PWSTR(label.as_mut_ptr()),
+            ..Default::default()
+        };
+        unsafe { SetMenuItemInfoW(menus.current.hmenu, command, false, &info)? };
+        Ok(())
+    }
+
+    /// shows the menu at the cursor, once the thread owning the window gets to it.
+    pub fn show_menu(&self) -> Result<()> {
+        unsafe { PostMessageW(self.hwnd, WM_SHOW_MENU, WPARAM(0), LPARAM(0))? };
+        Ok(())
+    }
+}
+
+/// logs the menu built and its mistakes, it's shown anyway.
+fn check<E: fmt::Debug + PartialEq>(menu: &Menu<E>) {
+    debug!("tray menu:\n{}", menu.outline());
+    menu.validate().context("the tray menu is malformed").warn();
+}
+
+impl<E> Drop for Tray<E> {
+    fn drop(&mut self) {
+        let shown = self.shown.locked();
+        unsafe {
+            Shell_NotifyIconW(NIM_DELETE, &shown.data);
+            DestroyWindow(self.hwnd).warn();
+        }
+    }
+}
+
+impl<E: PartialEq> Built<E> {
+    /// creates the menu, numbering the commands from `next_command` on.
+    fn new(menu: Menu<E>, next_command: &mut u32) -> Result<Self> {
+        let mut built = Self {
+            hmenu: unsafe { CreatePopupMenu()? },
+            commands: BTreeMap::new(),
+            glyphs: Vec::new(),
+        };
+        built.insert(built.hmenu, menu, next_command)?;
+        Ok(built)
+    }
+
+    fn command(&self, id: E) -> Option<u32> {
+        self.commands
+            .iter()
+            .find_map(|(&command, command_id)| (*command_id == id).then_some(command))
+    }
+
+    fn insert(&mut self, hmenu: HMENU, menu: Menu<E>, next_command: &mut u32) -> Result<()> {
+        for (position, item) in menu.0.into_iter().enumerate() {
+            let mut info = MENUITEMINFOW {
+                cbSize: mem::size_of::<MENUITEMINFOW>() as u32,
+                fMask: MIIM_FTYPE,
+                ..Default::default()
+            };
+            let mut submenu = None;
+            let (label, icon) = match item {
+                Item::Command {
+                    id,
+                    label,
+                    checked,
+                    disabled,
+                    icon,
+                } => {
+                    // note: `WM_COMMAND` carries 16 bits, the ids wrap around after many rebuilds.
+                    let command = *next_command;
+                    *next_command = command % 0xFFFF + 1;
+                    self.commands.insert(command, id);
+                    info.fMask |= MIIM_ID | MIIM_STATE;
+                    info.wID = command;
+                    if checked == Some(true) {
+                        info.fState |= MFS_CHECKED;
+                    }
+                    if disabled {
+                        info.fState |= MFS_DISABLED;
+                    }
+                    (Some(label), icon)
+                }
+                Item::Submenu { label, icon, menu } => {
+                    info.fMask |= MIIM_SUBMENU;
+                    info.hSubMenu = unsafe { CreatePopupMenu()? };
+                    submenu = Some((info.hSubMenu, menu));
+                    (Some(label), icon)
+                }
+                Item::Separator => {
+                    info.fType = MFT_SEPARATOR;
+                    (None, None)
+                }
+            };
+            // note: kept until the item was inserted, which copies it.
+            let mut label = label.as_deref().map(wide);
+            if let Some(label) = &mut label {
+                info.fMask |= MIIM_STRING;
+                info.dwTypeData = PWSTR(label.as_mut_ptr());
+            }
+            // note: drawn by the window, see `WM_DRAWITEM`.
+            if let Some(icon) = icon {
+                info.fMask |= MIIM_BITMAP | MIIM_DATA;
+                info.hbmpItem = HBMMENU_CALLBACK;
+                info.dwItemData = icon.0 .0 as usize;
+                self.glyphs.push(icon);
+            }
+            if let Err(err) = unsafe { InsertMenuItemW(hmenu, position as u32, true, &info) } {
+                if let Some((hsubmenu, _)) = submenu {
+                    unsafe { DestroyMenu(hsubmenu) }.warn();
+                }
+                return Err(err.into());
+            }
+            // note: filled once inserted, it's destroyed with the menu then.
+            if let Some((hsubmenu, menu)) = submenu {
+                self.insert(hsubmenu, menu, next_command)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// a hidden top-level window, message-only ones don't get the `TaskbarCreated` broadcast.
+///
+/// note: there's a single tray icon, so the class is registered once.
+fn create_window<E: Copy + Send + 'static>() -> Result<HWND> {
+    unsafe {
+        let instance = GetModuleHandleW(PCWSTR::null())?;
+        let class = WNDCLASSW {
+            lpfnWndProc: Some(on_message::<E>),
+            hInstance: instance.into(),
+            lpszClassName: CLASS,
+            ..Default::default()
+        };
+        ensure!(
+            RegisterClassW(&class) != 0,
+            "failed to register the window class: {}",
+            windows::core::Error::from_win32()
+        );
+        let hwnd = CreateWindowExW(
+            WS_EX_TOOLWINDOW,
+            class.lpszClassName,
+            PCWSTR::null(),
+            WS_POPUP,
+            0,
+            0,
+            0,
+            0,
+            HWND(0),
+            HMENU(0),
+            instance,
+            None,
+        );
+        ensure!(
+            hwnd != HWND(0),
+            "failed to create the window: {}",
+            windows::core::Error::from_win32()
+        );
+        Ok(hwnd)
+    }
+}
+
+fn modify(data: &NOTIFYICONDATAW) -> Result<()> {
+    // note: the icon is gone if Explorer restarted before we got `TaskbarCreated`.
+    if unsafe { Shell_NotifyIconW(NIM_MODIFY, data) }.as_bool() {
+        return Ok(());
+    }
+    ensure!(
+        unsafe { Shell_NotifyIconW(NIM_ADD, data) }.as_bool(),
+        "failed to update the tray icon"
+    );
+    Ok(())
+}
+
+/// sets the tooltip, cut off at the 127 characters the notification area shows.
+fn set_tip(data: &mut NOTIFYICONDATAW, tooltip: &str) {
+    set_text(&mut data.szTip, tooltip);
+}
+
+/// copies the text into a buffer of `NOTIFYICONDATAW`, cut off to leave room for the terminator.
+fn set_text<const N: usize>(buffer: &mut [u16; N], text: &str) {
+    *buffer = [0; N];
+    for (c, b) in text.encode_utf16().zip(&mut buffer[..N - 1]) {
+        *b = c;
+    }
+}
+
+fn wide(text: &str) -> Vec<u16> {
+    text.encode_utf16().chain(Some(0)).collect()
+}
+
+/// shows the menu at the cursor, its commands are sent to the window as `WM_COMMAND`.
+///
+/// note: the menu only closes once clicking elsewhere if the window was in the foreground.
+fn show_menu(hwnd: HWND, menu: HMENU) -> Result<()> {
+    let mut point = POINT::default();
+    let mut flags = TPM_RIGHTBUTTON | TPM_BOTTOMALIGN;
+    if quiet::reduces_motion() {
+        flags |= TPM_NOANIMATION;
+    }
+    unsafe {
+        GetCursorPos(&mut point)?;
+        SetForegroundWindow(hwnd);
+        let shown = TrackPopupMenuEx(menu, flags.0, point.x, point.y, hwnd, None);
+        PostMessageW(hwnd, WM_NULL, WPARAM(0), LPARAM(0)).warn();
+        shown?;
+    }
+    Ok(())
+}
+
+unsafe extern "system" fn on_message<E: Copy + Send + 'static>(
+    hwnd: HWND,
+    msg: u32,
+    wparam: WPARAM,
+    lparam: LPARAM,
+) -> LRESULT {
+    let raw = GetWindowLongPtrW(hwnd, GWLP_USERDATA) as *mut Handler<E>;
+    // note: messages like `WM_CREATE` arrive before the handler is set.
+    let Some(handler) = raw.as_ref() else {
+        return DefWindowProcW(hwnd, msg, wparam, lparam);
+    };
+    match msg {
+        WM_NOTIFY_ICON if lparam.0 as u32 == WM_RBUTTONUP => {
+            handler.sender.send(handler.right_click).warn();
+        }
+        WM_NOTIFY_ICON if lparam.0 as u32 == NIN_BALLOONUSERCLICK => {
+            let click = handler.balloon_click.locked().take();
+            if let Some(click) = click {
+                handler.sender.send(click).warn();
+            }
+        }
+        WM_NOTIFY_ICON if matches!(lparam.0 as u32, NIN_BALLOONTIMEOUT | NIN_BALLOONHIDE) => {
+            handler.balloon_click.locked().take();
+        }
+        WM_SHOW_MENU => {
+            // note: not shown anymore, the replaced menus are gone for good.
+            let hmenu = {
+                let mut menus = handler.menus.locked();
+                menus.retired.clear();
+                menus.current.hmenu
+            };
+            show_menu(hwnd, hmenu).warn();
+        }
+        // note: the high word is 0 for the commands of a menu.
+        WM_COMMAND if wparam.0 >> 16 == 0 => {
+            let command = (wparam.0 & 0xFFFF) as u32;
+            let menus = handler.menus.locked();
+            let id = [&menus.current]
+                .into_iter()
+                .chain(&menus.retired)
+                .find_map(|built| built.commands.get(&command).copied());
+            drop(menus);
+            if let Some(id) = id {
+                handler.sender.send(id).warn();
+            }
+        }
+        WM_MEASUREITEM => {
+            let item = &mut *(lparam.0 as *mut MEASUREITEMSTRUCT);
+            if item.CtlType == ODT_MENU {
+                let size = dpi::small_icon_size();
+                (item.itemWidth, item.itemHeight) = (size, size);
+                return LRESULT(1);
+            }
+        }
+        WM_DRAWITEM => {
+            let item = &*(lparam.0 as *const DRAWITEMSTRUCT);
+            if item.CtlType == ODT_MENU {
+                let size = dpi::small_icon_size() as i32;
+                let top = item.rcItem.top + (item.rcItem.bottom - item.rcItem.top - size) / 2;
+                DrawIconEx(
+                    item.hDC,
+                    item.rcItem.left,
+                    top,
+                    HICON(item.itemData as isize),
+                    size,
+                    size,
+                    0,
+                    HBRUSH(0),
+                    DI_NORMAL,
+                )
+                .warn();
+                return LRESULT(1);
+            }
+        }
+        WM_NCDESTROY => {
+            SetWindowLongPtrW(hwnd, GWLP_USERDATA, 0);
+            drop(Box::from_raw(raw));
+        }
+        msg if msg == handler.taskbar_created => {
+            let shown = handler.shown.locked();
+            info!("the taskbar was created again, adding the tray icon back");
+            Shell_NotifyIconW(NIM_ADD, &shown.data);
+            PostThreadMessageW(
+                GetCurrentThreadId(),
+                WM_TASKBAR_CREATED,
+                WPARAM(0),
+                LPARAM(0),
+            )
+            .warn();
+        }
+        _ => {}
+    }
+    DefWindowProcW(hwnd, msg, wparam, lparam)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use crate::{config::Config, status, tray_menu, MenuState};
+
+    /// where the executable runs from, the items changing the config need it.
+    const APP_PATH: &str = r"C:\Tools\vscode-cjk-toggle-terminal-fixer.exe";
+
+    const RULES: &str = r#"
+        chords = [
+            { keys = ["Ctrl+J"], action = "toggle_panel" },
+            { keys = ["Ctrl+K", "Ctrl+`"], action = "focus_terminal", enabled = false },
+        ]
+        gestures = [{ double_tap = "Ctrl", action = "toggle_terminal" }]
+    "#;
+
+    fn state() -> MenuState {
+        MenuState {
+            paused: false,
+            auto_launched: None,
+            learning: false,
+            elevated: Some(true),
+            status: status::LINES.map(|line| format!("{line:?}")),
+        }
+    }
+
+    /// the outline of the tray menu, failing unless it's valid.
+    fn outline(config: &str, app_path: Option<&str>, state: MenuState) -> String {
+        let config: Config = toml::from_str(config).unwrap();
+        let menu = tray_menu(&config, app_path.map(Path::new), &state, None);
+        menu.validate().unwrap();
+        menu.outline()
+    }
+
+    #[test]
+    #[cfg(feature = "settings-ui")]
+    fn default_menu() {
+        assert_eq!(
+            outline("", Some(APP_PATH), state()),
+            r#"
+Status >
+    Hotkeys (disabled) = Tray(Status(Hotkeys))
+    Backend (disabled) = Tray(Status(Backend))
+    Target (disabled) = Tray(Status(Target))
+    Layout (disabled) = Tray(Status(Layout))
+    Paused (disabled) = Tray(Status(Paused))
+    Uptime (disabled) = Tray(Status(Uptime))
+---
+[ ] Pause = Tray(Pause)
+[ ] Also Fix Ctrl+J (Toggle Panel) = Tray(Alternate(TogglePanel))
+[ ] Also Fix Ctrl+~ (New Terminal) = Tray(Alternate(NewTerminal))
+Presets >
+    VSCode only = Tray(Preset(VscodeOnly))
+    VSCode + terminals = Tray(Preset(VscodeAndTerminals))
+    JetBrains too = Tray(Preset(JetBrains))
+    Aggressive IME handling = Tray(Preset(AggressiveIme))
+Add Current Window's App as Target = Tray(AddTarget)
+[ ] Learning Mode = Tray(Learning)
+Disable for This Workspace = Tray(DisableWorkspace)
+Import from PowerToys = Tray(ImportPowerToys)
+Export as AutoHotkey Script = Tray(ExportAhk)
+Usage Statistics… = Tray(Telemetry)
+Report a Problem… = Tray(ReportProblem)
+Why Isn't It Working? = Tray(Troubleshoot)
+Show Recent Events = Tray(RecentEvents)
+Show Trigger History = Tray(TriggerHistory)
+---
+Restart = Tray(Restart)
+Exit = Tray(Exit)
+"#[1..]
+        );
+    }
+
+    #[test]
+    #[cfg(feature = "settings-ui")]
+    fn paused_with_rules() {
+        let state = MenuState {
+            paused: true,
+            auto_launched: Some(true),
+            learning: true,
+            elevated: Some(false),
+            ..state()
+        };
+        assert_eq!(
+            outline(RULES, Some(APP_PATH), state),
+            r#"
+Status >
+    Hotkeys (disabled) = Tray(Status(Hotkeys))
+    Backend (disabled) = Tray(Status(Backend))
+    Target (disabled) = Tray(Status(Target))
+    Layout (disabled) = Tray(Status(Layout))
+    Paused (disabled) = Tray(Status(Paused))
+    Uptime (disabled) = Tray(Status(Uptime))
+---
+[x] Pause = Tray(Pause)
+[x] Auto Launch = Tray(AutoLaunch)
+[x] Also Fix Ctrl+J (Toggle Panel) = Tray(Alternate(TogglePanel))
+[ ] Also Fix Ctrl+~ (New Terminal) = Tray(Alternate(NewTerminal))
+Presets >
+    VSCode only = Tray(Preset(VscodeOnly))
+    VSCode + terminals = Tray(Preset(VscodeAndTerminals))
+    JetBrains too = Tray(Preset(JetBrains))
+    Aggressive IME handling = Tray(Preset(AggressiveIme))
+Rules >
+    [x] View: Toggle Panel Visibility | Ctrl+J = Tray(Rule(Chord(0)))
+    [ ] Terminal: Focus Terminal | Ctrl+K Ctrl+` = Tray(Rule(Chord(1)))
+    ---
+    [x] View: Toggle Terminal | Double-Tap Ctrl = Tray(Rule(Gesture(0)))
+Add Current Window's App as Target = Tray(AddTarget)
+[x] Learning Mode = Tray(Learning)
+Disable for This Workspace = Tray(DisableWorkspace)
+Import from PowerToys = Tray(ImportPowerToys)
+Export as AutoHotkey Script = Tray(ExportAhk)
+Usage Statistics… = Tray(Telemetry)
+Report a Problem… = Tray(ReportProblem)
+Why Isn't It Working? = Tray(Troubleshoot)
+Show Recent Events = Tray(RecentEvents)
+Show Trigger History = Tray(TriggerHistory)
+---
+Restart = Tray(Restart)
+Restart as Administrator = Tray(RelaunchElevated)
+Exit = Tray(Exit)
+"#[1..]
+        );
+    }
+
+    /// note: without the path of the config, nothing in it can be changed from the menu.
+    #[test]
+    #[cfg(feature = "settings-ui")]
+    fn unknown_app_path() {
+        let state = MenuState {
+            elevated: Some(false),
+            ..state()
+        };
+        assert_eq!(
+            outline(RULES, None, state),
+            r#"
+Status >
+    Hotkeys (disabled) = Tray(Status(Hotkeys))
+    Backend (disabled) = Tray(Status(Backend))
+    Target (disabled) = Tray(Status(Target))
+    Layout (disabled) = Tray(Status(Layout))
+    Paused (disabled) = Tray(Status(Paused))
+    Uptime (disabled) = Tray(Status(Uptime))
+---
+[ ] Pause = Tray(Pause)
+Why Isn't It Working? = Tray(Troubleshoot)
+Show Recent Events = Tray(RecentEvents)
+Show Trigger History = Tray(TriggerHistory)
+---
+Restart = Tray(Restart)
+Exit = Tray(Exit)
+"#[1..]
+        );
+    }
+
+    #[test]
+    fn disabled_workspaces_leave_the_menu_alone() {
+        assert_eq!(
+            outline(
+                r#"disabled_workspaces = ["crate"]"#,
+                Some(APP_PATH),
+                state()
+            ),
+            outline("", Some(APP_PATH), state())
+        );
+    }
+
+    #[test]
+    #[cfg(not(feature = "settings-ui"))]
+    fn no_viewer_items_without_settings_ui() {
+        let outline = outline(RULES, Some(APP_PATH), state());
+        assert!(!outline.contains("Show Recent Events"));
+        assert!(!outline.contains("Show Trigger History"));
+    }
+}
@@ -0,0 +1,108 @@
+use std::{fs, path::PathBuf, thread};
+
+use windows::{
+    core::HSTRING,
+    Win32::{
+        Foundation::HWND,
+        System::Threading::GetCurrentProcessId,
+        UI::{
+            Input::KeyboardAndMouse::GetKeyboardLayout,
+            WindowsAndMessaging::{
+                GetWindowThreadProcessId, MessageBoxW, MB_ICONINFORMATION, MB_OK,
+            },
+        },
+    },
+};
+
+use crate::{conflict, keybindings, procs, status, window, PACKAGE_NAME};
+
+/// walks through the usual reasons the hotkey does nothing, checked against how things are right now.
+pub fn report() -> String {
+    let mut findings = Vec::new();
+
+    if status::get().hotkeys == 0 {
+        findings.push(
+            "No hotkey is registered. Unpause it in the tray, or another application took \
+             Ctrl+`, the log names the hotkey that failed."
+                .to_owned(),
+        );
+    }
+
+    findings.extend(conflict::detect().iter().map(ToString::to_string));
+
+    match window::last_vscode_window().or_else(window::find_vscode_window) {
+        Some(hwnd) => {
+            findings.extend(elevation(hwnd));
+            findings.push(layout(hwnd));
+        }
+        None => findings.push(
+            "No VSCode window was found, the hotkey only acts on windows titled \
+             \"… - Visual Studio Code\"."
+                .to_owned(),
+        ),
+    }
+
+    findings.extend(keybindings());
+
+    let findings: Vec<_> = findings
+        .iter()
+        .enumerate()
+        .map(|(i, finding)| format!("{}. {finding}", i + 1))
+        .collect();
+    findings.join("\n\n")
+}
+
+/// note: Windows drops input from a process running with less privileges than the window's.
+fn elevation(hwnd: HWND) -> Option<String> {
+    let ours = procs::is_elevated(unsafe { GetCurrentProcessId() }).unwrap_or(false);
+    let theirs = procs::is_elevated(procs::of_window(hwnd)).unwrap_or(!ours);
+    (theirs && !ours).then(|| {
+        "VSCode runs as administrator but we don't, so Windows blocks the keys we send. Run both \
+         the same way."
+            .to_owned()
+    })
+}
+
+fn layout(hwnd: HWND) -> String {
+    let hkl = unsafe { GetKeyboardLayout(GetWindowThreadProcessId(hwnd, None)) };
+    format!(
+        "VSCode uses the keyboard layout {:#010x}. If the terminal doesn't toggle or a 「`」 is \
+         typed instead, try the \"Aggressive IME handling\" preset.",
+        hkl.0 as u32
+    )
+}
+
+/// the user's VSCode keybindings, e.g. `%APPDATA%\Code\User\keybindings.json`, those of the profile
+/// of the last VSCode window.
+fn keybindings_path() -> Option<PathBuf> {
+    let hwnd = window::last_vscode_window().or_else(window::find_vscode_window);
+    keybindings::path(hwnd.unwrap_or(HWND(0)))
+}
+
+/// note: the file is JSON with comments, a text search is good enough to spot a rebinding.
+pub fn keybindings() -> Option<String> {
+    let text = fs::read_to_string(keybindings_path()?).ok()?;
+    let text = text.to_ascii_lowercase();
+    let unbound = text.contains("\"-workbench.action.terminal.toggleterminal\"");
+    let rebound = text.contains("\"ctrl+`\"");
+    (unbound || rebound).then(|| {
+        "Your VSCode keybindings.json changes Ctrl+` or the terminal toggle, we send the keys of \
+         the default keybinding, so \"command_palette\" or \"uia\" should be the first backend."
+            .to_owned()
+    })
+}
+
+/// shows the findings without blocking the caller.
+pub fn show() {
+    thread::spawn(|| {
+        let text = format!("Why isn't it working?\n\n{}", report());
+        unsafe {
+            MessageBoxW(
+                HWND(0),
+                &HSTRING::from(text),
+                &HSTRING::from(PACKAGE_NAME),
+                MB_OK | MB_ICONINFORMATION,
+            )
+        };
+    });
+}
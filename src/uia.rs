@@ -0,0 +1,128 @@
+use std::mem::ManuallyDrop;
+
+use anyhow::{Context, Result};
+use windows::{
+    core::BSTR,
+    Win32::{
+        Foundation::HWND,
+        System::Variant::{VariantClear, VARIANT, VT_BSTR},
+        UI::Accessibility::{
+            CUIAutomation, IUIAutomation, IUIAutomationCondition, IUIAutomationElement,
+            IUIAutomationExpandCollapsePattern, IUIAutomationInvokePattern, TreeScope_Descendants,
+            UIA_ComboBoxControlTypeId, UIA_EditControlTypeId, UIA_ExpandCollapsePatternId,
+            UIA_InvokePatternId, UIA_MenuItemControlTypeId, UIA_NamePropertyId,
+        },
+    },
+};
+
+use crate::com;
+
+/// runs `f` with the UI Automation client of the calling thread, created on first use.
+pub fn with<T>(f: impl FnOnce(&IUIAutomation) -> Result<T>) -> Result<T> {
+    com::with(&CUIAutomation, f)
+}
+
+/// describes the focused UI element, toggling the terminal moves the focus in or out of xterm's textarea.
+pub fn focus() -> Result<String> {
+    with(|automation| unsafe {
+        let element = automation.GetFocusedElement()?;
+        Ok(format!(
+            "{}|{}",
+            element.CurrentClassName()?,
+            element.CurrentName()?
+        ))
+    })
+}
+
+/// the class of the text input of VSCode's editor.
+const EDITOR_INPUT: &str = "inputarea";
+
+/// the classes of VSCode's text inputs the toggle is meant for, xterm's and the editor's.
+const TOGGLING_INPUTS: [&str; 2] = ["xterm-helper-textarea", EDITOR_INPUT];
+
+/// the classes of VSCode's overlays taking the keys, the quick open and command palette, and the
+/// dialogs drawn in the window.
+const OVERLAYS: [&str; 2] = ["quick-input-widget", "monaco-dialog-box"];
+
+/// how many ancestors of the focused element are searched for an overlay.
+const OVERLAY_DEPTH: usize = 12;
+
+/// describes the focused element if it takes the keys as text, e.g. the input of the command
+/// palette or a find widget, or belongs to a dialog, where the toggle would type a 「`」 instead.
+pub fn focused_input() -> Result<Option<String>> {
+    with(|automation| unsafe {
+        let element = automation.GetFocusedElement()?;
+        let class = element.CurrentClassName()?.to_string();
+        let is_text = [UIA_EditControlTypeId, UIA_ComboBoxControlTypeId]
+            .contains(&element.CurrentControlType()?);
+        // note: the ancestors are only searched away from the editor and terminal, it takes a
+        // round trip to VSCode per element.
+        if TOGGLING_INPUTS.iter().any(|input| class.contains(input)) {
+            return Ok(None);
+        }
+        if is_text {
+            return Ok(Some(format!("{class}|{}", element.CurrentName()?)));
+        }
+        let walker = automation.ControlViewWalker()?;
+        let mut ancestor = element;
+        for _ in 0..OVERLAY_DEPTH {
+            let Ok(parent) = walker.GetParentElement(&ancestor) else {
+                break;
+            };
+            let class = parent.CurrentClassName()?.to_string();
+            if let Some(overlay) = OVERLAYS.iter().find(|overlay| class.contains(*overlay)) {
+                return Ok(Some((*overlay).to_owned()));
+            }
+            ancestor = parent;
+        }
+        Ok(None)
+    })
+}
+
+/// whether the focus is in VSCode's editor, rather than e.g. the terminal.
+pub fn is_editor_focused() -> Result<bool> {
+    with(|automation| unsafe {
+        let class = automation.GetFocusedElement()?.CurrentClassName()?;
+        Ok(class.to_string().contains(EDITOR_INPUT))
+    })
+}
+
+/// invokes the menu items along `path` in the window one after another, e.g. `["View", "Terminal"]`.
+pub fn invoke_menu(hwnd: HWND, path: &[impl AsRef<str>]) -> Result<()> {
+    with(|automation| unsafe {
+        let root = automation.ElementFromHandle(hwnd)?;
+        for name in path.iter().map(AsRef::as_ref) {
+            let found = root.FindAll(TreeScope_Descendants, &name_condition(automation, name)?)?;
+            // note: an opened submenu is appended after the menu bar, so the last match is the innermost one.
+            let item = (0..found.Length()?)
+                .rev()
+                .filter_map(|i| found.GetElement(i).ok())
+                .find(|element| {
+                    element.CurrentControlType().ok() == Some(UIA_MenuItemControlTypeId)
+                })
+                .with_context(|| format!("menu item {name:?} not found"))?;
+            invoke(&item)?;
+        }
+        Ok(())
+    })
+}
+
+unsafe fn invoke(element: &IUIAutomationElement) -> Result<()> {
+    match element.GetCurrentPatternAs::<IUIAutomationInvokePattern>(UIA_InvokePatternId) {
+        Ok(pattern) => pattern.Invoke()?,
+        Err(_) => element
+            .GetCurrentPatternAs::<IUIAutomationExpandCollapsePattern>(UIA_ExpandCollapsePatternId)?
+            .Expand()?,
+    }
+    Ok(())
+}
+
+unsafe fn name_condition(automation: &IUIAutomation, name: &str) -> Result<IUIAutomationCondition> {
+    let mut variant = VARIANT::default();
+    (*variant.Anonymous.Anonymous).vt = VT_BSTR;
+    (*variant.Anonymous.Anonymous).Anonymous.bstrVal = ManuallyDrop::new(BSTR::from(name));
+    // note: the condition copies the value, so our variant must still be cleared.
+    let condition = automation.CreatePropertyCondition(UIA_NamePropertyId, variant.clone());
+    VariantClear(&mut variant)?;
+    Ok(condition?)
+}
@@ -0,0 +1,136 @@
+use std::{fs, thread, time::Duration};
+
+use anyhow::{Context, Result};
+use serde::Deserialize;
+#[allow(unused_imports)]
+use tracing::{debug, error, info, trace, warn};
+use windows::{
+    core::HSTRING,
+    Win32::{
+        Foundation::{BOOL, HWND, LPARAM, TRUE, WPARAM},
+        UI::WindowsAndMessaging::{
+            EnumWindows, IsWindowVisible, MessageBoxW, MB_ICONWARNING, MB_OK, WM_APP,
+        },
+    },
+};
+
+use crate::{config::Config, launch, procs, timer, troubleshoot, window, PACKAGE_NAME};
+
+/// sent to the message pump by the timer when the installed VSCode version should be checked.
+pub const WM_UPGRADE: u32 = WM_APP + 17;
+
+/// how often the version is checked, VSCode updates itself on restart.
+const CHECK_INTERVAL: Duration = Duration::from_secs(10 * 60);
+
+/// starts the timers of the calling thread, which gets `WM_UPGRADE` right away and then
+/// periodically.
+pub fn watch() -> Result<timer::Handle> {
+    timer::once(Duration::ZERO, WM_UPGRADE, WPARAM(0))?;
+    timer::every(CHECK_INTERVAL, WM_UPGRADE, WPARAM(0))
+}
+
+/// the version of the VSCode install, e.g. `1.89.1`.
+fn installed(config: &Config) -> Result<String> {
+    #[derive(Deserialize)]
+    struct Package {
+        version: String,
+    }
+
+    let code_path = config
+        .code_path
+        .clone()
+        .or_else(launch::detect_code_path)
+        .context("VSCode executable not found, set `code_path` in config")?;
+    // note: the user setup keeps the app next to `Code.exe`.
+    let path = code_path.with_file_name(r"resources\app\package.json");
+    let text = fs::read_to_string(&path).with_context(|| format!("failed to read {path:?}"))?;
+    let package: Package =
+        serde_json::from_str(&text).with_context(|| format!("invalid {path:?}"))?;
+    Ok(package.version)
+}
+
+/// remembers the installed version and, once it changed, checks what we rely on in the background,
+/// returns whether it changed.
+///
+/// note: the first version seen is only remembered, there's nothing to compare it with.
+pub fn check(config: &Config, known: &mut Option<String>) -> bool {
+    // note: checked every few minutes, a missing install would flood the log with warnings.
+    let version = match installed(config) {
+        Ok(version) => version,
+        Err(err) => {
+            debug!("{err:#}");
+            return false;
+        }
+    };
+    if known.as_ref() == Some(&version) {
+        return false;
+    }
+    let Some(previous) = known.replace(version.clone()) else {
+        info!("VSCode {version} is installed");
+        return true;
+    };
+    info!("VSCode was updated from {previous} to {version}");
+    thread::spawn(move || {
+        let findings = self_test();
+        if findings.is_empty() {
+            info!("the self-test passed with VSCode {version}");
+            return;
+        }
+        warn!("the self-test failed with VSCode {version}: {findings:?}");
+        let text = format!(
+            "VSCode was updated from {previous} to {version}, which changed something the fixer relies on:\n\n{}",
+            findings.join("\n\n")
+        );
+        unsafe {
+            MessageBoxW(
+                HWND(0),
+                &HSTRING::from(text),
+                &HSTRING::from(PACKAGE_NAME),
+                MB_OK | MB_ICONWARNING,
+            )
+        };
+    });
+    true
+}
+
+/// what stopped working, checked against the running VSCode and the user's keybindings.
+fn self_test() -> Vec<String> {
+    let mut findings = Vec::new();
+    if let Some(title) = unrecognized_title() {
+        findings.push(format!(
+            "Its windows aren't recognized by their title anymore, e.g. \"{title}\", so the hotkey \
+             passes them by. Please report this."
+        ));
+    }
+    findings.extend(troubleshoot::keybindings());
+    findings
+}
+
+/// the title of a visible `Code.exe` window, if none of them is recognized as VSCode's.
+///
+/// note: without a VSCode window there's nothing to test, the title is only known at runtime.
+fn unrecognized_title() -> Option<String> {
+    unsafe extern "system" fn visit(hwnd: HWND, lparam: LPARAM) -> BOOL {
+        if IsWindowVisible(hwnd).as_bool() {
+            (*(lparam.0 as *mut Vec<HWND>)).push(hwnd);
+        }
+        TRUE
+    }
+
+    let mut windows = Vec::new();
+    // note: the enumeration isn't stopped early, so it fails only if it can't start at all.
+    unsafe { EnumWindows(Some(visit), LPARAM(&mut windows as *mut Vec<HWND> as isize)) }.ok()?;
+    let titles: Vec<_> = windows
+        .into_iter()
+        .filter(|&hwnd| {
+            procs::name(procs::of_window(hwnd))
+                .is_some_and(|name| name.eq_ignore_ascii_case("Code.exe"))
+        })
+        .map(|hwnd| (window::is_vscode_window(hwnd), window::title(hwnd)))
+        .filter(|(_, title)| !title.is_empty())
+        .collect();
+    if titles.iter().any(|(recognized, _)| *recognized) {
+        return None;
+    }
+    titles.into_iter().next().map(|(_, title)| title)
+}
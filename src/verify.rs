@@ -0,0 +1,83 @@
+use std::{cell::RefCell, time::Duration};
+
+#[allow(unused_imports)]
+use tracing::{debug, error, info, trace, warn};
+use windows::Win32::{
+    Foundation::{HWND, WPARAM},
+    UI::WindowsAndMessaging::WM_APP,
+};
+
+use crate::{
+    hotkey::Hotkey,
+    inject::{self, Backend},
+    summary, timer, uia, LogExt,
+};
+
+/// posted to the message pump once the target had time to react to the injected action.
+pub const WM_VERIFY: u32 = WM_APP + 2;
+
+/// how long VSCode gets to move the focus in or out of the terminal.
+pub(crate) const VERIFY_DELAY: Duration = Duration::from_millis(200);
+
+struct Pending {
+    hwnd: HWND,
+    hotkey: Hotkey,
+    backend: Backend,
+    backends: Vec<Backend>,
+    focus: String,
+    timer: Option<timer::Handle>,
+}
+
+thread_local! {
+    static PENDING: RefCell<Option<Pending>> = const { RefCell::new(None) };
+}
+
+/// schedules `WM_VERIFY` for an action injected via `backend`, `focus` is the focus before injecting.
+///
+/// note: a later action replaces the pending one, it's verified instead.
+pub fn schedule(
+    hwnd: HWND,
+    hotkey: Hotkey,
+    backend: Backend,
+    backends: Vec<Backend>,
+    focus: String,
+) {
+    let timer = timer::once(VERIFY_DELAY, WM_VERIFY, WPARAM(0)).warn();
+    let replaced = PENDING.with(|pending| {
+        pending.borrow_mut().replace(Pending {
+            hwnd,
+            hotkey,
+            backend,
+            backends,
+            focus,
+            timer,
+        })
+    });
+    if let Some(timer) = replaced.and_then(|replaced| replaced.timer) {
+        timer.cancel();
+    }
+}
+
+/// checks whether the focus moved since the action was injected, returns the window, the hotkey and
+/// the remaining backends of the chain to retry with if not.
+pub fn finish() -> Option<(HWND, Hotkey, Vec<Backend>)> {
+    let pending = PENDING.with(|pending| pending.borrow_mut().take())?;
+    let focus = uia::focus().warn()?;
+    if focus != pending.focus {
+        return None;
+    }
+
+    inject::forget(pending.hwnd);
+    summary::record_failure("the injection had no effect");
+    let remaining: Vec<_> = pending
+        .backends
+        .into_iter()
+        .skip_while(|&backend| backend != pending.backend)
+        .skip(1)
+        .collect();
+    warn!(
+        "injection of {:?} via {:?} had no effect on {:?}: the focus stayed on {focus:?}, remaining backends: {remaining:?}",
+        pending.hotkey.action, pending.backend, pending.hwnd
+    );
+    (!remaining.is_empty()).then_some((pending.hwnd, pending.hotkey, remaining))
+}
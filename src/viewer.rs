@@ -0,0 +1,221 @@
+use std::{
+    cell::Cell,
+    ffi::c_void,
+    mem,
+    sync::atomic::{AtomicIsize, Ordering},
+    thread,
+};
+
+use anyhow::{ensure, Result};
+#[allow(unused_imports)]
+use tracing::{debug, error, info, trace, warn};
+use windows::{
+    core::{w, HSTRING, PCWSTR},
+    Win32::{
+        Foundation::{HWND, LPARAM, LRESULT, WPARAM},
+        Graphics::Gdi::{
+            CreateFontIndirectW, DeleteObject, GetStockObject, DEFAULT_GUI_FONT, HFONT,
+        },
+        System::LibraryLoader::GetModuleHandleW,
+        UI::{
+            Input::KeyboardAndMouse::{SetFocus, VK_ESCAPE},
+            WindowsAndMessaging::{
+                CreateWindowExW, DefWindowProcW, DestroyWindow, DispatchMessageW, GetMessageW,
+                MoveWindow, PostQuitMessage, RegisterClassW, SendMessageW, SetForegroundWindow,
+                SystemParametersInfoW, TranslateMessage, CW_USEDEFAULT, HMENU,
+                LBS_NOINTEGRALHEIGHT, LB_ADDSTRING, LB_SETCURSEL, LB_SETHORIZONTALEXTENT, MSG,
+                NONCLIENTMETRICSW, SPI_GETNONCLIENTMETRICS, SYSTEM_PARAMETERS_INFO_UPDATE_FLAGS,
+                WINDOW_STYLE, WM_CREATE, WM_DESTROY, WM_KEYDOWN, WM_SETFOCUS, WM_SETFONT, WM_SIZE,
+                WNDCLASSW, WS_CHILD, WS_EX_CLIENTEDGE, WS_HSCROLL, WS_OVERLAPPEDWINDOW, WS_TABSTOP,
+                WS_VISIBLE, WS_VSCROLL,
+            },
+        },
+    },
+};
+
+use crate::{LogExt, PACKAGE_NAME};
+
+/// wide enough for the longest log lines to be scrolled to.
+const LIST_EXTENT: usize = 4000;
+
+thread_local! {
+    /// the lines of the window being created, taken by its list.
+    static LINES: Cell<Vec<String>> = const { Cell::new(Vec::new()) };
+    static LIST: Cell<HWND> = const { Cell::new(HWND(0)) };
+    /// the font of the list, deleted with the window unless it's the stock one.
+    static FONT: Cell<Option<HFONT>> = const { Cell::new(None) };
+}
+
+/// the open window of a list, so a second click brings it to the front instead of opening another
+/// one.
+pub struct Window(AtomicIsize);
+
+impl Window {
+    pub const fn new() -> Self {
+        Self(AtomicIsize::new(0))
+    }
+}
+
+/// shows the lines in a window of their own, scrolled to the last one, without blocking the
+/// caller.
+pub fn show(window: &'static Window, title: &'static str, lines: fn() -> Vec<String>) {
+    let hwnd = HWND(window.0.load(Ordering::Relaxed));
+    if hwnd != HWND(0) {
+        unsafe { SetForegroundWindow(hwnd) };
+        return;
+    }
+    thread::spawn(move || run(&window.0, title, lines()).warn());
+}
+
+fn run(window: &AtomicIsize, title: &str, lines: Vec<String>) -> Result<()> {
+    LINES.with(|cell| cell.set(lines));
+    unsafe {
+        let instance = GetModuleHandleW(PCWSTR::null())?;
+        let class = WNDCLASSW {
+            lpfnWndProc: Some(on_message),
+            hInstance: instance.into(),
+            lpszClassName: w!("vscode-cjk-toggle-terminal-fixer-recent"),
+            ..Default::default()
+        };
+        // note: the class is still registered when the window is opened again.
+        RegisterClassW(&class);
+        let hwnd = CreateWindowExW(
+            Default::default(),
+            class.lpszClassName,
+            &HSTRING::from(format!("{title} - {PACKAGE_NAME}")),
+            WS_OVERLAPPEDWINDOW | WS_VISIBLE,
+            CW_USEDEFAULT,
+            CW_USEDEFAULT,
+            900,
+            500,
+            HWND(0),
+            HMENU(0),
+            instance,
+            None,
+        );
+        ensure!(
+            hwnd != HWND(0),
+            "failed to create the window: {}",
+            windows::core::Error::from_win32()
+        );
+        window.store(hwnd.0, Ordering::Relaxed);
+        SetForegroundWindow(hwnd);
+
+        let mut msg: MSG = mem::zeroed();
+        while GetMessageW(&mut msg, HWND(0), 0, 0).as_bool() {
+            // note: the list has the focus, Escape closes the window like a dialog.
+            if msg.message == WM_KEYDOWN && msg.wParam == WPARAM(VK_ESCAPE.0 as usize) {
+                DestroyWindow(hwnd).warn();
+                continue;
+            }
+            TranslateMessage(&msg);
+            DispatchMessageW(&msg);
+        }
+        window.store(0, Ordering::Relaxed);
+    }
+    Ok(())
+}
+
+unsafe extern "system" fn on_message(
+    hwnd: HWND,
+    msg: u32,
+    wparam: WPARAM,
+    lparam: LPARAM,
+) -> LRESULT {
+    match msg {
+        WM_CREATE => {
+            let list = CreateWindowExW(
+                WS_EX_CLIENTEDGE,
+                w!("LISTBOX"),
+                PCWSTR::null(),
+                WS_CHILD
+                    | WS_VISIBLE
+                    | WS_TABSTOP
+                    | WS_VSCROLL
+                    | WS_HSCROLL
+                    | WINDOW_STYLE(LBS_NOINTEGRALHEIGHT as u32),
+                0,
+                0,
+                0,
+                0,
+                hwnd,
+                HMENU(0),
+                None,
+                None,
+            );
+            LIST.with(|cell| cell.set(list));
+            let font = message_font();
+            FONT.with(|cell| cell.set(font));
+            let font = font.map_or_else(|| GetStockObject(DEFAULT_GUI_FONT).0, |font| font.0);
+            SendMessageW(list, WM_SETFONT, WPARAM(font as usize), LPARAM(0));
+            SendMessageW(list, LB_SETHORIZONTALEXTENT, WPARAM(LIST_EXTENT), LPARAM(0));
+            let lines = LINES.with(Cell::take);
+            for line in &lines {
+                let line = HSTRING::from(line.as_str());
+                SendMessageW(
+                    list,
+                    LB_ADDSTRING,
+                    WPARAM(0),
+                    LPARAM(line.as_ptr() as isize),
+                );
+            }
+            // note: selected rather than only scrolled to, so the arrow keys move on from the latest
+            // line and Narrator reads each one out.
+            SendMessageW(
+                list,
+                LB_SETCURSEL,
+                WPARAM(lines.len().saturating_sub(1)),
+                LPARAM(0),
+            );
+            LRESULT(0)
+        }
+        WM_SETFOCUS => {
+            SetFocus(LIST.with(Cell::get));
+            LRESULT(0)
+        }
+        WM_SIZE => {
+            let (width, height) = (lparam.0 & 0xFFFF, (lparam.0 >> 16) & 0xFFFF);
+            MoveWindow(
+                LIST.with(Cell::get),
+                0,
+                0,
+                width as i32,
+                height as i32,
+                true,
+            )
+            .warn();
+            LRESULT(0)
+        }
+        WM_DESTROY => {
+            if let Some(font) = FONT.with(|cell| cell.take()) {
+                DeleteObject(font);
+            }
+            PostQuitMessage(0);
+            LRESULT(0)
+        }
+        _ => DefWindowProcW(hwnd, msg, wparam, lparam),
+    }
+}
+
+/// the font of message boxes, e.g. Segoe UI, which links to a CJK font for the window titles and
+/// IME names in the events, unlike `DEFAULT_GUI_FONT`, which is the bitmap MS Sans Serif.
+///
+/// note: the list box measures its lines by the font it's given, so they fit the taller CJK
+/// glyphs.
+fn message_font() -> Option<HFONT> {
+    let mut metrics = NONCLIENTMETRICSW {
+        cbSize: mem::size_of::<NONCLIENTMETRICSW>() as u32,
+        ..Default::default()
+    };
+    unsafe {
+        SystemParametersInfoW(
+            SPI_GETNONCLIENTMETRICS,
+            metrics.cbSize,
+            Some(&mut metrics as *mut NONCLIENTMETRICSW as *mut c_void),
+            SYSTEM_PARAMETERS_INFO_UPDATE_FLAGS(0),
+        )
+    }
+    .warn()?;
+    let font = unsafe { CreateFontIndirectW(&metrics.lfMessageFont) };
+    (font.0 != 0).then_some(font)
+}
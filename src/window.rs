@@ -0,0 +1,449 @@
+use std::{
+    ffi::c_void,
+    fmt, mem,
+    path::Path,
+    sync::atomic::{AtomicBool, AtomicIsize, Ordering},
+    time::Duration,
+};
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+#[allow(unused_imports)]
+use tracing::{debug, error, info, trace, warn};
+use windows::Win32::{
+    Foundation::{BOOL, ERROR_TIMEOUT, FALSE, HMODULE, HWND, LPARAM, LRESULT, RECT, TRUE, WPARAM},
+    Graphics::{
+        Dwm::{DwmGetWindowAttribute, DWMWA_CLOAKED},
+        Gdi::{GetMonitorInfoW, MonitorFromWindow, MONITORINFO, MONITOR_DEFAULTTONEAREST},
+    },
+    System::Threading::{AttachThreadInput, GetCurrentThreadId},
+    UI::{
+        Accessibility::{SetWinEventHook, HWINEVENTHOOK},
+        Input::KeyboardAndMouse::GetKeyboardLayout,
+        WindowsAndMessaging::{
+            BringWindowToTop, EnumWindows, GetAncestor, GetClassNameW, GetForegroundWindow,
+            GetWindowRect, GetWindowTextW, GetWindowThreadProcessId, IsHungAppWindow, IsIconic,
+            IsWindow, IsWindowVisible, PostThreadMessageW, SendMessageTimeoutW,
+            SetForegroundWindow, ShowWindow, EVENT_SYSTEM_FOREGROUND, GA_ROOTOWNER,
+            SMTO_ABORTIFHUNG, SMTO_BLOCK, SW_RESTORE, WINEVENT_OUTOFCONTEXT,
+            WINEVENT_SKIPOWNPROCESS, WM_APP, WM_NULL,
+        },
+    },
+};
+
+use crate::{config::Config, desktop, hotkey, launch, procs, quake, LogExt};
+
+/// posted to the message pump with the new foreground window in `wParam`.
+pub const WM_FOREGROUND: u32 = WM_APP + 8;
+
+/// which windows receive the hotkeys when several match.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Policy {
+    /// only the foreground window.
+    #[default]
+    Foreground,
+    /// the foreground window, or else the most recently active VSCode window.
+    LastActive,
+    /// every visible top-level window receiving the hotkeys, e.g. side by side on two monitors.
+    ///
+    /// note: the windows in the background don't see the modifiers the user holds, so the backends
+    /// pressing keys may not reach them.
+    AllVisible,
+}
+
+/// how long a window may take to process a message before it's considered busy.
+const RESPONSE_TIMEOUT: Duration = Duration::from_millis(100);
+
+/// the most recently focused VSCode window, updated by the foreground WinEvent hook.
+static LAST_VSCODE_WINDOW: AtomicIsize = AtomicIsize::new(0);
+
+/// the most recently focused window other than the taskbar's, updated by the foreground WinEvent
+/// hook.
+static LAST_FOREGROUND_WINDOW: AtomicIsize = AtomicIsize::new(0);
+
+/// whether the hotkey reached a hidden window since `take_hidden`.
+static HIDDEN: AtomicBool = AtomicBool::new(false);
+
+/// why a window shows nothing of what's injected into it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Hidden {
+    Minimized,
+    /// on a virtual desktop other than the current one.
+    OtherDesktop,
+    /// e.g. a suspended app.
+    Cloaked,
+}
+
+impl fmt::Display for Hidden {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Minimized => f.write_str("minimized"),
+            Self::OtherDesktop => f.write_str("on another desktop"),
+            Self::Cloaked => f.write_str("cloaked"),
+        }
+    }
+}
+
+/// the classes of the taskbar and the notification area, which take the focus when the tray menu is
+/// opened.
+const SHELL_CLASSES: [&str; 4] = [
+    "Shell_TrayWnd",
+    "Shell_SecondaryTrayWnd",
+    "NotifyIconOverflowWindow",
+    "TopLevelWindowForOverflowXamlIsland",
+];
+
+/// the classes of IME windows that take the foreground while composing, e.g. the candidate window
+/// of the built-in Japanese and Chinese IMEs.
+const IME_CLASSES: [&str; 4] = [
+    "IME",
+    "MSCTFIME UI",
+    "MSCTFIME Composition",
+    "Microsoft.IME.UIManager.CandidateWindow.Host",
+];
+
+/// starts tracking the most recently focused VSCode window and the keyboard layout of the foreground
+/// window.
+///
+/// note: the hook is out-of-context, so the callback runs on the message pump of the calling thread.
+pub fn track_foreground() -> HWINEVENTHOOK {
+    unsafe {
+        SetWinEventHook(
+            EVENT_SYSTEM_FOREGROUND,
+            EVENT_SYSTEM_FOREGROUND,
+            HMODULE(0),
+            Some(on_foreground_changed),
+            0,
+            0,
+            WINEVENT_OUTOFCONTEXT | WINEVENT_SKIPOWNPROCESS,
+        )
+    }
+}
+
+unsafe extern "system" fn on_foreground_changed(
+    _hook: HWINEVENTHOOK,
+    _event: u32,
+    hwnd: HWND,
+    _id_object: i32,
+    _id_child: i32,
+    _id_event_thread: u32,
+    _event_time: u32,
+) {
+    let hwnd = owner_of_ime(hwnd);
+    let hkl = GetKeyboardLayout(GetWindowThreadProcessId(hwnd, None));
+    PostThreadMessageW(
+        GetCurrentThreadId(),
+        hotkey::WM_LAYOUT,
+        WPARAM(0),
+        LPARAM(hkl.0),
+    )
+    .warn();
+    PostThreadMessageW(
+        GetCurrentThreadId(),
+        WM_FOREGROUND,
+        WPARAM(hwnd.0 as usize),
+        LPARAM(0),
+    )
+    .warn();
+
+    if !is_shell_window(hwnd) {
+        LAST_FOREGROUND_WINDOW.store(hwnd.0, Ordering::Relaxed);
+    }
+    if is_vscode_window(hwnd) {
+        LAST_VSCODE_WINDOW.store(hwnd.0, Ordering::Relaxed);
+        // note: the action is left to the message pump, which owns the config.
+        if launch::is_pending() {
+            PostThreadMessageW(
+                GetCurrentThreadId(),
+                launch::WM_LAUNCHED,
+                WPARAM(hwnd.0 as usize),
+                LPARAM(0),
+            )
+            .warn();
+        }
+    }
+}
+
+pub fn title(hwnd: HWND) -> String {
+    let mut buffer = [0u16; 512];
+    let len = unsafe { GetWindowTextW(hwnd, &mut buffer) } as usize;
+    String::from_utf16_lossy(&buffer[..len])
+}
+
+pub fn is_vscode_window(hwnd: HWND) -> bool {
+    if matches!(hwnd, HWND(0)) {
+        return false;
+    }
+
+    let mut buffer = [0u16; 512];
+    let buffer_used_count = unsafe { GetWindowTextW(hwnd, &mut buffer) } as usize;
+    let window_title = &buffer[..buffer_used_count];
+
+    // note: compared as UTF-16, it's on the path of every hotkey and shouldn't allocate.
+    let separator = [b' ', b'-', b' '].map(u16::from);
+    let app_name = match window_title
+        .windows(separator.len())
+        .rposition(|window| window == separator)
+    {
+        Some(i) => &window_title[i + separator.len()..],
+        None => window_title,
+    };
+    let app_name = trim(app_name);
+    ["Visual Studio Code", "VS Code"]
+        .into_iter()
+        .any(|name| name.encode_utf16().eq(app_name.iter().copied()))
+}
+
+fn trim(text: &[u16]) -> &[u16] {
+    let is_space = |unit: &u16| char::from_u32(*unit as u32).is_some_and(char::is_whitespace);
+    let start = text
+        .iter()
+        .position(|unit| !is_space(unit))
+        .unwrap_or(text.len());
+    let end = text
+        .iter()
+        .rposition(|unit| !is_space(unit))
+        .map_or(start, |i| i + 1);
+    &text[start..end]
+}
+
+fn is_shell_window(hwnd: HWND) -> bool {
+    has_class(hwnd, &SHELL_CLASSES)
+}
+
+fn is_ime_window(hwnd: HWND) -> bool {
+    has_class(hwnd, &IME_CLASSES)
+}
+
+fn has_class(hwnd: HWND, classes: &[&str]) -> bool {
+    let mut buffer = [0u16; 64];
+    let len = unsafe { GetClassNameW(hwnd, &mut buffer) } as usize;
+    let class = &buffer[..len];
+    classes
+        .iter()
+        .any(|name| name.encode_utf16().eq(class.iter().copied()))
+}
+
+/// the foreground window, or the one composing if it's an IME window, so the hotkeys pressed while
+/// its candidate window is open still reach VSCode.
+pub fn foreground() -> HWND {
+    owner_of_ime(unsafe { GetForegroundWindow() })
+}
+
+/// the window an IME window belongs to, by its owner, or else the window focused before it, e.g.
+/// for the candidate window of an IME running out of process; any other window as is.
+fn owner_of_ime(hwnd: HWND) -> HWND {
+    if !is_ime_window(hwnd) {
+        return hwnd;
+    }
+    let owner = unsafe { GetAncestor(hwnd, GA_ROOTOWNER) };
+    if owner != HWND(0) && owner != hwnd && !is_ime_window(owner) {
+        debug!("{hwnd:?} is an IME window of {owner:?}");
+        return owner;
+    }
+    match last_foreground_window() {
+        Some(focused) if focused != hwnd => {
+            debug!("{hwnd:?} is an IME window, {focused:?} was focused before");
+            focused
+        }
+        _ => hwnd,
+    }
+}
+
+/// the window focused before the taskbar, e.g. while the tray menu is open.
+///
+/// note: only tracked while the foreground hook is installed.
+pub fn last_foreground_window() -> Option<HWND> {
+    let hwnd = HWND(LAST_FOREGROUND_WINDOW.load(Ordering::Relaxed));
+    (hwnd != HWND(0) && unsafe { IsWindow(hwnd) }.as_bool()).then_some(hwnd)
+}
+
+/// adds the executable of the window to `target_processes` in the config file, keeping the user's
+/// other keys and comments, returns its name.
+pub fn add_target(path: &Path, hwnd: HWND) -> Result<String> {
+    let name = procs::name(procs::of_window(hwnd))
+        .with_context(|| format!("failed to get the executable of {hwnd:?}"))?;
+    let mut processes = Config::load(path)?.target_processes;
+    if !processes
+        .iter()
+        .any(|process| process.eq_ignore_ascii_case(&name))
+    {
+        processes.push(name.clone());
+    }
+    let values = format!("target_processes = {}", toml::Value::try_from(&processes)?);
+    Config::update(path, &values).with_context(|| format!("failed to add {name:?}"))?;
+    Ok(name)
+}
+
+/// whether the hotkeys are sent to the window when it's focused.
+pub fn is_target(config: &Config, hwnd: HWND) -> bool {
+    is_vscode_window(hwnd) || is_target_process(config, hwnd)
+}
+
+fn is_target_process(config: &Config, hwnd: HWND) -> bool {
+    !config.target_processes.is_empty()
+        && procs::name(procs::of_window(hwnd)).is_some_and(|name| {
+            config
+                .target_processes
+                .iter()
+                .any(|target| target.eq_ignore_ascii_case(&name))
+        })
+}
+
+/// whether the window processes its messages, the keys posted to a busy one would arrive much later
+/// and toggle again.
+///
+/// note: Windows considers a window hung after 5 seconds only, so it's asked to process a message.
+pub fn is_responsive(hwnd: HWND) -> bool {
+    unsafe {
+        if IsHungAppWindow(hwnd).as_bool() {
+            return false;
+        }
+        let result = SendMessageTimeoutW(
+            hwnd,
+            WM_NULL,
+            WPARAM(0),
+            LPARAM(0),
+            SMTO_ABORTIFHUNG | SMTO_BLOCK,
+            RESPONSE_TIMEOUT.as_millis() as u32,
+            None,
+        );
+        // note: only a timeout means busy, e.g. UIPI rejects the message to an elevated window.
+        result != LRESULT(0)
+            || windows::core::Error::from_win32().code() != ERROR_TIMEOUT.to_hresult()
+    }
+}
+
+/// note: a closed window has no title anymore, so stale handles are rejected as well.
+pub fn last_vscode_window() -> Option<HWND> {
+    let hwnd = HWND(LAST_VSCODE_WINDOW.load(Ordering::Relaxed));
+    is_vscode_window(hwnd).then_some(hwnd)
+}
+
+/// finds any visible top-level VSCode window, e.g. one that hasn't been focused since we started.
+pub fn find_vscode_window() -> Option<HWND> {
+    unsafe extern "system" fn visit(hwnd: HWND, lparam: LPARAM) -> BOOL {
+        if IsWindowVisible(hwnd).as_bool() && is_vscode_window(hwnd) {
+            *(lparam.0 as *mut HWND) = hwnd;
+            return FALSE;
+        }
+        TRUE
+    }
+
+    let mut found = HWND(0);
+    // note: stopping the enumeration early is reported as an error, so the result is ignored.
+    let _ = unsafe { EnumWindows(Some(visit), LPARAM(&mut found as *mut HWND as isize)) };
+    (found != HWND(0)).then_some(found)
+}
+
+/// whether the window is there but not to be seen, even though it's visible to `IsWindowVisible`.
+pub fn hidden(hwnd: HWND) -> Option<Hidden> {
+    if unsafe { IsIconic(hwnd) }.as_bool() {
+        return Some(Hidden::Minimized);
+    }
+    let mut cloaked = 0u32;
+    unsafe {
+        DwmGetWindowAttribute(
+            hwnd,
+            DWMWA_CLOAKED,
+            &mut cloaked as *mut u32 as *mut c_void,
+            mem::size_of::<u32>() as u32,
+        )
+    }
+    .warn()?;
+    if cloaked == 0 {
+        None
+    } else if desktop::is_elsewhere(hwnd) {
+        Some(Hidden::OtherDesktop)
+    } else {
+        Some(Hidden::Cloaked)
+    }
+}
+
+/// whether the window covers its whole monitor, e.g. VSCode in full screen or zen mode.
+///
+/// note: a maximized window covers the work area only, and its borders reach past the monitor.
+pub fn is_full_screen(hwnd: HWND) -> bool {
+    let mut rect = RECT::default();
+    if unsafe { GetWindowRect(hwnd, &mut rect) }.warn().is_none() {
+        return false;
+    }
+    let mut info = MONITORINFO {
+        cbSize: mem::size_of::<MONITORINFO>() as u32,
+        ..Default::default()
+    };
+    let monitor = unsafe { MonitorFromWindow(hwnd, MONITOR_DEFAULTTONEAREST) };
+    unsafe { GetMonitorInfoW(monitor, &mut info) }.as_bool() && rect == info.rcMonitor
+}
+
+/// notes that the hotkey reached a hidden window and was skipped, e.g. to explain it once.
+pub fn report_hidden() {
+    HIDDEN.store(true, Ordering::Relaxed);
+}
+
+/// whether the hotkey reached a hidden window since the last call.
+pub fn take_hidden() -> bool {
+    HIDDEN.swap(false, Ordering::Relaxed)
+}
+
+/// every visible top-level window receiving the hotkeys, in z-order.
+///
+/// note: the minimized and cloaked ones are left out, nothing could be seen happening in them.
+pub fn all_visible(config: &Config) -> Vec<HWND> {
+    unsafe extern "system" fn visit(hwnd: HWND, lparam: LPARAM) -> BOOL {
+        if IsWindowVisible(hwnd).as_bool() && hidden(hwnd).is_none() {
+            (*(lparam.0 as *mut Vec<HWND>)).push(hwnd);
+        }
+        TRUE
+    }
+
+    let mut windows = Vec::new();
+    unsafe { EnumWindows(Some(visit), LPARAM(&mut windows as *mut Vec<HWND> as isize)) }.warn();
+    windows.retain(|&hwnd| is_target(config, hwnd));
+    windows
+}
+
+/// picks the window the toggle should be sent to, activating it first if configured.
+pub fn target(config: &Config) -> Option<HWND> {
+    let h_active_wnd = foreground();
+    if is_target(config, h_active_wnd) {
+        return Some(h_active_wnd);
+    }
+
+    let h_last_wnd = last_vscode_window().or_else(find_vscode_window)?;
+    if config.bring_to_front {
+        activate(h_last_wnd).warn()?;
+        if config.quake_mode {
+            quake::dock(h_last_wnd, config.quake_height_percent).warn();
+        }
+        return Some(h_last_wnd);
+    }
+    (config.target_windows == Policy::LastActive).then_some(h_last_wnd)
+}
+
+/// brings the window to the foreground.
+///
+/// note: we attach to the input queue of the current foreground thread so that the foreground lock
+/// doesn't reject `SetForegroundWindow` and merely flash the taskbar button.
+pub fn activate(hwnd: HWND) -> Result<()> {
+    unsafe {
+        if IsIconic(hwnd).as_bool() {
+            ShowWindow(hwnd, SW_RESTORE);
+        }
+
+        let tid = GetCurrentThreadId();
+        let foreground_tid = GetWindowThreadProcessId(GetForegroundWindow(), None);
+        let attached = foreground_tid != 0
+            && foreground_tid != tid
+            && AttachThreadInput(tid, foreground_tid, TRUE).as_bool();
+
+        let result = SetForegroundWindow(hwnd).ok();
+        BringWindowToTop(hwnd).warn();
+
+        if attached {
+            AttachThreadInput(tid, foreground_tid, FALSE);
+        }
+        Ok(result?)
+    }
+}
@@ -0,0 +1,52 @@
+use std::path::Path;
+
+use anyhow::{Context, Result};
+use windows::Win32::Foundation::HWND;
+
+use crate::{config::Config, window};
+
+/// the workspace of a VSCode window, the segment of the title before the app name, e.g. `crate` of
+/// `main.rs - crate - Visual Studio Code`.
+///
+/// note: VSCode's default `window.title` is assumed, a custom one may name something else, as does
+/// a window without a folder, which is named after its file.
+pub fn name(hwnd: HWND) -> Option<String> {
+    if !window::is_vscode_window(hwnd) {
+        return None;
+    }
+    let title = window::title(hwnd);
+    let name = title.rsplit(" - ").nth(1)?.trim();
+    (!name.is_empty()).then(|| name.to_owned())
+}
+
+/// whether the fixer is disabled for the workspace of the window.
+pub fn is_disabled(config: &Config, hwnd: HWND) -> bool {
+    !config.disabled_workspaces.is_empty()
+        && name(hwnd).is_some_and(|name| {
+            config
+                .disabled_workspaces
+                .iter()
+                .any(|disabled| disabled.eq_ignore_ascii_case(&name))
+        })
+}
+
+/// the workspace of the most recently focused VSCode window, e.g. while the tray menu has the focus.
+pub fn last_active() -> Option<String> {
+    window::last_vscode_window()
+        .or_else(window::find_vscode_window)
+        .and_then(name)
+}
+
+/// adds the workspace to `disabled_workspaces` in the config file, keeping the user's other keys and
+/// comments.
+pub fn disable(path: &Path, name: &str) -> Result<()> {
+    let mut workspaces = Config::load(path)?.disabled_workspaces;
+    if !workspaces.iter().any(|workspace| workspace == name) {
+        workspaces.push(name.to_owned());
+    }
+    let values = format!(
+        "disabled_workspaces = {}",
+        toml::Value::try_from(&workspaces)?
+    );
+    Config::update(path, &values).with_context(|| format!("failed to disable {name:?}"))
+}